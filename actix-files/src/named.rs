@@ -6,7 +6,7 @@ use std::{
 };
 
 use actix_web::{
-    body::{self, BoxBody, SizedStream},
+    body::{BoxBody, SizedStream},
     dev::{
         self, AppService, HttpServiceFactory, ResourceDef, Service, ServiceFactory, ServiceRequest,
         ServiceResponse,
@@ -16,9 +16,9 @@ use actix_web::{
             self, Charset, ContentDisposition, ContentEncoding, DispositionParam, DispositionType,
             ExtendedValue, HeaderValue,
         },
-        StatusCode,
+        Precondition, Preconditions, StatusCode,
     },
-    Error, HttpMessage, HttpRequest, HttpResponse, Responder,
+    Error, HttpRequest, HttpResponse, Responder,
 };
 use bitflags::bitflags;
 use derive_more::derive::{Deref, DerefMut};
@@ -458,39 +458,19 @@ impl NamedFile {
         };
 
         // check preconditions
-        let precondition_failed = if !any_match(etag.as_ref(), req) {
-            true
-        } else if let (Some(ref m), Some(header::IfUnmodifiedSince(ref since))) =
-            (last_modified, req.get_header())
-        {
-            let t1: SystemTime = (*m).into();
-            let t2: SystemTime = (*since).into();
-
-            match (t1.duration_since(UNIX_EPOCH), t2.duration_since(UNIX_EPOCH)) {
-                (Ok(t1), Ok(t2)) => t1.as_secs() > t2.as_secs(),
-                _ => false,
-            }
-        } else {
-            false
-        };
+        let mut preconditions = Preconditions::new();
 
-        // check last modified
-        let not_modified = if !none_match(etag.as_ref(), req) {
-            true
-        } else if req.headers().contains_key(header::IF_NONE_MATCH) {
-            false
-        } else if let (Some(ref m), Some(header::IfModifiedSince(ref since))) =
-            (last_modified, req.get_header())
-        {
-            let t1: SystemTime = (*m).into();
-            let t2: SystemTime = (*since).into();
-
-            match (t1.duration_since(UNIX_EPOCH), t2.duration_since(UNIX_EPOCH)) {
-                (Ok(t1), Ok(t2)) => t1.as_secs() <= t2.as_secs(),
-                _ => false,
-            }
-        } else {
-            false
+        if let Some(etag) = &etag {
+            preconditions = preconditions.etag(etag.clone());
+        }
+
+        if let Some(lm) = last_modified {
+            preconditions = preconditions.last_modified(lm);
+        }
+
+        let honor_range = match preconditions.evaluate(req) {
+            Precondition::Proceed { honor_range } => honor_range,
+            Precondition::Respond(res) => return res,
         };
 
         let mut res = HttpResponse::build(self.status_code);
@@ -528,7 +508,7 @@ impl NamedFile {
         let mut offset = 0;
 
         // check for range header
-        if let Some(ranges) = req.headers().get(header::RANGE) {
+        if let Some(ranges) = req.headers().get(header::RANGE).filter(|_| honor_range) {
             if let Ok(ranges_header) = ranges.to_str() {
                 if let Ok(ranges) = HttpRange::parse(ranges_header, length) {
                     length = ranges[0].length;
@@ -568,15 +548,6 @@ impl NamedFile {
             };
         };
 
-        if precondition_failed {
-            return res.status(StatusCode::PRECONDITION_FAILED).finish();
-        } else if not_modified {
-            return res
-                .status(StatusCode::NOT_MODIFIED)
-                .body(body::None::new())
-                .map_into_boxed_body();
-        }
-
         let reader = chunked::new_chunked_read(length, offset, self.file);
 
         if offset != 0 || length != self.md.len() {
@@ -587,46 +558,6 @@ impl NamedFile {
     }
 }
 
-/// Returns true if `req` has no `If-Match` header or one which matches `etag`.
-fn any_match(etag: Option<&header::EntityTag>, req: &HttpRequest) -> bool {
-    match req.get_header::<header::IfMatch>() {
-        None | Some(header::IfMatch::Any) => true,
-
-        Some(header::IfMatch::Items(ref items)) => {
-            if let Some(some_etag) = etag {
-                for item in items {
-                    if item.strong_eq(some_etag) {
-                        return true;
-                    }
-                }
-            }
-
-            false
-        }
-    }
-}
-
-/// Returns true if `req` doesn't have an `If-None-Match` header matching `req`.
-fn none_match(etag: Option<&header::EntityTag>, req: &HttpRequest) -> bool {
-    match req.get_header::<header::IfNoneMatch>() {
-        Some(header::IfNoneMatch::Any) => false,
-
-        Some(header::IfNoneMatch::Items(ref items)) => {
-            if let Some(some_etag) = etag {
-                for item in items {
-                    if item.weak_eq(some_etag) {
-                        return false;
-                    }
-                }
-            }
-
-            true
-        }
-
-        None => true,
-    }
-}
-
 impl Responder for NamedFile {
     type Body = BoxBody;
 
@@ -23,13 +23,80 @@ use crate::{
 
 const MAX_CHUNK_SIZE_DECODE_IN_PLACE: usize = 2049;
 
+/// Limits applied by [`Decoder`] to decompressed output.
+///
+/// Limits protect against "decompression bombs": small compressed payloads that expand to an
+/// excessive size. When a limit is exceeded, the decoder yields a
+/// [`PayloadError::DecompressionLimit`] error. Limits are not applied to unencoded payloads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecoderConfig {
+    max_size: Option<usize>,
+    max_ratio: Option<u32>,
+}
+
+impl DecoderConfig {
+    /// Constructs a config with no limits.
+    pub const fn new() -> Self {
+        Self {
+            max_size: None,
+            max_ratio: None,
+        }
+    }
+
+    /// Sets the maximum total size, in bytes, of decompressed output.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets the maximum ratio of decompressed output to compressed input.
+    ///
+    /// The ratio is checked against the totals read and decompressed so far, so that output is
+    /// limited as data is received.
+    pub fn max_ratio(mut self, max_ratio: u32) -> Self {
+        self.max_ratio = Some(max_ratio);
+        self
+    }
+
+    /// Returns the maximum decompressed size, if set.
+    pub fn get_max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    /// Returns the maximum compression ratio, if set.
+    pub fn get_max_ratio(&self) -> Option<u32> {
+        self.max_ratio
+    }
+
+    /// Returns how many more bytes may be decompressed, given the totals so far.
+    fn budget(&self, read: u64, decoded: u64) -> usize {
+        let size_budget = self
+            .max_size
+            .map_or(u64::MAX, |max| (max as u64).saturating_sub(decoded));
+
+        let ratio_budget = self.max_ratio.map_or(u64::MAX, |ratio| {
+            read.saturating_mul(ratio as u64).saturating_sub(decoded)
+        });
+
+        size_budget
+            .min(ratio_budget)
+            .try_into()
+            .unwrap_or(usize::MAX)
+    }
+}
+
 pin_project_lite::pin_project! {
     pub struct Decoder<S> {
         decoder: Option<ContentDecoder>,
         #[pin]
         stream: S,
         eof: bool,
-        fut: Option<JoinHandle<Result<(Option<Bytes>, ContentDecoder), io::Error>>>,
+        fut: Option<JoinHandle<Result<(Option<Bytes>, ContentDecoder), PayloadError>>>,
+        config: DecoderConfig,
+        // total compressed bytes read from stream
+        read: u64,
+        // total decompressed bytes produced
+        decoded: u64,
     }
 }
 
@@ -71,6 +138,9 @@ where
             stream,
             fut: None,
             eof: false,
+            config: DecoderConfig::new(),
+            read: 0,
+            decoded: 0,
         }
     }
 
@@ -86,6 +156,12 @@ where
 
         Self::new(stream, encoding)
     }
+
+    /// Sets limits on decompressed output.
+    pub fn with_config(mut self, config: DecoderConfig) -> Self {
+        self.config = config;
+        self
+    }
 }
 
 impl<S> Stream for Decoder<S>
@@ -110,6 +186,7 @@ where
                 this.fut.take();
 
                 if let Some(chunk) = chunk {
+                    *this.decoded += chunk.len() as u64;
                     return Poll::Ready(Some(Ok(chunk)));
                 }
             }
@@ -123,16 +200,20 @@ where
 
                 Some(Ok(chunk)) => {
                     if let Some(mut decoder) = this.decoder.take() {
+                        *this.read += chunk.len() as u64;
+                        let budget = this.config.budget(*this.read, *this.decoded);
+
                         if chunk.len() < MAX_CHUNK_SIZE_DECODE_IN_PLACE {
-                            let chunk = decoder.feed_data(chunk)?;
+                            let chunk = decoder.feed_data_limited(chunk, budget)?;
                             *this.decoder = Some(decoder);
 
                             if let Some(chunk) = chunk {
+                                *this.decoded += chunk.len() as u64;
                                 return Poll::Ready(Some(Ok(chunk)));
                             }
                         } else {
                             *this.fut = Some(spawn_blocking(move || {
-                                let chunk = decoder.feed_data(chunk)?;
+                                let chunk = decoder.feed_data_limited(chunk, budget)?;
                                 Ok((chunk, decoder))
                             }));
                         }
@@ -147,10 +228,12 @@ where
                     *this.eof = true;
 
                     return if let Some(mut decoder) = this.decoder.take() {
-                        match decoder.feed_eof() {
+                        let budget = this.config.budget(*this.read, *this.decoded);
+
+                        match decoder.feed_eof_limited(budget) {
                             Ok(Some(res)) => Poll::Ready(Some(Ok(res))),
                             Ok(None) => Poll::Ready(None),
-                            Err(err) => Poll::Ready(Some(Err(err.into()))),
+                            Err(err) => Poll::Ready(Some(Err(err))),
                         }
                     } else {
                         Poll::Ready(None)
@@ -178,6 +261,52 @@ enum ContentDecoder {
 }
 
 impl ContentDecoder {
+    fn writer_mut(&mut self) -> &mut Writer {
+        match self {
+            #[cfg(feature = "compress-brotli")]
+            ContentDecoder::Brotli(ref mut decoder) => decoder.get_mut(),
+
+            #[cfg(feature = "compress-gzip")]
+            ContentDecoder::Gzip(ref mut decoder) => decoder.get_mut(),
+
+            #[cfg(feature = "compress-gzip")]
+            ContentDecoder::Deflate(ref mut decoder) => decoder.get_mut(),
+
+            #[cfg(feature = "compress-zstd")]
+            ContentDecoder::Zstd(ref mut decoder) => decoder.get_mut(),
+        }
+    }
+
+    /// Sets the output limit, runs `f`, then maps failures caused by the limit to
+    /// [`PayloadError::DecompressionLimit`].
+    fn limited<T>(
+        &mut self,
+        limit: usize,
+        f: impl FnOnce(&mut Self) -> io::Result<T>,
+    ) -> Result<T, PayloadError> {
+        self.writer_mut().limit = limit;
+
+        f(self).map_err(|err| {
+            if self.writer_mut().exceeded {
+                PayloadError::DecompressionLimit
+            } else {
+                err.into()
+            }
+        })
+    }
+
+    fn feed_data_limited(
+        &mut self,
+        data: Bytes,
+        limit: usize,
+    ) -> Result<Option<Bytes>, PayloadError> {
+        self.limited(limit, |decoder| decoder.feed_data(data))
+    }
+
+    fn feed_eof_limited(&mut self, limit: usize) -> Result<Option<Bytes>, PayloadError> {
+        self.limited(limit, |decoder| decoder.feed_eof())
+    }
+
     fn feed_eof(&mut self) -> io::Result<Option<Bytes>> {
         match self {
             #[cfg(feature = "compress-brotli")]
@@ -300,3 +429,69 @@ impl ContentDecoder {
         }
     }
 }
+
+#[cfg(feature = "compress-gzip")]
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, StreamExt as _};
+
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Bytes {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(data).unwrap();
+        Bytes::from(encoder.finish().unwrap())
+    }
+
+    async fn decode(body: Bytes, config: DecoderConfig) -> Result<usize, PayloadError> {
+        let stream = stream::iter(
+            body.chunks(512)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect::<Vec<_>>(),
+        );
+
+        let mut decoder = Decoder::new(stream, ContentEncoding::Gzip).with_config(config);
+        let mut len = 0;
+
+        while let Some(chunk) = decoder.next().await {
+            len += chunk?.len();
+        }
+
+        Ok(len)
+    }
+
+    #[actix_rt::test]
+    async fn decompression_limits() {
+        let bomb = gzip(&[0; 1024 * 1024]);
+        assert!(bomb.len() < 4096);
+
+        assert_eq!(
+            decode(bomb.clone(), DecoderConfig::new()).await.unwrap(),
+            1024 * 1024
+        );
+
+        assert_eq!(
+            decode(bomb.clone(), DecoderConfig::new().max_size(1024 * 1024))
+                .await
+                .unwrap(),
+            1024 * 1024
+        );
+
+        let err = decode(bomb.clone(), DecoderConfig::new().max_size(64 * 1024))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PayloadError::DecompressionLimit));
+
+        let err = decode(bomb, DecoderConfig::new().max_ratio(100))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PayloadError::DecompressionLimit));
+
+        // typical payloads are well within ratio limits
+        let text = "hello world, ".repeat(100);
+        let len = decode(gzip(text.as_bytes()), DecoderConfig::new().max_ratio(100))
+            .await
+            .unwrap();
+        assert_eq!(len, text.len());
+    }
+}
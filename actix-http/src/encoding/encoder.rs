@@ -28,6 +28,62 @@ use crate::{
 
 const MAX_CHUNK_SIZE_ENCODE_IN_PLACE: usize = 1024;
 
+/// Compression levels used by [`Encoder`].
+///
+/// Higher levels trade encoding speed for smaller output. Levels are clamped to the range supported
+/// by each algorithm: 0–9 for gzip and deflate, 0–11 for brotli, and 1–22 for zstd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderConfig {
+    deflate: u32,
+    gzip: u32,
+    brotli: u32,
+    zstd: u32,
+}
+
+impl EncoderConfig {
+    /// Constructs a config with default levels, which favor speed over compression ratio.
+    pub const fn new() -> Self {
+        Self {
+            deflate: 1,
+            gzip: 1,
+            brotli: 3,
+            zstd: 3,
+        }
+    }
+
+    /// Sets the compression level used for `encoding`.
+    ///
+    /// Has no effect for the identity encoding.
+    pub fn level(mut self, encoding: ContentEncoding, level: u32) -> Self {
+        match encoding {
+            ContentEncoding::Deflate => self.deflate = level.min(9),
+            ContentEncoding::Gzip => self.gzip = level.min(9),
+            ContentEncoding::Brotli => self.brotli = level.min(11),
+            ContentEncoding::Zstd => self.zstd = level.clamp(1, 22),
+            _ => {}
+        }
+
+        self
+    }
+
+    /// Returns the compression level used for `encoding`, or `None` for the identity encoding.
+    pub fn get_level(&self, encoding: ContentEncoding) -> Option<u32> {
+        match encoding {
+            ContentEncoding::Deflate => Some(self.deflate),
+            ContentEncoding::Gzip => Some(self.gzip),
+            ContentEncoding::Brotli => Some(self.brotli),
+            ContentEncoding::Zstd => Some(self.zstd),
+            _ => None,
+        }
+    }
+}
+
+impl Default for EncoderConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pin_project! {
     pub struct Encoder<B> {
         #[pin]
@@ -59,7 +115,18 @@ impl<B: MessageBody> Encoder<B> {
         }
     }
 
+    /// Wraps a response body, compressing it with `encoding` using default compression levels.
     pub fn response(encoding: ContentEncoding, head: &mut ResponseHead, body: B) -> Self {
+        Self::response_with_config(encoding, EncoderConfig::default(), head, body)
+    }
+
+    /// Wraps a response body, compressing it with `encoding` using the levels in `config`.
+    pub fn response_with_config(
+        encoding: ContentEncoding,
+        config: EncoderConfig,
+        head: &mut ResponseHead,
+        body: B,
+    ) -> Self {
         // no need to compress empty bodies
         match body.size() {
             BodySize::None => return Self::none(),
@@ -79,7 +146,7 @@ impl<B: MessageBody> Encoder<B> {
 
         if should_encode {
             // wrap body only if encoder is feature-enabled
-            if let Some(enc) = ContentEncoder::select(encoding) {
+            if let Some(enc) = ContentEncoder::select(encoding, config) {
                 update_head(encoding, head);
 
                 return Encoder {
@@ -288,26 +355,29 @@ enum ContentEncoder {
 }
 
 impl ContentEncoder {
-    fn select(encoding: ContentEncoding) -> Option<Self> {
+    #[allow(unused_variables)] // config is only unused when no compress features are enabled
+    fn select(encoding: ContentEncoding, config: EncoderConfig) -> Option<Self> {
         match encoding {
             #[cfg(feature = "compress-gzip")]
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate2::Compression::new(config.deflate),
             ))),
 
             #[cfg(feature = "compress-gzip")]
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate2::Compression::new(config.gzip),
             ))),
 
             #[cfg(feature = "compress-brotli")]
-            ContentEncoding::Brotli => Some(ContentEncoder::Brotli(new_brotli_compressor())),
+            ContentEncoding::Brotli => {
+                Some(ContentEncoder::Brotli(new_brotli_compressor(config.brotli)))
+            }
 
             #[cfg(feature = "compress-zstd")]
             ContentEncoding::Zstd => {
-                let encoder = ZstdEncoder::new(Writer::new(), 3).ok()?;
+                let encoder = ZstdEncoder::new(Writer::new(), config.zstd as i32).ok()?;
                 Some(ContentEncoder::Zstd(encoder))
             }
 
@@ -402,11 +472,11 @@ impl ContentEncoder {
 }
 
 #[cfg(feature = "compress-brotli")]
-fn new_brotli_compressor() -> Box<brotli::CompressorWriter<Writer>> {
+fn new_brotli_compressor(quality: u32) -> Box<brotli::CompressorWriter<Writer>> {
     Box::new(brotli::CompressorWriter::new(
         Writer::new(),
         32 * 1024, // 32 KiB buffer
-        3,         // BROTLI_PARAM_QUALITY
+        quality,   // BROTLI_PARAM_QUALITY
         22,        // BROTLI_PARAM_LGWIN
     ))
 }
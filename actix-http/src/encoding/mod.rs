@@ -7,19 +7,30 @@ use bytes::{Bytes, BytesMut};
 mod decoder;
 mod encoder;
 
-pub use self::{decoder::Decoder, encoder::Encoder};
+pub use self::{
+    decoder::{Decoder, DecoderConfig},
+    encoder::{Encoder, EncoderConfig},
+};
 
 /// Special-purpose writer for streaming (de-)compression.
 ///
 /// Pre-allocates 8KiB of capacity.
 struct Writer {
     buf: BytesMut,
+
+    /// Maximum number of buffered bytes; writes that would exceed it fail.
+    limit: usize,
+
+    /// Set when a write has failed due to the limit.
+    exceeded: bool,
 }
 
 impl Writer {
     fn new() -> Writer {
         Writer {
             buf: BytesMut::with_capacity(8192),
+            limit: usize::MAX,
+            exceeded: false,
         }
     }
 
@@ -30,6 +41,11 @@ impl Writer {
 
 impl io::Write for Writer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() > self.limit.saturating_sub(self.buf.len()) {
+            self.exceeded = true;
+            return Err(io::Error::other("decompressed payload exceeded limit"));
+        }
+
         self.buf.extend_from_slice(buf);
         Ok(buf.len())
     }
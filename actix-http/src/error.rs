@@ -271,6 +271,10 @@ pub enum PayloadError {
     #[display("payload length is unknown")]
     UnknownLength,
 
+    /// Decompressed payload exceeded the configured size or compression ratio limit.
+    #[display("decompressed payload exceeded limit")]
+    DecompressionLimit,
+
     /// HTTP/2 payload error.
     #[cfg(feature = "http2")]
     #[display("{}", _0)]
@@ -289,6 +293,7 @@ impl std::error::Error for PayloadError {
             PayloadError::EncodingCorrupted => None,
             PayloadError::Overflow => None,
             PayloadError::UnknownLength => None,
+            PayloadError::DecompressionLimit => None,
             #[cfg(feature = "http2")]
             PayloadError::Http2Payload(err) => Some(err),
             PayloadError::Io(err) => Some(err),
@@ -0,0 +1,403 @@
+//! W3C Trace Context propagation.
+//!
+//! Implements parsing and formatting of the `traceparent` and `tracestate` headers defined by the
+//! [W3C Trace Context] specification, along with a notion of the "current" trace context so that
+//! server and client middleware can cooperate to propagate traces across services.
+//!
+//! [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher as _, Hasher as _},
+};
+
+use crate::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// The `traceparent` header name.
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+
+/// The `tracestate` header name.
+pub const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+
+/// Trace flag indicating that the caller may have recorded trace data.
+const FLAG_SAMPLED: u8 = 0x01;
+
+/// A 16-byte trace identifier, formatted as 32 lowercase hex characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceId([u8; 16]);
+
+impl TraceId {
+    /// Generates a random, non-zero trace ID.
+    ///
+    /// IDs are unique but not unpredictable, so they must not be used as secrets.
+    pub fn random() -> Self {
+        let (hi, lo) = random_u64_pair();
+
+        let mut id = [0; 16];
+        id[..8].copy_from_slice(&hi.to_be_bytes());
+        id[8..].copy_from_slice(&lo.to_be_bytes());
+
+        Self(id)
+    }
+
+    /// Returns the raw bytes of the trace ID.
+    pub fn to_bytes(self) -> [u8; 16] {
+        self.0
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+/// An 8-byte span identifier, formatted as 16 lowercase hex characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpanId([u8; 8]);
+
+impl SpanId {
+    /// Generates a random, non-zero span ID.
+    ///
+    /// IDs are unique but not unpredictable, so they must not be used as secrets.
+    pub fn random() -> Self {
+        let (id, _) = random_u64_pair();
+        Self(id.to_be_bytes())
+    }
+
+    /// Returns the raw bytes of the span ID.
+    pub fn to_bytes(self) -> [u8; 8] {
+        self.0
+    }
+}
+
+impl fmt::Display for SpanId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+
+    Ok(())
+}
+
+/// Identity of a span within a distributed trace, as propagated between services.
+///
+/// The [`Display`](fmt::Display) implementation produces a `traceparent` header value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: TraceId,
+    span_id: SpanId,
+    flags: u8,
+    state: Option<HeaderValue>,
+}
+
+impl TraceContext {
+    /// Constructs a sampled context that starts a new trace.
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: TraceId::random(),
+            span_id: SpanId::random(),
+            flags: FLAG_SAMPLED,
+            state: None,
+        }
+    }
+
+    /// Constructs a context for a new span within the same trace as `self`.
+    ///
+    /// The trace ID, flags and trace state are kept; only the span ID changes.
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: SpanId::random(),
+            ..self.clone()
+        }
+    }
+
+    /// Parses the `traceparent` and `tracestate` headers from `headers`.
+    ///
+    /// Returns `None` if the `traceparent` header is missing or invalid, in which case the
+    /// `tracestate` header must also be ignored.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut traceparent = headers.get_all(TRACEPARENT);
+        let parent = traceparent.next()?;
+
+        // multiple traceparent headers are invalid
+        if traceparent.next().is_some() {
+            return None;
+        }
+
+        let mut ctx = Self::parse_traceparent(parent.as_bytes())?;
+
+        let state = headers
+            .get_all(TRACESTATE)
+            .filter_map(|val| val.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        if !state.is_empty() {
+            ctx.state = HeaderValue::from_str(&state).ok();
+        }
+
+        Some(ctx)
+    }
+
+    fn parse_traceparent(val: &[u8]) -> Option<Self> {
+        if val.len() < 55 {
+            return None;
+        }
+
+        let version = parse_hex::<1>(&val[0..2])?;
+
+        match version[0] {
+            // version 255 is forbidden
+            0xff => return None,
+
+            // version 00 has an exact length
+            0x00 if val.len() != 55 => return None,
+
+            // future versions may append fields, but must keep the known prefix
+            _ if val.len() > 55 && val[55] != b'-' => return None,
+
+            _ => {}
+        }
+
+        if val[2] != b'-' || val[35] != b'-' || val[52] != b'-' {
+            return None;
+        }
+
+        let trace_id = parse_hex::<16>(&val[3..35])?;
+        let span_id = parse_hex::<8>(&val[36..52])?;
+        let flags = parse_hex::<1>(&val[53..55])?[0];
+
+        // all-zero IDs are invalid
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: TraceId(trace_id),
+            span_id: SpanId(span_id),
+            flags,
+            state: None,
+        })
+    }
+
+    /// Returns the trace ID.
+    pub fn trace_id(&self) -> TraceId {
+        self.trace_id
+    }
+
+    /// Returns the span ID.
+    pub fn span_id(&self) -> SpanId {
+        self.span_id
+    }
+
+    /// Returns true if the sampled flag is set.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+
+    /// Returns the vendor-specific trace state, if any.
+    pub fn trace_state(&self) -> Option<&HeaderValue> {
+        self.state.as_ref()
+    }
+
+    /// Inserts `traceparent` and (if present) `tracestate` headers into `headers`.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        let traceparent = HeaderValue::from_str(&self.to_string())
+            .expect("traceparent should always be a valid header value");
+        headers.insert(TRACEPARENT, traceparent);
+
+        match &self.state {
+            Some(state) => {
+                headers.insert(TRACESTATE, state.clone());
+            }
+            None => {
+                headers.remove(TRACESTATE);
+            }
+        }
+    }
+
+    /// Returns the trace context of the span currently being processed on this thread, if any.
+    ///
+    /// This is set by server tracing middleware while request handlers are running, and read by
+    /// client middleware to propagate the trace on outgoing requests.
+    pub fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Runs `f` with `self` set as the [current](Self::current) trace context.
+    ///
+    /// The previous context is restored when `f` returns or panics.
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<TraceContext>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let prev = self.0.take();
+                CURRENT.with(|current| *current.borrow_mut() = prev);
+            }
+        }
+
+        let prev = CURRENT.with(|current| current.borrow_mut().replace(self.clone()));
+        let _restore = Restore(prev);
+
+        f()
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceContext>> = const { RefCell::new(None) };
+}
+
+fn parse_hex<const N: usize>(src: &[u8]) -> Option<[u8; N]> {
+    fn nibble(c: u8) -> Option<u8> {
+        // uppercase hex is not allowed
+        match c {
+            b'0'..=b'9' => Some(c - b'0'),
+            b'a'..=b'f' => Some(c - b'a' + 10),
+            _ => None,
+        }
+    }
+
+    if src.len() != N * 2 {
+        return None;
+    }
+
+    let mut out = [0; N];
+
+    for (byte, pair) in out.iter_mut().zip(src.chunks_exact(2)) {
+        *byte = (nibble(pair[0])? << 4) | nibble(pair[1])?;
+    }
+
+    Some(out)
+}
+
+/// Returns a pair of non-zero pseudo-random numbers.
+///
+/// IDs only need to be unique, so hashing a counter with a randomly-keyed hasher is sufficient.
+fn random_u64_pair() -> (u64, u64) {
+    thread_local! {
+        static STATE: (RandomState, Cell<u64>) = (RandomState::new(), Cell::new(0));
+    }
+
+    STATE.with(|(state, counter)| {
+        let count = counter.get().wrapping_add(1);
+        counter.set(count);
+
+        let mut hi = state.build_hasher();
+        hi.write_u64(count);
+        let mut lo = state.build_hasher();
+        lo.write_u64(!count);
+
+        (hi.finish().max(1), lo.finish().max(1))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    fn headers(pairs: &[(HeaderName, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, val)| (name.clone(), HeaderValue::from_str(val).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_and_format() {
+        let ctx = TraceContext::from_headers(&headers(&[
+            (TRACEPARENT, PARENT),
+            (TRACESTATE, "congo=t61rcWkgMzE"),
+            (TRACESTATE, "rojo=00f067aa0ba902b7"),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            ctx.trace_id().to_string(),
+            "0af7651916cd43dd8448eb211c80319c"
+        );
+        assert_eq!(ctx.span_id().to_string(), "b7ad6b7169203331");
+        assert!(ctx.is_sampled());
+        assert_eq!(
+            ctx.trace_state().unwrap(),
+            "congo=t61rcWkgMzE,rojo=00f067aa0ba902b7"
+        );
+        assert_eq!(ctx.to_string(), PARENT);
+
+        let child = ctx.child();
+        assert_eq!(child.trace_id(), ctx.trace_id());
+        assert_ne!(child.span_id(), ctx.span_id());
+
+        let mut out = HeaderMap::new();
+        child.inject(&mut out);
+        assert_eq!(
+            TraceContext::from_headers(&out).unwrap().span_id(),
+            child.span_id()
+        );
+    }
+
+    #[test]
+    fn invalid_traceparent() {
+        for val in [
+            "",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra",
+            "00_0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        ] {
+            let headers = headers(&[(TRACEPARENT, val)]);
+            assert!(TraceContext::from_headers(&headers).is_none(), "{val}");
+        }
+
+        // future versions may have extra fields
+        let headers = headers(&[(
+            TRACEPARENT,
+            "01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00-extra",
+        )]);
+        let ctx = TraceContext::from_headers(&headers).unwrap();
+        assert!(!ctx.is_sampled());
+    }
+
+    #[test]
+    fn current_scope() {
+        assert!(TraceContext::current().is_none());
+
+        let ctx = TraceContext::new_root();
+        let child = ctx.child();
+
+        ctx.scope(|| {
+            assert_eq!(TraceContext::current().as_ref(), Some(&ctx));
+
+            child.scope(|| {
+                assert_eq!(TraceContext::current().as_ref(), Some(&child));
+            });
+
+            assert_eq!(TraceContext::current().as_ref(), Some(&ctx));
+        });
+
+        assert!(TraceContext::current().is_none());
+    }
+}
@@ -21,7 +21,7 @@ pub use actix_service::{
 pub use crate::handler::Handler;
 pub use crate::{
     config::{AppConfig, AppService},
-    info::{ConnectionInfo, PeerAddr},
+    info::{ConnectionInfo, PeerAddr, TrustedProxies},
     rmap::ResourceMap,
     service::{HttpServiceFactory, ServiceRequest, ServiceResponse, WebService},
     types::{JsonBody, Readlines, UrlEncoded},
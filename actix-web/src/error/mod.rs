@@ -17,10 +17,13 @@ use crate::http::StatusCode;
 mod error;
 mod internal;
 mod macros;
+mod problem_details;
 mod response_error;
 
 pub(crate) use self::macros::{downcast_dyn, downcast_get_type_id};
-pub use self::{error::Error, internal::*, response_error::ResponseError};
+pub use self::{
+    error::Error, internal::*, problem_details::ProblemDetails, response_error::ResponseError,
+};
 
 /// A convenience [`Result`](std::result::Result) for Actix Web operations.
 ///
@@ -0,0 +1,309 @@
+use std::fmt;
+
+use serde::ser::{Serialize, SerializeMap as _, Serializer};
+use serde_json::{Map, Value};
+
+use super::{
+    Error, InternalError, JsonPayloadError, PathError, QueryPayloadError, ResponseError,
+    UrlencodedError,
+};
+use crate::{
+    body::BoxBody,
+    http::{
+        header::{self, HeaderValue},
+        StatusCode,
+    },
+    HttpResponse,
+};
+
+/// Problem details for an HTTP API error, as defined in [RFC 9457].
+///
+/// Problem details are sent with the `application/problem+json` content type and give API clients
+/// a machine-readable description of an error. They can be returned from handlers as errors, since
+/// `ProblemDetails` implements [`ResponseError`].
+///
+/// The `title` of a new problem is the canonical reason phrase of its status code, which is
+/// appropriate when no problem `type` is set.
+///
+/// To render the errors of the built-in extractors as problem details, see
+/// [`ErrorHandlers::problem_details()`](crate::middleware::ErrorHandlers::problem_details).
+///
+/// # Examples
+/// ```
+/// use actix_web::{error::ProblemDetails, http::StatusCode, web, HttpResponse};
+///
+/// async fn withdraw(amount: web::Path<u64>) -> Result<HttpResponse, ProblemDetails> {
+///     let balance = 30;
+///
+///     if *amount > balance {
+///         return Err(ProblemDetails::new(StatusCode::FORBIDDEN)
+///             .with_type("https://example.com/probs/out-of-credit")
+///             .with_title("You do not have enough credit.")
+///             .with_detail(format!("Your current balance is {balance}."))
+///             .with_instance("/account/12345/msgs/abc")
+///             .with_extension("balance", balance));
+///     }
+///
+///     Ok(HttpResponse::Ok().finish())
+/// }
+/// ```
+///
+/// [RFC 9457]: https://www.rfc-editor.org/rfc/rfc9457
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProblemDetails {
+    type_uri: Option<String>,
+    title: Option<String>,
+    status: StatusCode,
+    detail: Option<String>,
+    instance: Option<String>,
+    extensions: Map<String, Value>,
+}
+
+impl ProblemDetails {
+    /// Constructs problem details for the given status code.
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            type_uri: None,
+            title: status.canonical_reason().map(str::to_owned),
+            status,
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    /// Constructs problem details from an error, using its status code and message.
+    ///
+    /// The error message is only used as the `detail` of client errors (400-499), so that details of
+    /// server errors are not exposed to clients.
+    pub fn from_error(err: &(impl ResponseError + ?Sized)) -> Self {
+        let problem = Self::new(err.status_code());
+
+        if problem.status.is_client_error() {
+            problem.with_detail(err.to_string())
+        } else {
+            problem
+        }
+    }
+
+    /// Sets the `type` member, a URI reference that identifies the problem type.
+    ///
+    /// When unset, the type is `about:blank`.
+    pub fn with_type(mut self, type_uri: impl Into<String>) -> Self {
+        self.type_uri = Some(type_uri.into());
+        self
+    }
+
+    /// Sets the `title` member, a short, human-readable summary of the problem type.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the `detail` member, a human-readable explanation of this occurrence of the problem.
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Sets the `instance` member, a URI reference that identifies this occurrence of the problem.
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Sets an extension member.
+    ///
+    /// Extension members named `type`, `title`, `status`, `detail` or `instance` are ignored when
+    /// serializing.
+    pub fn with_extension(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.extensions.insert(name.into(), value.into());
+        self
+    }
+
+    /// Returns the `type` member, if set.
+    pub fn type_uri(&self) -> Option<&str> {
+        self.type_uri.as_deref()
+    }
+
+    /// Returns the `title` member, if set.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Returns the status code.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the `detail` member, if set.
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    /// Returns the `instance` member, if set.
+    pub fn instance(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    /// Returns an extension member, if set.
+    pub fn extension(&self, name: &str) -> Option<&Value> {
+        self.extensions.get(name)
+    }
+
+    /// Constructs problem details for errors returned by the built-in extractors.
+    ///
+    /// The status code is taken from the response, since extractor configs can change it.
+    pub(crate) fn from_extractor_error(err: &Error, status: StatusCode) -> Option<Self> {
+        fn is<T: ResponseError + 'static>(err: &Error) -> bool {
+            err.as_error::<T>().is_some() || err.as_error::<InternalError<T>>().is_some()
+        }
+
+        // without a custom error handler, the `Path` extractor does not wrap errors in `PathError`
+        let is_path_err = is::<PathError>(err)
+            || err
+                .as_error::<InternalError<serde::de::value::Error>>()
+                .is_some();
+
+        if !(is_path_err
+            || is::<JsonPayloadError>(err)
+            || is::<QueryPayloadError>(err)
+            || is::<UrlencodedError>(err))
+        {
+            return None;
+        }
+
+        let problem = Self::new(status);
+
+        if status.is_client_error() {
+            Some(problem.with_detail(err.to_string()))
+        } else {
+            Some(problem)
+        }
+    }
+}
+
+impl fmt::Display for ProblemDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.title, &self.detail) {
+            (Some(title), Some(detail)) => write!(f, "{title}: {detail}"),
+            (Some(msg), None) | (None, Some(msg)) => f.write_str(msg),
+            (None, None) => write!(f, "{}", self.status),
+        }
+    }
+}
+
+impl std::error::Error for ProblemDetails {}
+
+impl Serialize for ProblemDetails {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        if let Some(type_uri) = &self.type_uri {
+            map.serialize_entry("type", type_uri)?;
+        }
+
+        if let Some(title) = &self.title {
+            map.serialize_entry("title", title)?;
+        }
+
+        map.serialize_entry("status", &self.status.as_u16())?;
+
+        if let Some(detail) = &self.detail {
+            map.serialize_entry("detail", detail)?;
+        }
+
+        if let Some(instance) = &self.instance {
+            map.serialize_entry("instance", instance)?;
+        }
+
+        for (name, value) in &self.extensions {
+            if !matches!(
+                name.as_str(),
+                "type" | "title" | "status" | "detail" | "instance"
+            ) {
+                map.serialize_entry(name, value)?;
+            }
+        }
+
+        map.end()
+    }
+}
+
+impl ResponseError for ProblemDetails {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const PROBLEM_JSON: HeaderValue = HeaderValue::from_static("application/problem+json");
+
+        match serde_json::to_string(self) {
+            Ok(body) => HttpResponse::build(self.status)
+                .insert_header((header::CONTENT_TYPE, PROBLEM_JSON))
+                .body(body),
+            Err(err) => HttpResponse::from_error(JsonPayloadError::Serialize(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::body::to_bytes;
+
+    #[actix_rt::test]
+    async fn response() {
+        let problem = ProblemDetails::new(StatusCode::FORBIDDEN)
+            .with_type("https://example.com/probs/out-of-credit")
+            .with_title("You do not have enough credit.")
+            .with_detail("Your current balance is 30, but that costs 50.")
+            .with_instance("/account/12345/msgs/abc")
+            .with_extension("balance", 30)
+            .with_extension("accounts", json!(["/account/12345", "/account/67890"]))
+            .with_extension("status", 200);
+
+        let res = problem.error_response();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+
+        let body: Value =
+            serde_json::from_slice(&to_bytes(res.into_body()).await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "You do not have enough credit.",
+                "status": 403,
+                "detail": "Your current balance is 30, but that costs 50.",
+                "instance": "/account/12345/msgs/abc",
+                "balance": 30,
+                "accounts": ["/account/12345", "/account/67890"],
+            })
+        );
+    }
+
+    #[test]
+    fn from_error() {
+        let problem = ProblemDetails::from_error(&QueryPayloadError::Deserialize(
+            serde_urlencoded::from_str::<u32>("x").unwrap_err(),
+        ));
+        assert_eq!(problem.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(problem.title(), Some("Bad Request"));
+        assert!(problem
+            .detail()
+            .unwrap()
+            .starts_with("Query deserialize error"));
+
+        let problem = ProblemDetails::from_error(&crate::error::BlockingError);
+        assert_eq!(problem.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(problem.detail(), None);
+        assert_eq!(problem.to_string(), "Internal Server Error");
+    }
+}
@@ -120,7 +120,8 @@ impl ResponseError for actix_http::error::ParseError {
 impl ResponseError for actix_http::error::PayloadError {
     fn status_code(&self) -> StatusCode {
         match *self {
-            actix_http::error::PayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            actix_http::error::PayloadError::Overflow
+            | actix_http::error::PayloadError::DecompressionLimit => StatusCode::PAYLOAD_TOO_LARGE,
             _ => StatusCode::BAD_REQUEST,
         }
     }
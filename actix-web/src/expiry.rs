@@ -0,0 +1,133 @@
+//! Map of entries with individual expiry times, used by in-memory stores.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    time::Instant,
+};
+
+/// Minimum number of queued expiries before the queue is compacted.
+const MIN_QUEUE_LEN: usize = 64;
+
+/// Map whose entries each expire at a given time.
+///
+/// Expired entries are never returned. They are dropped by [`purge`](Self::purge), which only
+/// visits entries that have expired, so stores can call it on every write.
+#[derive(Debug)]
+pub(crate) struct ExpiringMap<V> {
+    entries: HashMap<String, (V, Instant)>,
+
+    /// Keys ordered by the time at which they were set to expire.
+    ///
+    /// A key that has been re-inserted or removed since being queued is skipped when its expiry
+    /// is reached.
+    queue: BinaryHeap<Reverse<(Instant, String)>>,
+}
+
+impl<V> Default for ExpiringMap<V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            queue: BinaryHeap::new(),
+        }
+    }
+}
+
+impl<V> ExpiringMap<V> {
+    /// Returns the value of `key` if it has not expired by `now`.
+    pub(crate) fn get(&self, key: &str, now: Instant) -> Option<&V> {
+        self.entries
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(val, _)| val)
+    }
+
+    /// Inserts or replaces the value of `key`, to expire at `expires_at`.
+    pub(crate) fn insert(&mut self, key: String, val: V, expires_at: Instant) {
+        self.queue.push(Reverse((expires_at, key.clone())));
+        self.entries.insert(key, (val, expires_at));
+
+        // rebuild the queue once it is mostly made up of skipped keys
+        if self.queue.len() > (self.entries.len() * 2).max(MIN_QUEUE_LEN) {
+            self.queue = self
+                .entries
+                .iter()
+                .map(|(key, (_, expires_at))| Reverse((*expires_at, key.clone())))
+                .collect();
+        }
+    }
+
+    /// Removes `key`, returning its value even if it has expired.
+    pub(crate) fn remove(&mut self, key: &str) -> Option<V> {
+        self.entries.remove(key).map(|(val, _)| val)
+    }
+
+    /// Drops entries that expired by `now`.
+    pub(crate) fn purge(&mut self, now: Instant) {
+        while let Some(Reverse((expires_at, _))) = self.queue.peek() {
+            if *expires_at > now {
+                break;
+            }
+
+            let Reverse((_, key)) = self.queue.pop().unwrap();
+
+            if self
+                .entries
+                .get(&key)
+                .is_some_and(|(_, expires_at)| *expires_at <= now)
+            {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the number of entries, including expired ones not yet purged.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn expires_and_purges() {
+        let now = Instant::now();
+        let later = |secs| now + Duration::from_secs(secs);
+
+        let mut map = ExpiringMap::default();
+        map.insert("a".to_owned(), 1, later(10));
+        map.insert("b".to_owned(), 2, later(20));
+        map.insert("c".to_owned(), 3, later(5));
+
+        // re-inserting extends expiry
+        map.insert("c".to_owned(), 4, later(30));
+
+        assert_eq!(map.get("a", later(9)), Some(&1));
+        assert_eq!(map.get("a", later(10)), None);
+
+        map.purge(later(15));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("c", later(15)), Some(&4));
+
+        assert_eq!(map.remove("b"), Some(2));
+        map.purge(later(30));
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn compacts_queue() {
+        let now = Instant::now();
+        let mut map = ExpiringMap::default();
+
+        for i in 0..1000 {
+            map.insert("a".to_owned(), i, now + Duration::from_secs(i));
+        }
+
+        assert_eq!(map.len(), 1);
+        assert!(map.queue.len() <= MIN_QUEUE_LEN);
+    }
+}
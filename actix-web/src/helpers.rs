@@ -1,6 +1,8 @@
 use std::io;
 
-use bytes::BufMut;
+use bytes::{BufMut, Bytes};
+
+use crate::{dev, http::header, Error};
 
 /// An `io::Write`r that only requires mutable reference and assumes that there is space available
 /// in the buffer for every write operation or that it can be extended implicitly (like
@@ -23,3 +25,31 @@ where
         Ok(())
     }
 }
+
+/// Constructs a payload that yields `bytes` as a single chunk.
+///
+/// Useful for putting back request body data that has already been read.
+pub(crate) fn payload_from_bytes(bytes: Bytes) -> dev::Payload {
+    let (_, mut h1_payload) = actix_http::h1::Payload::create(true);
+    h1_payload.unread_data(bytes);
+    dev::Payload::from(h1_payload)
+}
+
+/// Buffers the request body and puts it back so that it can be extracted again.
+///
+/// The body is decoded according to its `Content-Encoding` and is subject to the
+/// [`PayloadConfig`](crate::web::PayloadConfig) limit, as with the [`Bytes`] extractor. Since the
+/// body that is put back is no longer encoded, the `Content-Encoding` and `Content-Length` headers
+/// are removed; otherwise extractors would try to decode it a second time.
+pub(crate) async fn buffer_payload(req: &mut dev::ServiceRequest) -> Result<Bytes, Error> {
+    let body = req.extract::<Bytes>().await?;
+
+    let headers = req.headers_mut();
+    if headers.remove(header::CONTENT_ENCODING).next().is_some() {
+        headers.remove(header::CONTENT_LENGTH);
+    }
+
+    req.set_payload(payload_from_bytes(body.clone()));
+
+    Ok(body)
+}
@@ -0,0 +1,300 @@
+use std::{
+    fmt::{self, Write as _},
+    str,
+};
+
+use base64::prelude::*;
+
+use super::{
+    from_one_raw_str, Header, HeaderName, HeaderValue, InvalidHeaderValue, TryIntoHeaderValue,
+    Writer, AUTHORIZATION,
+};
+use crate::{error::ParseError, HttpMessage};
+
+/// `Authorization` header, defined in [RFC 9110 §11.6.2].
+///
+/// The `Authorization` header field allows a user agent to authenticate itself with an origin
+/// server. Credentials for the `Basic` ([RFC 7617]) and `Bearer` ([RFC 6750]) schemes are decoded;
+/// credentials for other schemes are kept as-is.
+///
+/// # ABNF
+/// ```plain
+/// Authorization = credentials
+/// credentials   = auth-scheme [ 1*SP ( token68 / #auth-param ) ]
+/// ```
+///
+/// # Example Values
+/// - `Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==`
+/// - `Bearer mF_9.B5f-4.1JqM`
+///
+/// # Examples
+/// ```
+/// use actix_web::{http::header::Authorization, test::TestRequest};
+///
+/// let req = TestRequest::default()
+///     .insert_header(Authorization::basic("Aladdin", "open sesame"))
+///     .to_http_request();
+/// ```
+///
+/// ```
+/// use actix_web::{
+///     http::header::{Authorization, Header as _},
+///     HttpRequest, Responder,
+/// };
+///
+/// async fn index(req: HttpRequest) -> impl Responder {
+///     match Authorization::parse(&req) {
+///         Ok(Authorization::Bearer(token)) => format!("token: {token}"),
+///         _ => "no token".to_owned(),
+///     }
+/// }
+/// ```
+///
+/// [RFC 9110 §11.6.2]: https://www.rfc-editor.org/rfc/rfc9110#section-11.6.2
+/// [RFC 7617]: https://www.rfc-editor.org/rfc/rfc7617
+/// [RFC 6750]: https://www.rfc-editor.org/rfc/rfc6750
+#[derive(Clone, PartialEq, Eq)]
+pub enum Authorization {
+    /// Credentials for the `Basic` scheme.
+    Basic {
+        /// User ID.
+        user_id: String,
+
+        /// Password. May be empty.
+        password: String,
+    },
+
+    /// Token for the `Bearer` scheme.
+    Bearer(String),
+
+    /// Credentials for any other scheme.
+    Other {
+        /// Authentication scheme.
+        scheme: String,
+
+        /// Credentials following the scheme. May be empty.
+        credentials: String,
+    },
+}
+
+impl Authorization {
+    /// Constructs `Basic` credentials.
+    pub fn basic(user_id: impl Into<String>, password: impl Into<String>) -> Self {
+        Self::Basic {
+            user_id: user_id.into(),
+            password: password.into(),
+        }
+    }
+
+    /// Constructs `Bearer` credentials.
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Self::Bearer(token.into())
+    }
+
+    /// Returns the authentication scheme of the credentials.
+    pub fn scheme(&self) -> &str {
+        match self {
+            Self::Basic { .. } => "Basic",
+            Self::Bearer(_) => "Bearer",
+            Self::Other { scheme, .. } => scheme,
+        }
+    }
+}
+
+impl fmt::Debug for Authorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // avoid leaking secrets into logs
+        match self {
+            Self::Basic { user_id, .. } => f
+                .debug_struct("Basic")
+                .field("user_id", user_id)
+                .finish_non_exhaustive(),
+            Self::Bearer(_) => f.debug_struct("Bearer").finish_non_exhaustive(),
+            Self::Other { scheme, .. } => f
+                .debug_struct("Other")
+                .field("scheme", scheme)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+impl fmt::Display for Authorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Basic { user_id, password } => {
+                let credentials = BASE64_STANDARD.encode(format!("{user_id}:{password}"));
+                write!(f, "Basic {credentials}")
+            }
+            Self::Bearer(token) => write!(f, "Bearer {token}"),
+            Self::Other {
+                scheme,
+                credentials,
+            } if credentials.is_empty() => f.write_str(scheme),
+            Self::Other {
+                scheme,
+                credentials,
+            } => write!(f, "{scheme} {credentials}"),
+        }
+    }
+}
+
+impl str::FromStr for Authorization {
+    type Err = ParseError;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let val = val.trim();
+
+        let (scheme, credentials) = match val.split_once(' ') {
+            Some((scheme, credentials)) => (scheme, credentials.trim_start()),
+            None => (val, ""),
+        };
+
+        if !is_token(scheme) {
+            return Err(ParseError::Header);
+        }
+
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = BASE64_STANDARD
+                .decode(credentials)
+                .map_err(|_| ParseError::Header)?;
+            let decoded = String::from_utf8(decoded).map_err(|_| ParseError::Header)?;
+
+            let (user_id, password) = decoded.split_once(':').ok_or(ParseError::Header)?;
+
+            Ok(Self::basic(user_id, password))
+        } else if scheme.eq_ignore_ascii_case("bearer") {
+            if credentials.is_empty() || credentials.contains(char::is_whitespace) {
+                return Err(ParseError::Header);
+            }
+
+            Ok(Self::bearer(credentials))
+        } else {
+            Ok(Self::Other {
+                scheme: scheme.to_owned(),
+                credentials: credentials.to_owned(),
+            })
+        }
+    }
+}
+
+impl TryIntoHeaderValue for Authorization {
+    type Error = InvalidHeaderValue;
+
+    fn try_into_value(self) -> Result<HeaderValue, Self::Error> {
+        let mut writer = Writer::new();
+        let _ = write!(&mut writer, "{}", self);
+        HeaderValue::from_maybe_shared(writer.take())
+    }
+}
+
+impl Header for Authorization {
+    fn name() -> HeaderName {
+        AUTHORIZATION
+    }
+
+    fn parse<M: HttpMessage>(msg: &M) -> Result<Self, ParseError> {
+        from_one_raw_str(msg.headers().get(Self::name()))
+    }
+}
+
+/// Returns true if `val` is a non-empty `token`, as defined in [RFC 9110 §5.6.2].
+///
+/// [RFC 9110 §5.6.2]: https://www.rfc-editor.org/rfc/rfc9110#section-5.6.2
+pub(super) fn is_token(val: &str) -> bool {
+    !val.is_empty()
+        && val.bytes().all(|b| {
+            b.is_ascii_alphanumeric()
+                || matches!(
+                    b,
+                    b'!' | b'#'
+                        | b'$'
+                        | b'%'
+                        | b'&'
+                        | b'\''
+                        | b'*'
+                        | b'+'
+                        | b'-'
+                        | b'.'
+                        | b'^'
+                        | b'_'
+                        | b'`'
+                        | b'|'
+                        | b'~'
+                )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    fn parse(val: &str) -> Result<Authorization, ParseError> {
+        let req = TestRequest::default()
+            .insert_header((AUTHORIZATION, val))
+            .to_http_request();
+
+        Authorization::parse(&req)
+    }
+
+    #[test]
+    fn basic() {
+        assert_eq!(
+            parse("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==").unwrap(),
+            Authorization::basic("Aladdin", "open sesame"),
+        );
+        assert_eq!(
+            parse("basic dXNlcm5hbWU6").unwrap(),
+            Authorization::basic("username", ""),
+        );
+
+        // password may contain colons
+        let auth = Authorization::basic("user", "pass:word");
+        assert_eq!(parse(&auth.to_string()).unwrap(), auth);
+
+        assert!(parse("Basic").is_err());
+        assert!(parse("Basic !!!").is_err());
+        // no colon
+        assert!(parse("Basic dXNlcm5hbWU=").is_err());
+    }
+
+    #[test]
+    fn bearer() {
+        assert_eq!(
+            parse("Bearer mF_9.B5f-4.1JqM").unwrap(),
+            Authorization::bearer("mF_9.B5f-4.1JqM"),
+        );
+        assert_eq!(
+            Authorization::bearer("abc").try_into_value().unwrap(),
+            "Bearer abc"
+        );
+
+        assert!(parse("Bearer").is_err());
+        assert!(parse("Bearer a b").is_err());
+    }
+
+    #[test]
+    fn other_schemes() {
+        let auth = parse("Digest username=\"Mufasa\", realm=\"test\"").unwrap();
+        assert_eq!(auth.scheme(), "Digest");
+        assert_eq!(
+            auth.to_string(),
+            "Digest username=\"Mufasa\", realm=\"test\""
+        );
+
+        assert_eq!(parse("Negotiate").unwrap().to_string(), "Negotiate");
+
+        assert!(parse("").is_err());
+        assert!(parse("B@sic abc").is_err());
+    }
+
+    #[test]
+    fn debug_hides_secrets() {
+        let debug = format!("{:?}", Authorization::basic("user", "hunter2"));
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("hunter2"));
+
+        let debug = format!("{:?}", Authorization::bearer("secret-token"));
+        assert!(!debug.contains("secret-token"));
+    }
+}
@@ -21,6 +21,7 @@ mod accept_charset;
 mod accept_encoding;
 mod accept_language;
 mod allow;
+mod authorization;
 mod cache_control;
 mod content_disposition;
 mod content_language;
@@ -41,6 +42,8 @@ mod last_modified;
 mod macros;
 mod preference;
 mod range;
+mod strict_transport_security;
+mod www_authenticate;
 
 #[cfg(test)]
 pub(crate) use self::macros::common_header_test;
@@ -51,6 +54,7 @@ pub use self::{
     accept_encoding::AcceptEncoding,
     accept_language::AcceptLanguage,
     allow::Allow,
+    authorization::Authorization,
     cache_control::{CacheControl, CacheDirective},
     content_disposition::{ContentDisposition, DispositionParam, DispositionType},
     content_language::ContentLanguage,
@@ -70,6 +74,8 @@ pub use self::{
     last_modified::LastModified,
     preference::Preference,
     range::{ByteRangeSpec, Range},
+    strict_transport_security::StrictTransportSecurity,
+    www_authenticate::WwwAuthenticate,
 };
 
 /// Format writer ([`fmt::Write`]) for a [`BytesMut`].
@@ -0,0 +1,236 @@
+use std::{
+    fmt::{self, Write as _},
+    str,
+    time::Duration,
+};
+
+use super::{
+    from_one_raw_str, Header, HeaderName, HeaderValue, InvalidHeaderValue, TryIntoHeaderValue,
+    Writer, STRICT_TRANSPORT_SECURITY,
+};
+use crate::{error::ParseError, HttpMessage};
+
+/// `Strict-Transport-Security` header, defined in [RFC 6797 §6.1].
+///
+/// The `Strict-Transport-Security` header field tells user agents to only access the host, and
+/// optionally its subdomains, over HTTPS for the given amount of time. It must only be sent in
+/// responses to requests made over HTTPS.
+///
+/// # ABNF
+/// ```plain
+/// Strict-Transport-Security = [ directive ] *( ";" [ directive ] )
+/// directive                 = directive-name [ "=" directive-value ]
+/// ```
+///
+/// # Example Values
+/// - `max-age=31536000`
+/// - `max-age=63072000; includeSubDomains; preload`
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_web::{http::header::StrictTransportSecurity, HttpResponse};
+///
+/// let res = HttpResponse::Ok()
+///     .insert_header(
+///         StrictTransportSecurity::new(Duration::from_secs(2 * 365 * 24 * 60 * 60))
+///             .include_subdomains()
+///             .preload(),
+///     )
+///     .finish();
+/// ```
+///
+/// [RFC 6797 §6.1]: https://www.rfc-editor.org/rfc/rfc6797#section-6.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrictTransportSecurity {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl StrictTransportSecurity {
+    /// Constructs a policy that lasts for `max_age`. Sub-second precision is ignored.
+    ///
+    /// A `max_age` of zero tells user agents to forget the policy.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age: Duration::from_secs(max_age.as_secs()),
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+
+    /// Sets the `includeSubDomains` directive, applying the policy to all subdomains of the host.
+    pub fn include_subdomains(mut self) -> Self {
+        self.include_subdomains = true;
+        self
+    }
+
+    /// Sets the `preload` directive, consenting to inclusion in browser preload lists.
+    ///
+    /// Preload lists generally require a `max_age` of at least one year and `includeSubDomains`.
+    pub fn preload(mut self) -> Self {
+        self.preload = true;
+        self
+    }
+
+    /// Returns the `max-age` of the policy.
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Returns true if the `includeSubDomains` directive is set.
+    pub fn includes_subdomains(&self) -> bool {
+        self.include_subdomains
+    }
+
+    /// Returns true if the `preload` directive is set.
+    pub fn is_preload(&self) -> bool {
+        self.preload
+    }
+}
+
+impl fmt::Display for StrictTransportSecurity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "max-age={}", self.max_age.as_secs())?;
+
+        if self.include_subdomains {
+            f.write_str("; includeSubDomains")?;
+        }
+
+        if self.preload {
+            f.write_str("; preload")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl str::FromStr for StrictTransportSecurity {
+    type Err = ParseError;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        let mut preload = false;
+
+        for directive in val.split(';').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim_end(), Some(value.trim_start())),
+                None => (directive, None),
+            };
+
+            // directives must not appear more than once; unknown directives are ignored
+            if name.eq_ignore_ascii_case("max-age") {
+                let value = value.ok_or(ParseError::Header)?;
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+
+                if max_age.is_some()
+                    || value.is_empty()
+                    || !value.bytes().all(|b| b.is_ascii_digit())
+                {
+                    return Err(ParseError::Header);
+                }
+
+                max_age = Some(value.parse().map_err(|_| ParseError::Header)?);
+            } else if name.eq_ignore_ascii_case("includeSubDomains") {
+                if include_subdomains || value.is_some() {
+                    return Err(ParseError::Header);
+                }
+
+                include_subdomains = true;
+            } else if name.eq_ignore_ascii_case("preload") {
+                if preload || value.is_some() {
+                    return Err(ParseError::Header);
+                }
+
+                preload = true;
+            }
+        }
+
+        Ok(Self {
+            max_age: Duration::from_secs(max_age.ok_or(ParseError::Header)?),
+            include_subdomains,
+            preload,
+        })
+    }
+}
+
+impl TryIntoHeaderValue for StrictTransportSecurity {
+    type Error = InvalidHeaderValue;
+
+    fn try_into_value(self) -> Result<HeaderValue, Self::Error> {
+        let mut writer = Writer::new();
+        let _ = write!(&mut writer, "{}", self);
+        HeaderValue::from_maybe_shared(writer.take())
+    }
+}
+
+impl Header for StrictTransportSecurity {
+    fn name() -> HeaderName {
+        STRICT_TRANSPORT_SECURITY
+    }
+
+    fn parse<M: HttpMessage>(msg: &M) -> Result<Self, ParseError> {
+        from_one_raw_str(msg.headers().get(Self::name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        assert_eq!(
+            StrictTransportSecurity::new(Duration::from_millis(31_536_000_500)).to_string(),
+            "max-age=31536000"
+        );
+
+        assert_eq!(
+            StrictTransportSecurity::new(Duration::from_secs(63_072_000))
+                .preload()
+                .include_subdomains()
+                .try_into_value()
+                .unwrap(),
+            "max-age=63072000; includeSubDomains; preload"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let hsts: StrictTransportSecurity = "max-age=\"600\" ;INCLUDESUBDOMAINS; foo=bar"
+            .parse()
+            .unwrap();
+
+        assert_eq!(hsts.max_age(), Duration::from_secs(600));
+        assert!(hsts.includes_subdomains());
+        assert!(!hsts.is_preload());
+
+        let hsts = StrictTransportSecurity::new(Duration::ZERO).preload();
+        assert_eq!(
+            hsts.to_string().parse::<StrictTransportSecurity>().unwrap(),
+            hsts
+        );
+
+        assert!("".parse::<StrictTransportSecurity>().is_err());
+        assert!("includeSubDomains"
+            .parse::<StrictTransportSecurity>()
+            .is_err());
+        assert!("max-age=-1".parse::<StrictTransportSecurity>().is_err());
+        assert!("max-age=1; max-age=2"
+            .parse::<StrictTransportSecurity>()
+            .is_err());
+        assert!("max-age=1; preload=yes"
+            .parse::<StrictTransportSecurity>()
+            .is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    fmt::{self, Write as _},
+    str,
+};
+
+use super::{
+    authorization::is_token, from_one_raw_str, Header, HeaderName, HeaderValue, InvalidHeaderValue,
+    TryIntoHeaderValue, Writer, WWW_AUTHENTICATE,
+};
+use crate::{error::ParseError, HttpMessage};
+
+/// `WWW-Authenticate` header, defined in [RFC 9110 §11.6.1].
+///
+/// The `WWW-Authenticate` header field is sent with `401 Unauthorized` responses and indicates the
+/// authentication scheme and parameters applicable to the target resource. This type represents a
+/// single challenge using the `auth-param` syntax.
+///
+/// # ABNF
+/// ```plain
+/// WWW-Authenticate = 1#challenge
+/// challenge        = auth-scheme [ 1*SP ( token68 / #auth-param ) ]
+/// auth-param       = token BWS "=" BWS ( token / quoted-string )
+/// ```
+///
+/// # Example Values
+/// - `Basic realm="Dev", charset="UTF-8"`
+/// - `Bearer realm="example", error="invalid_token"`
+///
+/// # Examples
+/// ```
+/// use actix_web::{http::header::WwwAuthenticate, HttpResponse};
+///
+/// let res = HttpResponse::Unauthorized()
+///     .insert_header(WwwAuthenticate::basic("Admin area"))
+///     .finish();
+///
+/// let res = HttpResponse::Unauthorized()
+///     .insert_header(
+///         WwwAuthenticate::bearer()
+///             .realm("api")
+///             .error("invalid_token")
+///             .param("error_description", "The access token expired"),
+///     )
+///     .finish();
+/// ```
+///
+/// [RFC 9110 §11.6.1]: https://www.rfc-editor.org/rfc/rfc9110#section-11.6.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WwwAuthenticate {
+    scheme: String,
+    params: Vec<(String, String)>,
+}
+
+impl WwwAuthenticate {
+    /// Constructs a challenge for the given authentication scheme, without parameters.
+    pub fn new(scheme: impl Into<String>) -> Self {
+        Self {
+            scheme: scheme.into(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Constructs a `Basic` challenge for the given realm.
+    pub fn basic(realm: impl Into<String>) -> Self {
+        Self::new("Basic").realm(realm)
+    }
+
+    /// Constructs a `Bearer` challenge, without parameters.
+    pub fn bearer() -> Self {
+        Self::new("Bearer")
+    }
+
+    /// Sets a parameter, replacing any existing parameter with the same name.
+    pub fn param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        let value = value.into();
+
+        match self
+            .params
+            .iter_mut()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(&name))
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.params.push((name, value)),
+        }
+
+        self
+    }
+
+    /// Sets the `realm` parameter.
+    pub fn realm(self, realm: impl Into<String>) -> Self {
+        self.param("realm", realm)
+    }
+
+    /// Sets the `error` parameter, as used by the `Bearer` scheme.
+    pub fn error(self, error: impl Into<String>) -> Self {
+        self.param("error", error)
+    }
+
+    /// Returns the authentication scheme of the challenge.
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Returns the value of a parameter, if present. Parameter names are case-insensitive.
+    pub fn get_param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for WwwAuthenticate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.scheme)?;
+
+        for (idx, (name, value)) in self.params.iter().enumerate() {
+            let sep = if idx == 0 { " " } else { ", " };
+            write!(f, "{sep}{name}=\"")?;
+
+            for ch in value.chars() {
+                if matches!(ch, '"' | '\\') {
+                    f.write_char('\\')?;
+                }
+                f.write_char(ch)?;
+            }
+
+            f.write_char('"')?;
+        }
+
+        Ok(())
+    }
+}
+
+impl str::FromStr for WwwAuthenticate {
+    type Err = ParseError;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let val = val.trim();
+
+        let (scheme, mut rest) = match val.split_once(' ') {
+            Some((scheme, rest)) => (scheme, rest),
+            None => (val, ""),
+        };
+
+        if !is_token(scheme) {
+            return Err(ParseError::Header);
+        }
+
+        let mut challenge = Self::new(scheme);
+
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', ',']);
+            if rest.is_empty() {
+                break;
+            }
+
+            let (name, tail) = rest.split_once('=').ok_or(ParseError::Header)?;
+            let name = name.trim();
+            let tail = tail.trim_start();
+
+            if !is_token(name) {
+                return Err(ParseError::Header);
+            }
+
+            let value = if let Some(quoted) = tail.strip_prefix('"') {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+
+                loop {
+                    match chars.next() {
+                        Some((idx, '"')) => {
+                            rest = &quoted[idx + 1..];
+                            break;
+                        }
+                        Some((_, '\\')) => {
+                            value.push(chars.next().ok_or(ParseError::Header)?.1);
+                        }
+                        Some((_, ch)) => value.push(ch),
+                        None => return Err(ParseError::Header),
+                    }
+                }
+
+                value
+            } else {
+                let end = tail.find([',', ' ', '\t']).unwrap_or(tail.len());
+                let (value, tail) = tail.split_at(end);
+
+                if !is_token(value) {
+                    return Err(ParseError::Header);
+                }
+
+                rest = tail;
+                value.to_owned()
+            };
+
+            challenge.params.push((name.to_owned(), value));
+        }
+
+        Ok(challenge)
+    }
+}
+
+impl TryIntoHeaderValue for WwwAuthenticate {
+    type Error = InvalidHeaderValue;
+
+    fn try_into_value(self) -> Result<HeaderValue, Self::Error> {
+        let mut writer = Writer::new();
+        let _ = write!(&mut writer, "{}", self);
+        HeaderValue::from_maybe_shared(writer.take())
+    }
+}
+
+impl Header for WwwAuthenticate {
+    fn name() -> HeaderName {
+        WWW_AUTHENTICATE
+    }
+
+    fn parse<M: HttpMessage>(msg: &M) -> Result<Self, ParseError> {
+        from_one_raw_str(msg.headers().get(Self::name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        assert_eq!(
+            WwwAuthenticate::basic("Admin area").to_string(),
+            "Basic realm=\"Admin area\""
+        );
+
+        assert_eq!(
+            WwwAuthenticate::bearer()
+                .realm("say \"hi\"")
+                .error("invalid_token")
+                .realm("api")
+                .to_string(),
+            "Bearer realm=\"api\", error=\"invalid_token\""
+        );
+
+        assert_eq!(
+            WwwAuthenticate::new("Basic")
+                .realm("a\\b\"c")
+                .try_into_value()
+                .unwrap(),
+            "Basic realm=\"a\\\\b\\\"c\""
+        );
+
+        assert_eq!(WwwAuthenticate::bearer().to_string(), "Bearer");
+    }
+
+    #[test]
+    fn parse() {
+        let challenge: WwwAuthenticate =
+            "Bearer realm=\"example\", error=invalid_token, error_description=\"a \\\"b\\\"\""
+                .parse()
+                .unwrap();
+
+        assert_eq!(challenge.scheme(), "Bearer");
+        assert_eq!(challenge.get_param("Realm"), Some("example"));
+        assert_eq!(challenge.get_param("error"), Some("invalid_token"));
+        assert_eq!(challenge.get_param("error_description"), Some("a \"b\""));
+
+        let challenge = WwwAuthenticate::basic("a\\b\"c");
+        assert_eq!(
+            challenge.to_string().parse::<WwwAuthenticate>().unwrap(),
+            challenge
+        );
+
+        assert!("".parse::<WwwAuthenticate>().is_err());
+        assert!("Basic realm=\"unterminated"
+            .parse::<WwwAuthenticate>()
+            .is_err());
+        assert!("Basic realm".parse::<WwwAuthenticate>().is_err());
+    }
+}
@@ -1,5 +1,8 @@
 //! Various HTTP related types.
 
 pub mod header;
+mod preconditions;
 
 pub use actix_http::{uri, ConnectionType, Error, KeepAlive, Method, StatusCode, Uri, Version};
+
+pub use self::preconditions::{Precondition, Preconditions};
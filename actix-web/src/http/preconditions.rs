@@ -0,0 +1,372 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    http::{
+        header::{
+            self, EntityTag, HttpDate, IfMatch, IfModifiedSince, IfNoneMatch, IfRange,
+            IfUnmodifiedSince,
+        },
+        Method, StatusCode,
+    },
+    HttpMessage as _, HttpRequest, HttpResponse,
+};
+
+/// Outcome of evaluating a request's preconditions.
+///
+/// See [`Preconditions::evaluate()`].
+#[derive(Debug)]
+pub enum Precondition {
+    /// All preconditions passed; the request should be processed normally.
+    Proceed {
+        /// Whether the `Range` header, if any, should be honored.
+        ///
+        /// This is `false` when the request carries an `If-Range` header that does not match the
+        /// current representation, in which case the full representation should be sent.
+        honor_range: bool,
+    },
+
+    /// A precondition determined the response; either `304 Not Modified` or
+    /// `412 Precondition Failed`.
+    Respond(HttpResponse),
+}
+
+/// Evaluates conditional request headers against the current state of a resource.
+///
+/// Handlers describe the selected representation using its [entity tag](Self::etag) and/or
+/// [last modification time](Self::last_modified), then call [`evaluate()`](Self::evaluate). The
+/// `If-Match`, `If-Unmodified-Since`, `If-None-Match`, `If-Modified-Since` and `If-Range` headers
+/// are evaluated in the order given by [RFC 9110 §13.2.2].
+///
+/// Modification times are compared at a granularity of one second, matching the precision of HTTP
+/// dates.
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     http::{header::EntityTag, Precondition, Preconditions},
+///     HttpRequest, HttpResponse,
+/// };
+///
+/// async fn index(req: HttpRequest) -> HttpResponse {
+///     let etag = EntityTag::new_strong("v1".to_owned());
+///
+///     match Preconditions::new().etag(etag.clone()).evaluate(&req) {
+///         Precondition::Proceed { .. } => HttpResponse::Ok()
+///             .insert_header(actix_web::http::header::ETag(etag))
+///             .body("hello world"),
+///         Precondition::Respond(res) => res,
+///     }
+/// }
+/// ```
+///
+/// [RFC 9110 §13.2.2]: https://www.rfc-editor.org/rfc/rfc9110#section-13.2.2
+#[derive(Debug, Clone)]
+pub struct Preconditions {
+    etag: Option<EntityTag>,
+    last_modified: Option<SystemTime>,
+    exists: bool,
+}
+
+impl Preconditions {
+    /// Constructs a precondition evaluator for an existing resource with no validators.
+    pub fn new() -> Self {
+        Self {
+            etag: None,
+            last_modified: None,
+            exists: true,
+        }
+    }
+
+    /// Sets the entity tag of the selected representation.
+    pub fn etag(mut self, etag: EntityTag) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    /// Sets the last modification time of the selected representation.
+    pub fn last_modified(mut self, last_modified: impl Into<SystemTime>) -> Self {
+        self.last_modified = Some(last_modified.into());
+        self
+    }
+
+    /// Sets whether the target resource currently has a representation. Defaults to `true`.
+    ///
+    /// This determines whether `If-Match: *` and `If-None-Match: *` match, which is useful to guard
+    /// against lost updates when creating resources with `PUT`.
+    pub fn exists(mut self, exists: bool) -> Self {
+        self.exists = exists;
+        self
+    }
+
+    /// Evaluates the preconditions of `req`.
+    pub fn evaluate(&self, req: &HttpRequest) -> Precondition {
+        let is_get_or_head = matches!(*req.method(), Method::GET | Method::HEAD);
+
+        // step 1 and 2: If-Match, or If-Unmodified-Since in its absence
+        if let Some(if_match) = req.get_header::<IfMatch>() {
+            if !self.if_match(&if_match) {
+                return Precondition::Respond(self.precondition_failed());
+            }
+        } else if let Some(IfUnmodifiedSince(since)) = req.get_header() {
+            if matches!(self.modified_since(since), Some(true)) {
+                return Precondition::Respond(self.precondition_failed());
+            }
+        }
+
+        // step 3 and 4: If-None-Match, or If-Modified-Since in its absence
+        if let Some(if_none_match) = req.get_header::<IfNoneMatch>() {
+            if self.if_none_match(&if_none_match) {
+                return Precondition::Respond(if is_get_or_head {
+                    self.not_modified()
+                } else {
+                    self.precondition_failed()
+                });
+            }
+        } else if is_get_or_head {
+            if let Some(IfModifiedSince(since)) = req.get_header() {
+                if matches!(self.modified_since(since), Some(false)) {
+                    return Precondition::Respond(self.not_modified());
+                }
+            }
+        }
+
+        // step 5: If-Range
+        let honor_range =
+            if *req.method() == Method::GET && req.headers().contains_key(header::RANGE) {
+                match req.get_header::<IfRange>() {
+                    Some(IfRange::EntityTag(tag)) => {
+                        self.etag.as_ref().is_some_and(|etag| tag.strong_eq(etag))
+                    }
+                    Some(IfRange::Date(date)) => self
+                        .last_modified
+                        .zip(secs(date.into()))
+                        .is_some_and(|(lm, date)| secs(lm) == Some(date)),
+                    None => !req.headers().contains_key(header::IF_RANGE),
+                }
+            } else {
+                true
+            };
+
+        Precondition::Proceed { honor_range }
+    }
+
+    /// Returns true if `If-Match` matches the selected representation, using strong comparison.
+    fn if_match(&self, if_match: &IfMatch) -> bool {
+        match if_match {
+            IfMatch::Any => self.exists,
+            IfMatch::Items(items) => self
+                .etag
+                .as_ref()
+                .is_some_and(|etag| items.iter().any(|item| item.strong_eq(etag))),
+        }
+    }
+
+    /// Returns true if `If-None-Match` matches the selected representation, using weak comparison.
+    fn if_none_match(&self, if_none_match: &IfNoneMatch) -> bool {
+        match if_none_match {
+            IfNoneMatch::Any => self.exists,
+            IfNoneMatch::Items(items) => self
+                .etag
+                .as_ref()
+                .is_some_and(|etag| items.iter().any(|item| item.weak_eq(etag))),
+        }
+    }
+
+    /// Returns whether the representation was modified after `date`, if the last modification
+    /// time is known.
+    fn modified_since(&self, date: HttpDate) -> Option<bool> {
+        let last_modified = secs(self.last_modified?)?;
+        let date = secs(date.into())?;
+        Some(last_modified > date)
+    }
+
+    fn not_modified(&self) -> HttpResponse {
+        let mut res = HttpResponse::NotModified();
+
+        if let Some(etag) = &self.etag {
+            res.insert_header(header::ETag(etag.clone()));
+        }
+
+        if let Some(last_modified) = self.last_modified {
+            res.insert_header(header::LastModified(last_modified.into()));
+        }
+
+        res.finish()
+    }
+
+    fn precondition_failed(&self) -> HttpResponse {
+        HttpResponse::new(StatusCode::PRECONDITION_FAILED)
+    }
+}
+
+impl Default for Preconditions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns whole seconds since the Unix epoch, the precision of HTTP dates.
+fn secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|dur| dur.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::test::TestRequest;
+
+    fn status(preconditions: &Preconditions, req: TestRequest) -> Option<StatusCode> {
+        match preconditions.evaluate(&req.to_http_request()) {
+            Precondition::Proceed { .. } => None,
+            Precondition::Respond(res) => Some(res.status()),
+        }
+    }
+
+    fn honor_range(preconditions: &Preconditions, req: TestRequest) -> bool {
+        match preconditions.evaluate(&req.to_http_request()) {
+            Precondition::Proceed { honor_range } => honor_range,
+            Precondition::Respond(res) => panic!("unexpected response: {}", res.status()),
+        }
+    }
+
+    #[test]
+    fn entity_tags() {
+        let pre = Preconditions::new().etag(EntityTag::new_strong("v1".to_owned()));
+
+        assert_eq!(status(&pre, TestRequest::get()), None);
+
+        let req = TestRequest::get().insert_header((header::IF_NONE_MATCH, "\"v1\""));
+        assert_eq!(status(&pre, req), Some(StatusCode::NOT_MODIFIED));
+
+        let req = TestRequest::get().insert_header((header::IF_NONE_MATCH, "W/\"v1\""));
+        assert_eq!(status(&pre, req), Some(StatusCode::NOT_MODIFIED));
+
+        let req = TestRequest::put().insert_header((header::IF_NONE_MATCH, "\"v1\""));
+        assert_eq!(status(&pre, req), Some(StatusCode::PRECONDITION_FAILED));
+
+        let req = TestRequest::get().insert_header((header::IF_NONE_MATCH, "\"v2\""));
+        assert_eq!(status(&pre, req), None);
+
+        let req = TestRequest::put().insert_header((header::IF_MATCH, "\"v1\""));
+        assert_eq!(status(&pre, req), None);
+
+        // If-Match uses strong comparison
+        let req = TestRequest::put().insert_header((header::IF_MATCH, "W/\"v1\""));
+        assert_eq!(status(&pre, req), Some(StatusCode::PRECONDITION_FAILED));
+
+        let req = TestRequest::put().insert_header((header::IF_MATCH, "*"));
+        assert_eq!(status(&pre, req), None);
+
+        let missing = Preconditions::new().exists(false);
+
+        let req = TestRequest::put().insert_header((header::IF_MATCH, "*"));
+        assert_eq!(status(&missing, req), Some(StatusCode::PRECONDITION_FAILED));
+
+        let req = TestRequest::put().insert_header((header::IF_NONE_MATCH, "*"));
+        assert_eq!(status(&missing, req), None);
+
+        // 304 responses carry validators
+        let req = TestRequest::get().insert_header((header::IF_NONE_MATCH, "*"));
+        match pre.evaluate(&req.to_http_request()) {
+            Precondition::Respond(res) => {
+                assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"v1\"");
+            }
+            _ => panic!("expected response"),
+        }
+    }
+
+    #[test]
+    fn dates() {
+        let last_modified = UNIX_EPOCH + Duration::from_millis(1_000_000_500);
+        let pre = Preconditions::new().last_modified(last_modified);
+
+        let same = HttpDate::from(UNIX_EPOCH + Duration::from_secs(1_000_000));
+        let before = HttpDate::from(UNIX_EPOCH + Duration::from_secs(999_999));
+
+        let req = TestRequest::get().insert_header(IfModifiedSince(same));
+        assert_eq!(status(&pre, req), Some(StatusCode::NOT_MODIFIED));
+
+        let req = TestRequest::get().insert_header(IfModifiedSince(before));
+        assert_eq!(status(&pre, req), None);
+
+        // If-Modified-Since is ignored for unsafe methods
+        let req = TestRequest::post().insert_header(IfModifiedSince(same));
+        assert_eq!(status(&pre, req), None);
+
+        let req = TestRequest::put().insert_header(IfUnmodifiedSince(same));
+        assert_eq!(status(&pre, req), None);
+
+        let req = TestRequest::put().insert_header(IfUnmodifiedSince(before));
+        assert_eq!(status(&pre, req), Some(StatusCode::PRECONDITION_FAILED));
+    }
+
+    #[test]
+    fn evaluation_order() {
+        let pre = Preconditions::new()
+            .etag(EntityTag::new_strong("v1".to_owned()))
+            .last_modified(UNIX_EPOCH + Duration::from_secs(1_000_000));
+
+        let before = HttpDate::from(UNIX_EPOCH + Duration::from_secs(999_999));
+        let same = HttpDate::from(UNIX_EPOCH + Duration::from_secs(1_000_000));
+
+        // If-Match takes precedence over If-Unmodified-Since
+        let req = TestRequest::put()
+            .insert_header((header::IF_MATCH, "\"v1\""))
+            .insert_header(IfUnmodifiedSince(before));
+        assert_eq!(status(&pre, req), None);
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let req = TestRequest::get()
+            .insert_header((header::IF_NONE_MATCH, "\"v2\""))
+            .insert_header(IfModifiedSince(same));
+        assert_eq!(status(&pre, req), None);
+
+        // 412 takes precedence over 304
+        let req = TestRequest::get()
+            .insert_header((header::IF_MATCH, "\"v2\""))
+            .insert_header((header::IF_NONE_MATCH, "\"v1\""));
+        assert_eq!(status(&pre, req), Some(StatusCode::PRECONDITION_FAILED));
+    }
+
+    #[test]
+    fn if_range() {
+        let pre = Preconditions::new()
+            .etag(EntityTag::new_strong("v1".to_owned()))
+            .last_modified(UNIX_EPOCH + Duration::from_secs(1_000_000));
+
+        let range = || TestRequest::get().insert_header((header::RANGE, "bytes=0-1"));
+
+        assert!(honor_range(&pre, range()));
+        assert!(honor_range(
+            &pre,
+            range().insert_header((header::IF_RANGE, "\"v1\""))
+        ));
+        assert!(!honor_range(
+            &pre,
+            range().insert_header((header::IF_RANGE, "\"v2\""))
+        ));
+        assert!(!honor_range(
+            &pre,
+            range().insert_header((header::IF_RANGE, "W/\"v1\""))
+        ));
+
+        let same = HttpDate::from(UNIX_EPOCH + Duration::from_secs(1_000_000));
+        let before = HttpDate::from(UNIX_EPOCH + Duration::from_secs(999_999));
+        assert!(honor_range(
+            &pre,
+            range().insert_header(IfRange::Date(same))
+        ));
+        assert!(!honor_range(
+            &pre,
+            range().insert_header(IfRange::Date(before))
+        ));
+
+        // If-Range without Range is ignored
+        let req = TestRequest::get().insert_header((header::IF_RANGE, "\"v2\""));
+        assert!(honor_range(&pre, req));
+    }
+}
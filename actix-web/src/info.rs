@@ -1,4 +1,7 @@
-use std::{convert::Infallible, net::SocketAddr};
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
 
 use actix_utils::future::{err, ok, Ready};
 use derive_more::derive::{Display, Error};
@@ -35,6 +38,11 @@ fn bare_address(val: &str) -> &str {
     }
 }
 
+/// Parses an IP address that may have a port and, for IPv6, square brackets.
+pub(crate) fn parse_ip(val: &str) -> Option<IpAddr> {
+    val.parse().or_else(|_| bare_address(val).parse()).ok()
+}
+
 /// Extracts and trims first value for given header name.
 fn first_header_value<'a>(req: &'a RequestHead, name: &'_ HeaderName) -> Option<&'a str> {
     let hdr = req.headers.get(name)?.to_str().ok()?;
@@ -70,6 +78,10 @@ fn first_header_value<'a>(req: &'a RequestHead, name: &'_ HeaderName) -> Option<
 /// If the older, related headers are also present (eg. `X-Forwarded-For`), then `Forwarded`
 /// is preferred.
 ///
+/// By default, forwarding headers are trusted regardless of who sent them. Register
+/// [`TrustedProxies`] with [`App::app_data()`](crate::App::app_data) to only honor them when they
+/// are sent by known proxies.
+///
 /// [rfc7239]: https://datatracker.ietf.org/doc/html/rfc7239
 /// [rfc7239-62]: https://datatracker.ietf.org/doc/html/rfc7239#section-6.2
 /// [rfc7239-63]: https://datatracker.ietf.org/doc/html/rfc7239#section-6.3
@@ -82,14 +94,28 @@ pub struct ConnectionInfo {
 }
 
 impl ConnectionInfo {
-    pub(crate) fn new(req: &RequestHead, cfg: &AppConfig) -> ConnectionInfo {
+    pub(crate) fn new(
+        req: &RequestHead,
+        cfg: &AppConfig,
+        trusted_proxies: Option<&TrustedProxies>,
+    ) -> ConnectionInfo {
+        let peer_ip = req.peer_addr.map(|addr| addr.ip());
+
+        // when trusted proxies are configured, forwarding headers are only honored if they were
+        // sent by one of them
+        let honor_forwarded = match trusted_proxies {
+            Some(trusted_proxies) => peer_ip.is_some_and(|ip| trusted_proxies.contains(ip)),
+            None => true,
+        };
+
         let mut host = None;
         let mut scheme = None;
-        let mut realip_remote_addr = None;
+        let mut forwarded_for = Vec::new();
 
         for (name, val) in req
             .headers
             .get_all(&header::FORWARDED)
+            .filter(|_| honor_forwarded)
             .filter_map(|hdr| hdr.to_str().ok())
             // "for=1.2.3.4, for=5.6.7.8; scheme=https"
             .flat_map(|val| val.split(';'))
@@ -104,9 +130,9 @@ impl ConnectionInfo {
             // [(name , val      ), ...                                    ]
             // [("for", "1.2.3.4"), ("for", "5.6.7.8"), ("scheme", "https")]
 
-            // taking the first value for each property is correct because spec states that first
-            // "for" value is client and rest are proxies; multiple values other properties have
-            // no defined semantics
+            // taking the first value for properties other than "for" is correct because multiple
+            // values have no defined semantics; "for" values are kept in order since the spec
+            // states that the first is the client and the rest are proxies
             //
             // > In a chain of proxy servers where this is fully utilized, the first
             // > "for" parameter will disclose the client where the request was first
@@ -114,9 +140,13 @@ impl ConnectionInfo {
             // --- https://datatracker.ietf.org/doc/html/rfc7239#section-5.2
 
             match name.trim().to_lowercase().as_str() {
-                "for" => realip_remote_addr.get_or_insert_with(|| bare_address(unquote(val))),
-                "proto" => scheme.get_or_insert_with(|| unquote(val)),
-                "host" => host.get_or_insert_with(|| unquote(val)),
+                "for" => forwarded_for.push(bare_address(unquote(val))),
+                "proto" => {
+                    scheme.get_or_insert_with(|| unquote(val));
+                }
+                "host" => {
+                    host.get_or_insert_with(|| unquote(val));
+                }
                 "by" => {
                     // TODO: implement https://datatracker.ietf.org/doc/html/rfc7239#section-5.1
                     continue;
@@ -125,25 +155,46 @@ impl ConnectionInfo {
             };
         }
 
+        let forwarded_header = |name| {
+            if honor_forwarded {
+                first_header_value(req, name)
+            } else {
+                None
+            }
+        };
+
         let scheme = scheme
-            .or_else(|| first_header_value(req, &X_FORWARDED_PROTO))
+            .or_else(|| forwarded_header(&X_FORWARDED_PROTO))
             .or_else(|| req.uri.scheme().map(Scheme::as_str))
             .or_else(|| Some("https").filter(|_| cfg.secure()))
             .unwrap_or("http")
             .to_owned();
 
         let host = host
-            .or_else(|| first_header_value(req, &X_FORWARDED_HOST))
+            .or_else(|| forwarded_header(&X_FORWARDED_HOST))
             .or_else(|| req.headers.get(&header::HOST)?.to_str().ok())
             .or_else(|| req.uri.authority().map(Authority::as_str))
             .unwrap_or_else(|| cfg.host())
             .to_owned();
 
-        let realip_remote_addr = realip_remote_addr
-            .or_else(|| first_header_value(req, &X_FORWARDED_FOR))
-            .map(str::to_owned);
+        if forwarded_for.is_empty() && honor_forwarded {
+            forwarded_for.extend(
+                req.headers
+                    .get_all(&X_FORWARDED_FOR)
+                    .filter_map(|hdr| hdr.to_str().ok())
+                    .flat_map(|val| val.split(','))
+                    .map(str::trim)
+                    .filter(|val| !val.is_empty()),
+            );
+        }
+
+        let realip_remote_addr = match trusted_proxies {
+            Some(trusted_proxies) => trusted_proxies.client_addr(&forwarded_for),
+            None => forwarded_for.first().copied(),
+        }
+        .map(str::to_owned);
 
-        let peer_addr = req.peer_addr.map(|addr| addr.ip().to_string());
+        let peer_addr = peer_ip.map(|ip| ip.to_string());
 
         ConnectionInfo {
             host,
@@ -160,10 +211,15 @@ impl ConnectionInfo {
     /// - `X-Forwarded-For` header
     /// - peer address of opened socket (same as [`remote_addr`](Self::remote_addr))
     ///
+    /// If [`TrustedProxies`] are configured, the forwarding headers are only used when the peer is
+    /// a trusted proxy and the address chain is walked from right to left, skipping trusted
+    /// proxies, to find the client.
+    ///
     /// # Security
-    /// Do not use this function for security purposes unless you can be sure that the `Forwarded`
-    /// and `X-Forwarded-For` headers cannot be spoofed by the client. If you are running without a
-    /// proxy then [obtaining the peer address](Self::peer_addr) would be more appropriate.
+    /// Without [`TrustedProxies`], do not use this function for security purposes unless you can be
+    /// sure that the `Forwarded` and `X-Forwarded-For` headers cannot be spoofed by the client. If
+    /// you are running without a proxy then [obtaining the peer address](Self::peer_addr) would be
+    /// more appropriate.
     #[inline]
     pub fn realip_remote_addr(&self) -> Option<&str> {
         self.realip_remote_addr
@@ -219,6 +275,153 @@ impl FromRequest for ConnectionInfo {
     }
 }
 
+/// Proxies trusted to set forwarding headers, used when resolving [`ConnectionInfo`].
+///
+/// When registered with [`App::app_data()`](crate::App::app_data), the `Forwarded`,
+/// `X-Forwarded-For`, `X-Forwarded-Host` and `X-Forwarded-Proto` headers are ignored unless the
+/// peer address is in one of the trusted ranges. The client's [real IP
+/// address](ConnectionInfo::realip_remote_addr) is then found by walking the forwarded address
+/// chain from right to left, skipping addresses of trusted proxies, up to a maximum number of
+/// [hops](Self::hops).
+///
+/// Only configuration registered at the `App` level is used.
+///
+/// # Examples
+/// ```
+/// use actix_web::{dev::TrustedProxies, App};
+///
+/// let app = App::new().app_data(
+///     TrustedProxies::new()
+///         .trust("10.0.0.0/8")
+///         .trust("fd00::/8")
+///         .hops(2),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrustedProxies {
+    ranges: Vec<IpRange>,
+    hops: usize,
+}
+
+impl TrustedProxies {
+    /// Constructs a configuration that trusts no proxies.
+    pub fn new() -> Self {
+        Self {
+            ranges: Vec::new(),
+            hops: usize::MAX,
+        }
+    }
+
+    /// Trusts proxies whose address is within the given CIDR range, such as `10.0.0.0/8`. A single
+    /// address, such as `127.0.0.1`, is also accepted.
+    ///
+    /// # Panics
+    /// Panics if `range` is not a valid IP address or CIDR range.
+    pub fn trust(mut self, range: &str) -> Self {
+        let range = range
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid trusted proxy range: {range}"));
+
+        self.ranges.push(range);
+        self
+    }
+
+    /// Sets the maximum number of trusted proxies in front of the application, including the peer.
+    ///
+    /// Forwarded addresses further than this many hops away are not walked over even if they are
+    /// in a trusted range. Defaults to no limit.
+    ///
+    /// # Panics
+    /// Panics if `hops` is zero.
+    pub fn hops(mut self, hops: usize) -> Self {
+        assert!(hops > 0, "TrustedProxies must allow at least one hop");
+
+        self.hops = hops;
+        self
+    }
+
+    /// Returns true if `ip` is a trusted proxy.
+    fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+
+    /// Walks a forwarded address chain from right to left, assuming the peer is trusted, and
+    /// returns the first address that is not a trusted proxy.
+    fn client_addr<'a>(&self, chain: &[&'a str]) -> Option<&'a str> {
+        for (idx, &addr) in chain.iter().rev().enumerate() {
+            // the peer counts as the first hop
+            let hops = idx + 1;
+
+            if hops >= self.hops || !parse_ip(addr).is_some_and(|ip| self.contains(ip)) {
+                return Some(addr);
+            }
+        }
+
+        // every address is a trusted proxy
+        chain.first().copied()
+    }
+}
+
+impl Default for TrustedProxies {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An IP address range in CIDR notation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IpRange {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    /// Returns true if `ip` is in the range. IPv4-mapped IPv6 addresses must be canonicalized.
+    pub(crate) fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                (u32::from(net) ^ u32::from(ip)) & mask == 0
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                (u128::from(net) ^ u128::from(ip)) & mask == 0
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::str::FromStr for IpRange {
+    type Err = ();
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match val.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (val, None),
+        };
+
+        let addr = addr.parse::<IpAddr>().map_err(|_| ())?.to_canonical();
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse().map_err(|_| ())?,
+            None => max_prefix,
+        };
+
+        if prefix > max_prefix {
+            return Err(());
+        }
+
+        Ok(Self { addr, prefix })
+    }
+}
+
 /// Extractor for peer's socket address.
 ///
 /// Also see [`HttpRequest::peer_addr`] and [`ConnectionInfo::peer_addr`].
@@ -486,4 +689,91 @@ mod tests {
         let conn_info = ConnectionInfo::extract(&req).await.unwrap();
         assert_eq!(conn_info.realip_remote_addr().unwrap(), "127.0.0.1");
     }
+
+    #[test]
+    fn trusted_proxies_untrusted_peer() {
+        let req = TestRequest::default()
+            .app_data(TrustedProxies::new().trust("10.0.0.0/8"))
+            .peer_addr("192.0.2.1:8080".parse().unwrap())
+            .insert_header((header::HOST, "rust-lang.org"))
+            .insert_header((
+                header::FORWARDED,
+                "for=198.51.100.1; proto=https; host=evil.com",
+            ))
+            .insert_header((X_FORWARDED_FOR, "198.51.100.2"))
+            .insert_header((X_FORWARDED_HOST, "evil.com"))
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .to_http_request();
+
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.1"));
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.scheme(), "http");
+    }
+
+    #[test]
+    fn trusted_proxies_walk_chain() {
+        let trusted = TrustedProxies::new()
+            .trust("10.0.0.0/8")
+            .trust("2001:db8::/32");
+
+        // spoofed leftmost entry is skipped since the client's address is appended by the proxy
+        let req = TestRequest::default()
+            .app_data(trusted.clone())
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "1.1.1.1, 203.0.113.7, 10.0.0.2"))
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .to_http_request();
+
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("203.0.113.7"));
+        assert_eq!(info.scheme(), "https");
+
+        let req = TestRequest::default()
+            .app_data(trusted.clone())
+            .peer_addr("[::ffff:10.0.0.1]:8080".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                r#"for=203.0.113.7, for="[2001:db8::1]:4711", for=10.1.2.3"#,
+            ))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("203.0.113.7")
+        );
+
+        // all entries trusted
+        let req = TestRequest::default()
+            .app_data(trusted.clone())
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "10.0.0.3, 10.0.0.2"))
+            .to_http_request();
+        assert_eq!(req.connection_info().realip_remote_addr(), Some("10.0.0.3"));
+
+        // hop limit
+        let req = TestRequest::default()
+            .app_data(trusted.hops(2))
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "203.0.113.7, 10.0.0.3, 10.0.0.2"))
+            .to_http_request();
+        assert_eq!(req.connection_info().realip_remote_addr(), Some("10.0.0.3"));
+    }
+
+    #[test]
+    fn ip_ranges() {
+        let range: IpRange = "192.168.0.0/16".parse().unwrap();
+        assert!(range.contains("192.168.1.1".parse().unwrap()));
+        assert!(!range.contains("192.169.0.1".parse().unwrap()));
+        assert!(!range.contains("::1".parse().unwrap()));
+
+        let range: IpRange = "0.0.0.0/0".parse().unwrap();
+        assert!(range.contains("8.8.8.8".parse().unwrap()));
+
+        let range: IpRange = "::1".parse().unwrap();
+        assert!(range.contains("::1".parse().unwrap()));
+        assert!(!range.contains("::2".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("localhost".parse::<IpRange>().is_err());
+    }
 }
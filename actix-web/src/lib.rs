@@ -68,7 +68,7 @@
 //! - `rustls-0_21` - HTTPS support via `rustls` 0.21 crate, supports `HTTP/2`
 //! - `rustls-0_22` - HTTPS support via `rustls` 0.22 crate, supports `HTTP/2`
 //! - `rustls-0_23` - HTTPS support via `rustls` 0.23 crate, supports `HTTP/2`
-//! - `secure-cookies` - secure cookies support
+//! - `secure-cookies` - secure cookies support and [cookie-backed sessions](session)
 
 #![doc(html_logo_url = "https://actix.rs/img/logo.png")]
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
@@ -85,6 +85,7 @@ mod config;
 mod data;
 pub mod dev;
 pub mod error;
+mod expiry;
 mod extract;
 pub mod guard;
 mod handler;
@@ -103,6 +104,8 @@ pub mod rt;
 mod scope;
 mod server;
 mod service;
+#[cfg(feature = "secure-cookies")]
+pub mod session;
 pub mod test;
 mod thin_data;
 pub(crate) mod types;
@@ -0,0 +1,287 @@
+//! For middleware documentation, see [`HttpAuthentication`].
+
+use std::{fmt, future::Future, marker::PhantomData, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::EitherBody,
+    dev::{ServiceRequest, ServiceResponse},
+    web::{BasicAuth, BearerAuth},
+    Error, FromRequest,
+};
+
+/// Middleware for authenticating requests using credentials from the `Authorization` header.
+///
+/// Credentials are extracted from each request using an extractor such as [`BasicAuth`] or
+/// [`BearerAuth`] and passed, along with the request, to an async validator. Requests without valid
+/// credentials are rejected using the extractor's error; for the built-in extractors this is a
+/// `401 Unauthorized` response with a `WWW-Authenticate` challenge configured by
+/// [`BasicAuthConfig`] or [`BearerAuthConfig`].
+///
+/// The validator either returns the request, which is then passed to the wrapped service, or an
+/// error along with the request, which is used to respond. An [`AuthenticationError`] produces a
+/// challenge response.
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     dev::ServiceRequest,
+///     middleware::HttpAuthentication,
+///     web::{self, BearerAuth, BearerAuthConfig},
+///     App, Error, HttpResponse,
+/// };
+///
+/// async fn validator(
+///     req: ServiceRequest,
+///     auth: BearerAuth,
+/// ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
+///     if auth.token() == "s3cr3t" {
+///         Ok(req)
+///     } else {
+///         let config = req
+///             .app_data::<BearerAuthConfig>()
+///             .cloned()
+///             .unwrap_or_default();
+///
+///         Err((config.error("invalid_token").into(), req))
+///     }
+/// }
+///
+/// let app = App::new()
+///     .app_data(BearerAuthConfig::default().realm("api"))
+///     .wrap(HttpAuthentication::bearer(validator))
+///     .route("/", web::get().to(HttpResponse::Ok));
+/// ```
+///
+/// [`BasicAuthConfig`]: crate::web::BasicAuthConfig
+/// [`BearerAuthConfig`]: crate::web::BearerAuthConfig
+/// [`AuthenticationError`]: crate::web::AuthenticationError
+pub struct HttpAuthentication<T, F> {
+    validator: Rc<F>,
+    _credentials: PhantomData<T>,
+}
+
+impl<T, F, O> HttpAuthentication<T, F>
+where
+    T: FromRequest,
+    F: Fn(ServiceRequest, T) -> O,
+    O: Future<Output = Result<ServiceRequest, (Error, ServiceRequest)>>,
+{
+    /// Constructs authentication middleware using credentials extracted by `T`.
+    pub fn with_fn(validator: F) -> Self {
+        Self {
+            validator: Rc::new(validator),
+            _credentials: PhantomData,
+        }
+    }
+}
+
+impl<F, O> HttpAuthentication<BasicAuth, F>
+where
+    F: Fn(ServiceRequest, BasicAuth) -> O,
+    O: Future<Output = Result<ServiceRequest, (Error, ServiceRequest)>>,
+{
+    /// Constructs authentication middleware for HTTP Basic authentication.
+    pub fn basic(validator: F) -> Self {
+        Self::with_fn(validator)
+    }
+}
+
+impl<F, O> HttpAuthentication<BearerAuth, F>
+where
+    F: Fn(ServiceRequest, BearerAuth) -> O,
+    O: Future<Output = Result<ServiceRequest, (Error, ServiceRequest)>>,
+{
+    /// Constructs authentication middleware for `Bearer` token authentication.
+    pub fn bearer(validator: F) -> Self {
+        Self::with_fn(validator)
+    }
+}
+
+impl<T, F> Clone for HttpAuthentication<T, F> {
+    fn clone(&self) -> Self {
+        Self {
+            validator: Rc::clone(&self.validator),
+            _credentials: PhantomData,
+        }
+    }
+}
+
+impl<T, F> fmt::Debug for HttpAuthentication<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpAuthentication").finish_non_exhaustive()
+    }
+}
+
+impl<S, B, T, F, O> Transform<S, ServiceRequest> for HttpAuthentication<T, F>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    T: FromRequest + 'static,
+    F: Fn(ServiceRequest, T) -> O + 'static,
+    O: Future<Output = Result<ServiceRequest, (Error, ServiceRequest)>> + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = HttpAuthenticationMiddleware<S, T, F>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HttpAuthenticationMiddleware {
+            service: Rc::new(service),
+            validator: Rc::clone(&self.validator),
+            _credentials: PhantomData,
+        }))
+    }
+}
+
+/// Service created by [`HttpAuthentication`] middleware.
+pub struct HttpAuthenticationMiddleware<S, T, F> {
+    service: Rc<S>,
+    validator: Rc<F>,
+    _credentials: PhantomData<T>,
+}
+
+impl<S, B, T, F, O> Service<ServiceRequest> for HttpAuthenticationMiddleware<S, T, F>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    T: FromRequest + 'static,
+    F: Fn(ServiceRequest, T) -> O + 'static,
+    O: Future<Output = Result<ServiceRequest, (Error, ServiceRequest)>> + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let validator = Rc::clone(&self.validator);
+
+        Box::pin(async move {
+            let credentials = match req.extract::<T>().await {
+                Ok(credentials) => credentials,
+                Err(err) => return Ok(req.error_response(err).map_into_right_body()),
+            };
+
+            match validator(req, credentials).await {
+                Ok(req) => Ok(service.call(req).await?.map_into_left_body()),
+                Err((err, req)) => Ok(req.error_response(err).map_into_right_body()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{
+            header::{self, Authorization},
+            StatusCode,
+        },
+        test::{self, TestRequest},
+        web::{self, BasicAuthConfig},
+        App, HttpMessage as _, HttpResponse,
+    };
+
+    async fn validator(
+        req: ServiceRequest,
+        auth: BasicAuth,
+    ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
+        if auth.user_id() == "admin" && auth.password() == "hunter2" {
+            req.extensions_mut().insert(auth);
+            Ok(req)
+        } else {
+            let err = req
+                .app_data::<BasicAuthConfig>()
+                .cloned()
+                .unwrap_or_default()
+                .challenge();
+
+            Err((err.into(), req))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn validates_credentials() {
+        let app = test::init_service(
+            App::new()
+                .app_data(BasicAuthConfig::default().realm("Admin area"))
+                .wrap(HttpAuthentication::basic(validator))
+                .route(
+                    "/",
+                    web::get().to(|auth: web::ReqData<BasicAuth>| async move {
+                        auth.user_id().to_owned()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get().to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Basic realm=\"Admin area\", charset=\"UTF-8\""
+        );
+
+        let req = TestRequest::get()
+            .insert_header(Authorization::basic("admin", "wrong"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(res.headers().contains_key(header::WWW_AUTHENTICATE));
+
+        let req = TestRequest::get()
+            .insert_header(Authorization::basic("admin", "hunter2"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "admin");
+    }
+
+    #[actix_rt::test]
+    async fn bearer_error_codes() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::BearerAuthConfig::default().realm("api"))
+                .wrap(HttpAuthentication::bearer(
+                    |req: ServiceRequest, auth: BearerAuth| async move {
+                        if auth.token() == "valid" {
+                            Ok(req)
+                        } else {
+                            let err = web::BearerAuthConfig::default()
+                                .realm("api")
+                                .error("invalid_token");
+                            Err((err.into(), req))
+                        }
+                    },
+                ))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .insert_header(Authorization::bearer("expired"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer realm=\"api\", error=\"invalid_token\""
+        );
+
+        let req = TestRequest::get()
+            .insert_header(Authorization::bearer("valid"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
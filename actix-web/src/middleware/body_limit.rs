@@ -0,0 +1,339 @@
+//! For middleware documentation, see [`BodyLimit`].
+
+use std::{
+    cell::Cell,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_http::{error::PayloadError, BoxedPayloadStream};
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use bytes::Bytes;
+use futures_core::{future::LocalBoxFuture, ready, Stream};
+use pin_project_lite::pin_project;
+
+use crate::{
+    body::EitherBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::CONTENT_LENGTH,
+    Error, HttpMessage as _,
+};
+
+/// Middleware for limiting the size of request bodies.
+///
+/// The limit applies to every request, regardless of which extractor, if any, reads the body. This
+/// includes handlers that read the raw [`Payload`](crate::web::Payload) stream, which is otherwise
+/// unlimited.
+///
+/// Reading the body fails with [`PayloadError::Overflow`] once the limit is exceeded, which
+/// extractors turn into a `413 Payload Too Large` response. Requests with a `Content-Length`
+/// greater than the limit fail as soon as their body is read, without any of it being received.
+///
+/// The limit applies to the body as received, before any content decoding. Limits set by
+/// extractor configs, such as [`PayloadConfig`](crate::web::PayloadConfig), still apply.
+///
+/// `BodyLimit` can be registered on an app, scope or resource. When nested, the innermost limit
+/// takes effect, so an app-wide limit can be raised or lowered for specific scopes or resources.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::BodyLimit, web, App, HttpResponse};
+///
+/// let app = App::new()
+///     .wrap(BodyLimit::new(64 * 1024))
+///     .route("/items", web::post().to(HttpResponse::Ok))
+///     .service(
+///         web::resource("/upload")
+///             .wrap(BodyLimit::new(100 * 1024 * 1024))
+///             .route(web::post().to(|_body: web::Payload| async { HttpResponse::Ok() })),
+///     );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimit {
+    limit: usize,
+}
+
+impl BodyLimit {
+    /// Constructs middleware that limits request bodies to `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for BodyLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = BodyLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(BodyLimitMiddleware {
+            service: Rc::new(service),
+            limit: self.limit,
+        }))
+    }
+}
+
+/// Limit of a request's body, shared between nested [`BodyLimit`] middleware.
+///
+/// The outermost middleware limits the payload; inner ones replace the limit as they are called.
+#[derive(Clone)]
+struct SharedLimit(Rc<Cell<usize>>);
+
+/// Service created by [`BodyLimit`] middleware.
+pub struct BodyLimitMiddleware<S> {
+    service: Rc<S>,
+    limit: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for BodyLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let shared = req.extensions().get::<SharedLimit>().cloned();
+
+        match shared {
+            // payload is already limited by an outer `BodyLimit`
+            Some(SharedLimit(limit)) => limit.set(self.limit),
+
+            None => {
+                let limit = Rc::new(Cell::new(self.limit));
+                req.extensions_mut().insert(SharedLimit(Rc::clone(&limit)));
+
+                let content_length = req
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|len| len.to_str().ok())
+                    .and_then(|len| len.parse::<u64>().ok());
+
+                let payload = req.take_payload();
+
+                if !matches!(payload, Payload::None) {
+                    let payload: BoxedPayloadStream = Box::pin(LimitedPayload {
+                        payload,
+                        limit,
+                        content_length,
+                        read: 0,
+                        overflowed: false,
+                    });
+
+                    req.set_payload(Payload::from(payload));
+                }
+            }
+        }
+
+        let service = Rc::clone(&self.service);
+        Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) })
+    }
+}
+
+pin_project! {
+    /// Payload stream that fails once more than `limit` bytes are read, or before reading anything
+    /// if the `Content-Length` is greater than `limit`.
+    struct LimitedPayload {
+        #[pin]
+        payload: Payload,
+        limit: Rc<Cell<usize>>,
+        content_length: Option<u64>,
+        read: usize,
+        overflowed: bool,
+    }
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        if *this.overflowed {
+            return Poll::Ready(None);
+        }
+
+        // read the limit on each poll since it is set by the innermost middleware
+        let limit = this.limit.get();
+
+        if this.content_length.is_some_and(|len| len > limit as u64) {
+            *this.overflowed = true;
+            return Poll::Ready(Some(Err(PayloadError::Overflow)));
+        }
+
+        match ready!(this.payload.poll_next(cx)) {
+            Some(Ok(chunk)) if chunk.len() > limit.saturating_sub(*this.read) => {
+                *this.overflowed = true;
+                Poll::Ready(Some(Err(PayloadError::Overflow)))
+            }
+            Some(Ok(chunk)) => {
+                *this.read += chunk.len();
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            item => Poll::Ready(item),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService as _;
+    use futures_util::stream;
+
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[actix_rt::test]
+    async fn rejects_large_content_length() {
+        let app = test::init_service(
+            App::new()
+                .wrap(BodyLimit::new(4))
+                .route("/", web::post().to(|body: String| async move { body })),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .insert_header((CONTENT_LENGTH, 4))
+            .set_payload("1234")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .insert_header((CONTENT_LENGTH, 5))
+            .set_payload("12345")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // body is not read at all
+        let mut payload = Box::pin(LimitedPayload {
+            payload: Payload::from(Box::pin(stream::poll_fn(|_| -> Poll<Option<_>> {
+                panic!("payload read")
+            })) as BoxedPayloadStream),
+            limit: Rc::new(Cell::new(4)),
+            content_length: Some(5),
+            read: 0,
+            overflowed: false,
+        });
+        let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+        assert!(matches!(
+            payload.as_mut().poll_next(&mut cx),
+            Poll::Ready(Some(Err(PayloadError::Overflow)))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn innermost_limit_applies() {
+        let echo = || web::post().to(|body: String| async move { body });
+
+        let app = test::init_service(
+            App::new()
+                .wrap(BodyLimit::new(4))
+                .route("/", echo())
+                .service(
+                    web::resource("/upload")
+                        .wrap(BodyLimit::new(8))
+                        .route(echo()),
+                )
+                .service(
+                    web::scope("/small")
+                        .wrap(BodyLimit::new(2))
+                        .route("", echo()),
+                ),
+        )
+        .await;
+
+        let post = |uri: &str, body: &'static str| {
+            TestRequest::post()
+                .uri(uri)
+                .insert_header((CONTENT_LENGTH, body.len()))
+                .set_payload(body)
+                .to_request()
+        };
+
+        let res = test::call_service(&app, post("/", "12345678")).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // resource limit raises the app limit
+        let res = test::call_service(&app, post("/upload", "12345678")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "12345678");
+
+        let res = test::call_service(&app, post("/upload", "123456789")).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // scope limit lowers it
+        let res = test::call_service(&app, post("/small", "123")).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn limits_payload_stream() {
+        let srv = |mut req: ServiceRequest| async move {
+            let payload = req.extract::<web::Payload>().await?;
+
+            let res = match payload.to_bytes().await {
+                Ok(body) => HttpResponse::Ok().body(body),
+                Err(err) => HttpResponse::from_error(err),
+            };
+
+            Ok(req.into_response(res))
+        };
+
+        let limit = BodyLimit::new(6)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let chunked = |chunks: &'static [&'static str]| {
+            let stream = stream::iter(
+                chunks
+                    .iter()
+                    .map(|chunk| Ok(Bytes::from_static(chunk.as_bytes()))),
+            );
+            let stream: BoxedPayloadStream = Box::pin(stream);
+            Payload::from(stream)
+        };
+
+        let mut req = TestRequest::post().to_srv_request();
+        req.set_payload(chunked(&["1234", "56"]));
+        let res = limit.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "123456");
+
+        let mut req = TestRequest::post().to_srv_request();
+        req.set_payload(chunked(&["1234", "567"]));
+        let res = limit.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // raw payload extractor is limited too
+        let app = test::init_service(App::new().wrap(BodyLimit::new(4)).route(
+            "/",
+            web::post().to(|body: web::Payload| async move {
+                body.to_bytes().await.map(|_| HttpResponse::Ok())
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::post().set_payload("12345").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
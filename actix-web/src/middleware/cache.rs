@@ -0,0 +1,825 @@
+//! For middleware documentation, see [`ResponseCache`].
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, mem,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use bytes::Bytes;
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::{self, BodySize, BoxBody, EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        header::{self, CacheDirective, HeaderMap, HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+    Error, HttpResponse,
+};
+
+/// Default size, in bytes, of the largest response body that will be cached.
+const DEFAULT_MAX_BODY_SIZE: u64 = 1024 * 1024;
+
+/// Default capacity, in bytes, of [`InMemoryResponseCacheStore`].
+const DEFAULT_STORE_SIZE: usize = 64 * 1024 * 1024;
+
+/// A response stored by [`ResponseCache`] middleware.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// Response status code.
+    pub status: StatusCode,
+
+    /// Response headers.
+    pub headers: HeaderMap,
+
+    /// Response body.
+    pub body: Bytes,
+
+    /// Values of the request headers named by the response's `Vary` header, which select this
+    /// variant of the resource.
+    pub vary: Vec<(HeaderName, Option<HeaderValue>)>,
+
+    /// Time at which the response was stored.
+    pub stored_at: SystemTime,
+
+    /// Time for which the response is fresh after being stored.
+    pub max_age: Duration,
+
+    /// Time after becoming stale during which the response may still be served while it is
+    /// revalidated in the background.
+    pub stale_while_revalidate: Duration,
+}
+
+impl CachedResponse {
+    /// Returns the time elapsed since the response was stored.
+    pub fn age(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.stored_at).unwrap_or_default()
+    }
+
+    /// Returns true if the response can no longer be served, even while revalidating.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.age(now) > self.max_age + self.stale_while_revalidate
+    }
+
+    /// Returns true if `other` is the same variant of a resource, i.e., it was selected by the same
+    /// request header values.
+    pub fn is_same_variant(&self, other: &CachedResponse) -> bool {
+        self.vary == other.vary
+    }
+
+    /// Returns the approximate number of bytes used by the response.
+    pub fn size(&self) -> usize {
+        let headers = self
+            .headers
+            .iter()
+            .chain(
+                self.vary
+                    .iter()
+                    .filter_map(|(name, val)| Some((name, val.as_ref()?))),
+            )
+            .map(|(name, val)| name.as_str().len() + val.len())
+            .sum::<usize>();
+
+        mem::size_of::<Self>() + headers + self.body.len()
+    }
+
+    /// Returns true if this variant was selected by the same header values as `req` carries.
+    fn matches(&self, req: &ServiceRequest) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, val)| vary_value(req.headers(), name).as_ref() == val.as_ref())
+    }
+}
+
+/// Storage backend for [`ResponseCache`].
+///
+/// Responses are stored under a key derived from the request method and URL, including the scheme
+/// and host, so virtual hosts sharing a store do not see each other's responses. Since a resource
+/// may have several variants, selected using the request headers named by the `Vary` response
+/// header, each key maps to a list of responses. See
+/// [sharing state between workers](crate::middleware#sharing-state-between-workers).
+pub trait ResponseCacheStore {
+    /// Returns the stored variants of the resource identified by `key`.
+    fn get(&self, key: &str) -> LocalBoxFuture<'static, Result<Vec<CachedResponse>, Error>>;
+
+    /// Stores a variant of the resource identified by `key`, replacing any stored variant that
+    /// [is the same variant](CachedResponse::is_same_variant).
+    fn insert(&self, key: &str, res: CachedResponse) -> LocalBoxFuture<'static, Result<(), Error>>;
+}
+
+#[derive(Debug)]
+struct LruEntry {
+    variants: Vec<CachedResponse>,
+    size: usize,
+    tick: u64,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<String, LruEntry>,
+
+    /// Keys ordered from least to most recently used.
+    order: BTreeMap<u64, String>,
+
+    size: usize,
+    max_size: usize,
+    tick: u64,
+}
+
+impl Lru {
+    fn get(&mut self, key: &str, now: SystemTime) -> Vec<CachedResponse> {
+        let mut entry = match self.remove(key) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+
+        entry.variants.retain(|res| !res.is_expired(now));
+        entry.size = entry.variants.iter().map(CachedResponse::size).sum();
+
+        let variants = entry.variants.clone();
+
+        if !entry.variants.is_empty() {
+            self.push(key.to_owned(), entry);
+        }
+
+        variants
+    }
+
+    fn insert(&mut self, key: &str, res: CachedResponse) {
+        let mut entry = self.remove(key).unwrap_or(LruEntry {
+            variants: Vec::new(),
+            size: 0,
+            tick: 0,
+        });
+
+        entry
+            .variants
+            .retain(|variant| !variant.is_same_variant(&res));
+        entry.variants.push(res);
+
+        // drop the oldest variants of this resource if it would not fit on its own
+        entry.size = entry.variants.iter().map(CachedResponse::size).sum();
+        while entry.size > self.max_size {
+            let res = entry.variants.remove(0);
+            entry.size -= res.size();
+        }
+
+        if !entry.variants.is_empty() {
+            self.push(key.to_owned(), entry);
+        }
+
+        while self.size > self.max_size {
+            match self.order.first_key_value() {
+                Some((_, key)) => {
+                    let key = key.clone();
+                    self.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn push(&mut self, key: String, mut entry: LruEntry) {
+        self.tick += 1;
+        entry.tick = self.tick;
+
+        self.size += entry.size;
+        self.order.insert(entry.tick, key.clone());
+        self.entries.insert(key, entry);
+    }
+
+    fn remove(&mut self, key: &str) -> Option<LruEntry> {
+        let entry = self.entries.remove(key)?;
+
+        self.size -= entry.size;
+        self.order.remove(&entry.tick);
+
+        Some(entry)
+    }
+}
+
+/// In-memory [`ResponseCacheStore`] shared across all workers of a server.
+///
+/// The store holds responses up to a total size in bytes, evicting the least recently used
+/// resources when full. Expired responses are dropped when they are next looked up. Cloning this
+/// type produces a new handle to the same responses.
+#[derive(Clone)]
+pub struct InMemoryResponseCacheStore {
+    lru: Arc<Mutex<Lru>>,
+}
+
+impl InMemoryResponseCacheStore {
+    /// Constructs a new in-memory store holding up to 64MiB of responses.
+    pub fn new() -> Self {
+        Self::with_max_size(DEFAULT_STORE_SIZE)
+    }
+
+    /// Constructs a new in-memory store holding up to `max_size` bytes of responses.
+    pub fn with_max_size(max_size: usize) -> Self {
+        Self {
+            lru: Arc::new(Mutex::new(Lru {
+                max_size,
+                ..Lru::default()
+            })),
+        }
+    }
+
+    /// Returns the approximate number of bytes used by stored responses.
+    pub fn size(&self) -> usize {
+        self.lru.lock().unwrap_or_else(|err| err.into_inner()).size
+    }
+}
+
+impl Default for InMemoryResponseCacheStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for InMemoryResponseCacheStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lru = self.lru.lock().unwrap_or_else(|err| err.into_inner());
+
+        f.debug_struct("InMemoryResponseCacheStore")
+            .field("size", &lru.size)
+            .field("max_size", &lru.max_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResponseCacheStore for InMemoryResponseCacheStore {
+    fn get(&self, key: &str) -> LocalBoxFuture<'static, Result<Vec<CachedResponse>, Error>> {
+        let variants = self
+            .lru
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(key, SystemTime::now());
+
+        Box::pin(ready(Ok(variants)))
+    }
+
+    fn insert(&self, key: &str, res: CachedResponse) -> LocalBoxFuture<'static, Result<(), Error>> {
+        self.lru
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(key, res);
+
+        Box::pin(ready(Ok(())))
+    }
+}
+
+struct Inner {
+    store: Box<dyn ResponseCacheStore>,
+    max_body_size: u64,
+}
+
+/// Middleware for caching responses in a shared store.
+///
+/// Responses to `GET` and `HEAD` requests are stored if their `Cache-Control` header gives them an
+/// explicit freshness lifetime using the `s-maxage` or `max-age` directives. Responses marked
+/// `no-store`, `no-cache` or `private`, responses that set cookies, and responses with
+/// `Vary: *` are never stored. Only bodies of known size up to a limit (1MiB by default) are
+/// stored; streaming and larger responses are passed through unchanged.
+///
+/// Responses are keyed by request method and URL (including the scheme and host, as given by
+/// [`ConnectionInfo`](crate::dev::ConnectionInfo)) and, where the response has a `Vary` header,
+/// by the values of the request headers it names. Fresh responses are served from the cache with
+/// an `Age` header. Stale responses with a `stale-while-revalidate` directive continue to be served
+/// for the given time while a fresh response is fetched in the background.
+///
+/// Requests with an `Authorization` header or a `no-store` cache directive bypass the cache.
+/// Requests with a `no-cache` directive are always forwarded to the wrapped service, but their
+/// responses may still be stored.
+///
+/// Errors from the store are logged and otherwise treated as cache misses.
+///
+/// # Examples
+/// ```no_run
+/// use actix_web::{
+///     http::header::{CacheControl, CacheDirective},
+///     middleware::{InMemoryResponseCacheStore, ResponseCache},
+///     web, App, HttpResponse, HttpServer,
+/// };
+///
+/// # async fn run() -> std::io::Result<()> {
+/// // one store shared by all workers
+/// let store = InMemoryResponseCacheStore::new();
+///
+/// HttpServer::new(move || {
+///     App::new()
+///         .wrap(ResponseCache::new(store.clone()))
+///         .default_service(web::to(|| async {
+///             HttpResponse::Ok()
+///                 .insert_header(CacheControl(vec![CacheDirective::MaxAge(60)]))
+///                 .body("expensive report")
+///         }))
+/// })
+/// .bind(("127.0.0.1", 8080))?
+/// .run()
+/// .await
+/// # }
+/// ```
+pub struct ResponseCache {
+    inner: Rc<Inner>,
+}
+
+impl ResponseCache {
+    /// Constructs response caching middleware that stores responses in `store`.
+    pub fn new(store: impl ResponseCacheStore + 'static) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                store: Box::new(store),
+                max_body_size: DEFAULT_MAX_BODY_SIZE,
+            }),
+        }
+    }
+
+    /// Sets the size, in bytes, of the largest response body that will be cached. Defaults to 1MiB.
+    pub fn max_body_size(mut self, max_body_size: u64) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("ResponseCache must be configured before it is cloned")
+            .max_body_size = max_body_size;
+        self
+    }
+}
+
+impl fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseCache")
+            .field("max_body_size", &self.inner.max_body_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ResponseCache
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = ResponseCacheMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ResponseCacheMiddleware {
+            service: Rc::new(service),
+            inner: Rc::clone(&self.inner),
+            revalidating: Rc::default(),
+        }))
+    }
+}
+
+/// Service created by [`ResponseCache`] middleware.
+pub struct ResponseCacheMiddleware<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+
+    /// Keys of stale responses currently being revalidated by this worker.
+    revalidating: Rc<RefCell<HashSet<String>>>,
+}
+
+impl<S, B> Service<ServiceRequest> for ResponseCacheMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let inner = Rc::clone(&self.inner);
+        let revalidating = Rc::clone(&self.revalidating);
+
+        Box::pin(async move {
+            if !matches!(*req.method(), Method::GET | Method::HEAD)
+                || req.headers().contains_key(header::AUTHORIZATION)
+                || has_directive(req.headers(), &CacheDirective::NoStore)
+            {
+                return Ok(service.call(req).await?.map_into_left_body());
+            }
+
+            let key = cache_key(&req);
+
+            if !has_directive(req.headers(), &CacheDirective::NoCache) {
+                let variants = inner.store.get(&key).await.unwrap_or_else(|err| {
+                    log::warn!("Failed to read from response cache: {}", err);
+                    Vec::new()
+                });
+
+                let now = SystemTime::now();
+
+                if let Some(cached) = variants
+                    .into_iter()
+                    .find(|res| res.matches(&req) && !res.is_expired(now))
+                {
+                    let age = cached.age(now);
+
+                    if age > cached.max_age && revalidating.borrow_mut().insert(key.clone()) {
+                        // the original request is still in use, so revalidate with a copy of it
+                        let req = ServiceRequest::from_request(req.request().detached());
+                        let guard = RevalidatingGuard { revalidating, key };
+
+                        actix_rt::spawn(async move {
+                            if let Ok(res) = service.call(req).await {
+                                let _ = store_response(&inner, &guard.key, res).await;
+                            }
+
+                            drop(guard);
+                        });
+                    }
+
+                    let mut res = HttpResponse::with_body(cached.status, cached.body);
+                    *res.headers_mut() = cached.headers;
+                    res.headers_mut()
+                        .insert(header::AGE, HeaderValue::from(age.as_secs()));
+
+                    return Ok(req
+                        .into_response(res.map_into_boxed_body())
+                        .map_into_right_body());
+                }
+            }
+
+            let res = service.call(req).await?;
+            store_response(&inner, &key, res).await
+        })
+    }
+}
+
+/// Releases a key being revalidated when dropped, including if revalidation panics.
+struct RevalidatingGuard {
+    revalidating: Rc<RefCell<HashSet<String>>>,
+    key: String,
+}
+
+impl Drop for RevalidatingGuard {
+    fn drop(&mut self) {
+        self.revalidating.borrow_mut().remove(&self.key);
+    }
+}
+
+/// Returns the key under which responses to `req` are stored.
+fn cache_key(req: &ServiceRequest) -> String {
+    let info = req.connection_info();
+    let path = req.uri().path_and_query().map_or("/", |path| path.as_str());
+
+    format!(
+        "{} {}://{}{}",
+        req.method(),
+        info.scheme(),
+        info.host(),
+        path
+    )
+}
+
+/// Stores `res` if it is cacheable and returns it.
+async fn store_response<B>(
+    inner: &Inner,
+    key: &str,
+    res: ServiceResponse<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error>
+where
+    B: MessageBody + 'static,
+{
+    let (max_age, stale_while_revalidate) = match freshness(&res) {
+        Some(freshness) => freshness,
+        None => return Ok(res.map_into_left_body()),
+    };
+
+    match res.response().body().size() {
+        BodySize::Sized(size) if size <= inner.max_body_size => {}
+        _ => return Ok(res.map_into_left_body()),
+    }
+
+    let vary = match vary(&res) {
+        Some(vary) => vary,
+        None => return Ok(res.map_into_left_body()),
+    };
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+
+    let body = match body.try_into_bytes() {
+        Ok(body) => body,
+        Err(body) => body::to_bytes(body).await.map_err(Into::into)?,
+    };
+
+    let cached = CachedResponse {
+        status: res.status(),
+        headers: res.headers().clone(),
+        body: body.clone(),
+        vary,
+        stored_at: SystemTime::now(),
+        max_age,
+        stale_while_revalidate,
+    };
+
+    if let Err(err) = inner.store.insert(key, cached).await {
+        log::warn!("Failed to write to response cache: {}", err);
+    }
+
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))).map_into_right_body())
+}
+
+/// Returns the freshness lifetime and `stale-while-revalidate` period of a response, or `None` if
+/// it may not be stored in a shared cache.
+fn freshness<B>(res: &ServiceResponse<B>) -> Option<(Duration, Duration)> {
+    if !matches!(
+        res.status().as_u16(),
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    ) || res.headers().contains_key(header::SET_COOKIE)
+    {
+        return None;
+    }
+
+    let directives: Vec<CacheDirective> =
+        header::from_comma_delimited(res.headers().get_all(header::CACHE_CONTROL)).ok()?;
+
+    let mut max_age = None;
+    let mut s_maxage = None;
+    let mut stale_while_revalidate = 0;
+
+    for directive in directives {
+        match directive {
+            CacheDirective::NoStore | CacheDirective::NoCache | CacheDirective::Private => {
+                return None
+            }
+            CacheDirective::MaxAge(secs) => max_age = Some(secs),
+            CacheDirective::SMaxAge(secs) => s_maxage = Some(secs),
+            CacheDirective::Extension(name, Some(secs)) if name == "stale-while-revalidate" => {
+                stale_while_revalidate = secs.parse().unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    let max_age = s_maxage.or(max_age).filter(|&secs| secs > 0)?;
+
+    Some((
+        Duration::from_secs(max_age.into()),
+        Duration::from_secs(stale_while_revalidate),
+    ))
+}
+
+/// Returns the request header values named by the response's `Vary` header, or `None` if the
+/// response varies on `*`.
+fn vary<B>(res: &ServiceResponse<B>) -> Option<Vec<(HeaderName, Option<HeaderValue>)>> {
+    let names: Vec<String> =
+        header::from_comma_delimited(res.headers().get_all(header::VARY)).ok()?;
+
+    let mut vary = Vec::with_capacity(names.len());
+
+    for name in names {
+        if name == "*" {
+            return None;
+        }
+
+        let name = HeaderName::try_from(name).ok()?;
+        let val = vary_value(res.request().headers(), &name);
+        vary.push((name, val));
+    }
+
+    Some(vary)
+}
+
+/// Returns the combined value of a request header named by `Vary`.
+fn vary_value(headers: &HeaderMap, name: &HeaderName) -> Option<HeaderValue> {
+    let mut vals = headers.get_all(name);
+    let first = vals.next()?;
+
+    if vals.len() == 0 {
+        return Some(first.clone());
+    }
+
+    let mut combined = first.as_bytes().to_vec();
+    for val in vals {
+        combined.extend_from_slice(b", ");
+        combined.extend_from_slice(val.as_bytes());
+    }
+
+    HeaderValue::from_bytes(&combined).ok()
+}
+
+/// Returns true if the request's `Cache-Control` header contains `directive`.
+fn has_directive(headers: &HeaderMap, directive: &CacheDirective) -> bool {
+    header::from_comma_delimited::<_, CacheDirective>(headers.get_all(header::CACHE_CONTROL))
+        .is_ok_and(|directives| directives.contains(directive))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        http::header::CacheControl,
+        test::{self, TestRequest},
+        web, App, HttpRequest,
+    };
+
+    fn cached(body: &'static str, max_age: u64, swr: u64, age: u64) -> CachedResponse {
+        CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+            vary: Vec::new(),
+            stored_at: SystemTime::now() - Duration::from_secs(age),
+            max_age: Duration::from_secs(max_age),
+            stale_while_revalidate: Duration::from_secs(swr),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn caches_fresh_responses() {
+        let calls = Rc::new(Cell::new(0));
+        let store = InMemoryResponseCacheStore::new();
+
+        let app = test::init_service(App::new().wrap(ResponseCache::new(store.clone())).route(
+            "/",
+            web::get().to({
+                let calls = Rc::clone(&calls);
+                move |req: HttpRequest| {
+                    calls.set(calls.get() + 1);
+
+                    let lang = req
+                        .headers()
+                        .get(header::ACCEPT_LANGUAGE)
+                        .map(|val| val.to_str().unwrap().to_owned())
+                        .unwrap_or_default();
+
+                    let mut res = HttpResponse::Ok();
+                    res.insert_header(CacheControl(vec![CacheDirective::MaxAge(60)]))
+                        .insert_header((header::VARY, "accept-language"));
+
+                    if req.query_string() == "private" {
+                        res.insert_header(CacheControl(vec![CacheDirective::Private]));
+                    }
+
+                    ready(res.body(format!("hello {lang}")))
+                }
+            }),
+        ))
+        .await;
+
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert!(!res.headers().contains_key(header::AGE));
+        assert_eq!(test::read_body(res).await, "hello ");
+        assert_eq!(calls.get(), 1);
+
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert_eq!(res.headers().get(header::AGE).unwrap(), "0");
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "accept-language");
+        assert_eq!(test::read_body(res).await, "hello ");
+        assert_eq!(calls.get(), 1);
+
+        // variants are selected using the headers named by Vary
+        let req = || TestRequest::get().insert_header((header::ACCEPT_LANGUAGE, "fr"));
+        let res = test::call_service(&app, req().to_request()).await;
+        assert_eq!(test::read_body(res).await, "hello fr");
+        let res = test::call_service(&app, req().to_request()).await;
+        assert!(res.headers().contains_key(header::AGE));
+        assert_eq!(test::read_body(res).await, "hello fr");
+        assert_eq!(calls.get(), 2);
+
+        // no-cache requests are forwarded
+        let req = TestRequest::get()
+            .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+            .to_request();
+        test::call_service(&app, req).await;
+        assert_eq!(calls.get(), 3);
+
+        // private responses are not stored
+        for _ in 0..2 {
+            let req = TestRequest::get().uri("/?private").to_request();
+            test::call_service(&app, req).await;
+        }
+        assert_eq!(calls.get(), 5);
+
+        // other methods are not cached
+        let req = TestRequest::post().to_request();
+        test::call_service(&app, req).await;
+        assert!(store.size() > 0);
+    }
+
+    #[actix_rt::test]
+    async fn serves_stale_while_revalidating() {
+        let calls = Rc::new(Cell::new(0));
+        let store = InMemoryResponseCacheStore::new();
+
+        let app = test::init_service(App::new().wrap(ResponseCache::new(store.clone())).route(
+            "/",
+            web::get().to({
+                let calls = Rc::clone(&calls);
+                move || {
+                    calls.set(calls.get() + 1);
+                    ready(
+                        HttpResponse::Ok()
+                            .insert_header((header::CACHE_CONTROL, "max-age=60"))
+                            .body("fresh"),
+                    )
+                }
+            }),
+        ))
+        .await;
+
+        store
+            .insert("GET http://localhost:8080/", cached("stale", 10, 60, 30))
+            .await
+            .unwrap();
+
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert_eq!(res.headers().get(header::AGE).unwrap(), "30");
+
+        // allow background revalidation to complete while the stale response is still alive
+        actix_rt::task::yield_now().await;
+        assert_eq!(calls.get(), 1);
+        assert_eq!(test::read_body(res).await, "stale");
+
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert_eq!(test::read_body(res).await, "fresh");
+        assert_eq!(calls.get(), 1);
+
+        // responses past their stale-while-revalidate period are not served
+        store
+            .insert("GET http://localhost:8080/", cached("stale", 10, 60, 90))
+            .await
+            .unwrap();
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert!(!res.headers().contains_key(header::AGE));
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn keyed_by_host() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ResponseCache::new(InMemoryResponseCacheStore::new()))
+                .route(
+                    "/",
+                    web::get().to(|req: HttpRequest| {
+                        ready(
+                            HttpResponse::Ok()
+                                .insert_header(CacheControl(vec![CacheDirective::MaxAge(60)]))
+                                .body(req.connection_info().host().to_owned()),
+                        )
+                    }),
+                ),
+        )
+        .await;
+
+        for host in ["a.example.com", "b.example.com", "a.example.com"] {
+            let req = TestRequest::get()
+                .insert_header((header::HOST, host))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(test::read_body(res).await, host);
+        }
+
+        // same host over a different scheme
+        let req = TestRequest::get()
+            .uri("https://a.example.com/")
+            .insert_header((header::HOST, "a.example.com"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(!res.headers().contains_key(header::AGE));
+    }
+
+    #[actix_rt::test]
+    async fn in_memory_store_evicts_least_recently_used() {
+        let size = cached("a", 60, 0, 0).size();
+        let store = InMemoryResponseCacheStore::with_max_size(size * 2);
+
+        store.insert("a", cached("a", 60, 0, 0)).await.unwrap();
+        store.insert("b", cached("b", 60, 0, 0)).await.unwrap();
+        assert_eq!(store.get("a").await.unwrap().len(), 1);
+
+        store.insert("c", cached("c", 60, 0, 0)).await.unwrap();
+        assert_eq!(store.size(), size * 2);
+        assert_eq!(store.get("a").await.unwrap().len(), 1);
+        assert!(store.get("b").await.unwrap().is_empty());
+        assert_eq!(store.get("c").await.unwrap().len(), 1);
+
+        // same variant is replaced
+        store.insert("c", cached("d", 60, 0, 0)).await.unwrap();
+        let variants = store.get("c").await.unwrap();
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].body, "d");
+
+        // expired responses are dropped
+        store.insert("a", cached("a", 10, 0, 20)).await.unwrap();
+        assert!(store.get("a").await.unwrap().is_empty());
+        assert_eq!(store.size(), size);
+    }
+}
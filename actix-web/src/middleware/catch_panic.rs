@@ -0,0 +1,262 @@
+//! For middleware documentation, see [`CatchPanic`].
+
+use std::{
+    any::Any,
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use pin_project_lite::pin_project;
+
+use crate::{
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    Error, HttpResponse,
+};
+
+type Renderer = dyn Fn(&str) -> HttpResponse;
+
+/// Middleware for converting panics in handlers and inner middleware into error responses.
+///
+/// Without this middleware, a panic while handling a request drops the connection, which for
+/// HTTP/2 aborts all other requests multiplexed on it. With it, the panic is caught, logged along
+/// with its message, and turned into an [`Error`] whose response is `500 Internal Server Error`
+/// with an empty body, or the response produced by a [custom renderer](Self::renderer). Outer
+/// middleware see the error as they would any other service error.
+///
+/// The panic message is passed to the renderer but is not included in the default response, so
+/// that internal details are not exposed to clients.
+///
+/// This middleware has no effect if panics are configured to abort. Services that panic may be
+/// left in an inconsistent state, so this should not be relied on for normal error handling.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::CatchPanic, web, App, HttpResponse};
+///
+/// async fn index() -> HttpResponse {
+///     panic!("oops");
+/// }
+///
+/// let app = App::new()
+///     .wrap(CatchPanic::new().renderer(|_msg| {
+///         HttpResponse::InternalServerError().body("Something went wrong.")
+///     }))
+///     .route("/", web::get().to(index));
+/// ```
+#[derive(Clone, Default)]
+pub struct CatchPanic {
+    renderer: Option<Rc<Renderer>>,
+}
+
+impl CatchPanic {
+    /// Constructs middleware that responds to panics with an empty `500 Internal Server Error`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a function for rendering the response to a panic, given the panic message.
+    pub fn renderer<F>(mut self, renderer: F) -> Self
+    where
+        F: Fn(&str) -> HttpResponse + 'static,
+    {
+        self.renderer = Some(Rc::new(renderer));
+        self
+    }
+}
+
+impl fmt::Debug for CatchPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatchPanic")
+            .field("renderer", &self.renderer.is_some())
+            .finish()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CatchPanic
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = CatchPanicMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CatchPanicMiddleware {
+            service,
+            renderer: self.renderer.clone(),
+        }))
+    }
+}
+
+/// Service created by [`CatchPanic`] middleware.
+pub struct CatchPanicMiddleware<S> {
+    service: S,
+    renderer: Option<Rc<Renderer>>,
+}
+
+impl<S, B> Service<ServiceRequest> for CatchPanicMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = CatchPanicFuture<S::Future>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.service.call(req))) {
+            Ok(fut) => CatchPanicFuture::Service {
+                fut,
+                renderer: self.renderer.clone(),
+            },
+            Err(payload) => CatchPanicFuture::Panicked {
+                err: Some(panic_error(payload, self.renderer.as_deref())),
+            },
+        }
+    }
+}
+
+pin_project! {
+    #[doc(hidden)]
+    #[project = CatchPanicProj]
+    pub enum CatchPanicFuture<F> {
+        Service { #[pin] fut: F, renderer: Option<Rc<Renderer>>, },
+        Panicked { err: Option<Error>, },
+    }
+}
+
+impl<F, B> Future for CatchPanicFuture<F>
+where
+    F: Future<Output = Result<ServiceResponse<B>, Error>>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            CatchPanicProj::Service { fut, renderer } => {
+                match panic::catch_unwind(AssertUnwindSafe(|| fut.poll(cx))) {
+                    Ok(poll) => poll,
+                    Err(payload) => Poll::Ready(Err(panic_error(payload, renderer.as_deref()))),
+                }
+            }
+
+            CatchPanicProj::Panicked { err } => Poll::Ready(Err(err
+                .take()
+                .expect("CatchPanicFuture polled after completion"))),
+        }
+    }
+}
+
+/// Logs a caught panic and converts it into an error.
+fn panic_error(payload: Box<dyn Any + Send>, renderer: Option<&Renderer>) -> Error {
+    let msg = if let Some(msg) = payload.downcast_ref::<&str>() {
+        (*msg).to_owned()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    };
+
+    log::error!("Panic while handling request: {msg}");
+
+    let res = match renderer {
+        Some(renderer) => renderer(&msg),
+        None => HttpResponse::InternalServerError().finish(),
+    };
+
+    InternalError::from_response(msg, res).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    async fn panics() -> HttpResponse {
+        panic!("oops");
+    }
+
+    #[actix_rt::test]
+    async fn catches_handler_panic() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CatchPanic::new())
+                .route("/panic", web::get().to(panics))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/panic").to_request();
+        let err = app.call(req).await.unwrap_err();
+        assert_eq!(err.to_string(), "oops");
+
+        let res = err.error_response();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(test::read_body(ServiceResponse::new(
+            TestRequest::default().to_http_request(),
+            res
+        ))
+        .await
+        .is_empty());
+
+        // service is still usable
+        let req = TestRequest::default().to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn custom_renderer() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CatchPanic::new().renderer(|msg| {
+                    HttpResponse::ServiceUnavailable().body(format!("panicked: {msg}"))
+                }))
+                .route("/", web::get().to(panics)),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let res = app.call(req).await.unwrap_err().error_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let res = ServiceResponse::new(TestRequest::default().to_http_request(), res);
+        assert_eq!(test::read_body(res).await, "panicked: oops");
+    }
+
+    #[actix_rt::test]
+    async fn catches_panic_in_call() {
+        let srv = |_: ServiceRequest| -> Ready<Result<ServiceResponse, Error>> {
+            panic!("{}", String::from("sync panic"))
+        };
+
+        let mw = CatchPanic::new()
+            .new_transform(actix_service::fn_service(srv))
+            .await
+            .unwrap();
+
+        let err = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "sync panic");
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
@@ -4,21 +4,21 @@ use std::{
     future::Future,
     marker::PhantomData,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
-use actix_http::encoding::Encoder;
+use actix_http::encoding::{Encoder, EncoderConfig};
 use actix_service::{Service, Transform};
 use actix_utils::future::{ok, Either, Ready};
 use futures_core::ready;
 use mime::Mime;
-use once_cell::sync::Lazy;
 use pin_project_lite::pin_project;
 
 use crate::{
-    body::{EitherBody, MessageBody},
+    body::{BodySize, EitherBody, MessageBody},
     http::{
-        header::{self, AcceptEncoding, ContentEncoding, Encoding, HeaderValue},
+        header::{self, AcceptEncoding, ContentEncoding, Encoding, HeaderValue, Preference},
         StatusCode,
     },
     service::{ServiceRequest, ServiceResponse},
@@ -32,6 +32,17 @@ use crate::{
 /// Payloads are not compressed if the header is not sent. The `compress-*` [feature flags] are also
 /// considered in this selection process.
 ///
+/// When the client rates several encodings equally, the server's preference order is used. By
+/// default this is brotli, zstd, gzip then deflate; see [`encodings`](Self::encodings).
+///
+/// # Compression Policy
+/// By default, every eligible response is compressed using fast compression levels, except those
+/// with an `image/*` or `video/*` content type. This can be tuned using:
+/// - [`level`](Self::level) to trade compression ratio for latency;
+/// - [`min_size`](Self::min_size) to skip small bodies, where compression saves little; and
+/// - [`allow_content_type`](Self::allow_content_type) and
+///   [`deny_content_type`](Self::deny_content_type) to select which responses are compressed.
+///
 /// # Pre-compressed Payload
 /// If you are serving some data that is already using a compressed representation (e.g., a gzip
 /// compressed HTML file from disk) you can signal this to `Compress` by setting an appropriate
@@ -56,6 +67,22 @@ use crate::{
 ///     .default_service(web::to(|| async { HttpResponse::Ok().body("hello world") }));
 /// ```
 ///
+/// Customizing the compression policy:
+/// ```
+/// use actix_web::{http::header::ContentEncoding, middleware::Compress, web, App, HttpResponse};
+///
+/// let compress = Compress::new()
+///     .encodings([ContentEncoding::Zstd, ContentEncoding::Gzip])
+///     .level(ContentEncoding::Zstd, 6)
+///     .min_size(1024)
+///     .deny_content_type("application/zip")
+///     .allow_content_type("image/svg+xml");
+///
+/// let app = App::new()
+///     .wrap(compress)
+///     .default_service(web::to(|| async { HttpResponse::Ok().body("hello world") }));
+/// ```
+///
 /// Pre-compressed Gzip file being served from disk with correct headers added to bypass middleware:
 /// ```no_run
 /// use actix_web::{middleware, http::header, web, App, HttpResponse, Responder};
@@ -72,9 +99,242 @@ use crate::{
 /// ```
 ///
 /// [feature flags]: ../index.html#crate-features
-#[derive(Debug, Clone, Default)]
-#[non_exhaustive]
-pub struct Compress;
+#[derive(Debug, Clone)]
+pub struct Compress {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// Supported encodings; identity first, then others in order of server preference.
+    encodings: Vec<Encoding>,
+
+    /// Supported encodings, formatted for the body of 406 responses.
+    encodings_string: String,
+
+    config: EncoderConfig,
+    min_size: u64,
+    content_types: Vec<ContentTypeRule>,
+}
+
+impl Compress {
+    /// Constructs compression middleware with the default policy.
+    pub fn new() -> Self {
+        let mut inner = Inner {
+            encodings: Vec::new(),
+            encodings_string: String::new(),
+            config: EncoderConfig::default(),
+            min_size: 0,
+            content_types: vec![
+                ContentTypeRule::new("image/*", false),
+                ContentTypeRule::new("video/*", false),
+            ],
+        };
+
+        inner.set_encodings([
+            ContentEncoding::Brotli,
+            ContentEncoding::Zstd,
+            ContentEncoding::Gzip,
+            ContentEncoding::Deflate,
+        ]);
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("Compress must be configured before it is cloned")
+    }
+
+    /// Sets the encodings that may be used, in order of server preference.
+    ///
+    /// The client's preferences, as indicated by `Accept-Encoding` q-factors, take priority; this
+    /// order is used to choose between encodings the client rates equally. Encodings whose
+    /// `compress-*` feature is not enabled are ignored.
+    pub fn encodings(mut self, encodings: impl IntoIterator<Item = ContentEncoding>) -> Self {
+        self.inner_mut().set_encodings(encodings);
+        self
+    }
+
+    /// Sets the compression level used for `encoding`.
+    ///
+    /// See [`EncoderConfig`] for the supported range of each algorithm. Defaults favor speed over
+    /// compression ratio.
+    pub fn level(mut self, encoding: ContentEncoding, level: u32) -> Self {
+        let inner = self.inner_mut();
+        inner.config = inner.config.level(encoding, level);
+        self
+    }
+
+    /// Sets the minimum body size, in bytes, for compression to be used. Defaults to 0.
+    ///
+    /// Streaming bodies, whose size is not known in advance, are always eligible for compression.
+    pub fn min_size(mut self, bytes: u64) -> Self {
+        self.inner_mut().min_size = bytes;
+        self
+    }
+
+    /// Adds a content type that should be compressed.
+    ///
+    /// The pattern is either a full media type (e.g., `application/json`) or a wildcard subtype
+    /// (e.g., `text/*`). If any allowed content types are set, responses whose content type does
+    /// not match one are not compressed.
+    ///
+    /// When allowed and denied patterns both match, the more specific one applies; if they are
+    /// equally specific, the response is not compressed.
+    ///
+    /// # Panics
+    /// Panics if `pattern` is not of the form `type/subtype` or `type/*`.
+    pub fn allow_content_type(mut self, pattern: &str) -> Self {
+        self.inner_mut()
+            .content_types
+            .push(ContentTypeRule::new(pattern, true));
+        self
+    }
+
+    /// Adds a content type that should not be compressed, such as formats that are already
+    /// compressed.
+    ///
+    /// `image/*` and `video/*` are denied by default. See
+    /// [`allow_content_type`](Self::allow_content_type) for pattern syntax and precedence.
+    ///
+    /// # Panics
+    /// Panics if `pattern` is not of the form `type/subtype` or `type/*`.
+    pub fn deny_content_type(mut self, pattern: &str) -> Self {
+        self.inner_mut()
+            .content_types
+            .push(ContentTypeRule::new(pattern, false));
+        self
+    }
+}
+
+impl Default for Compress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inner {
+    fn set_encodings(&mut self, encodings: impl IntoIterator<Item = ContentEncoding>) {
+        self.encodings = vec![Encoding::identity()];
+
+        for encoding in encodings {
+            let encoding = Encoding::Known(encoding);
+
+            if is_enabled(&encoding) && !self.encodings.contains(&encoding) {
+                self.encodings.push(encoding);
+            }
+        }
+
+        self.encodings_string = self.encodings[1..]
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+
+    /// Selects an encoding, using server preference to break ties between equally-rated encodings.
+    fn negotiate(&self, accept_encoding: &AcceptEncoding) -> Option<Encoding> {
+        let selected = accept_encoding.negotiate(self.encodings.iter())?;
+
+        if selected == Encoding::identity() {
+            return Some(selected);
+        }
+
+        let quality = |encoding: &Encoding| {
+            accept_encoding
+                .iter()
+                .find(|item| item.item == Preference::Specific(encoding.clone()))
+                .map(|item| item.quality)
+        };
+
+        let selected_quality = quality(&selected);
+
+        let preferred = self.encodings[1..]
+            .iter()
+            .find(|encoding| quality(encoding) == selected_quality)
+            .cloned();
+
+        Some(preferred.unwrap_or(selected))
+    }
+
+    fn should_compress(&self, body_size: BodySize, content_type: Option<&HeaderValue>) -> bool {
+        if let BodySize::Sized(size) = body_size {
+            if size < self.min_size {
+                return false;
+            }
+        }
+
+        let mime = content_type
+            .and_then(|hdr| hdr.to_str().ok())
+            .and_then(|hdr| hdr.parse::<Mime>().ok());
+
+        let matched = mime.and_then(|mime| {
+            self.content_types
+                .iter()
+                .filter(|rule| rule.matches(&mime))
+                // most specific rule wins; deny rules win ties
+                .max_by_key(|rule| (rule.essence.is_some(), !rule.allow))
+        });
+
+        match matched {
+            Some(rule) => rule.allow,
+            None => !self.content_types.iter().any(|rule| rule.allow),
+        }
+    }
+}
+
+fn is_enabled(encoding: &Encoding) -> bool {
+    match encoding {
+        #[cfg(feature = "compress-brotli")]
+        Encoding::Known(ContentEncoding::Brotli) => true,
+
+        #[cfg(feature = "compress-gzip")]
+        Encoding::Known(ContentEncoding::Gzip | ContentEncoding::Deflate) => true,
+
+        #[cfg(feature = "compress-zstd")]
+        Encoding::Known(ContentEncoding::Zstd) => true,
+
+        _ => false,
+    }
+}
+
+/// Content type pattern used to decide whether a response should be compressed.
+#[derive(Debug)]
+struct ContentTypeRule {
+    type_: String,
+
+    /// Full media type (e.g., `image/svg+xml`), or `None` for a wildcard subtype.
+    essence: Option<String>,
+
+    allow: bool,
+}
+
+impl ContentTypeRule {
+    fn new(pattern: &str, allow: bool) -> Self {
+        let essence = pattern.split(';').next().unwrap_or_default().trim();
+
+        let (type_, subtype) = essence
+            .split_once('/')
+            .filter(|(type_, subtype)| !type_.is_empty() && !subtype.is_empty())
+            .unwrap_or_else(|| panic!("invalid content type pattern: {pattern:?}"));
+
+        Self {
+            type_: type_.to_ascii_lowercase(),
+            essence: (subtype != "*").then(|| essence.to_ascii_lowercase()),
+            allow,
+        }
+    }
+
+    fn matches(&self, mime: &Mime) -> bool {
+        // compare full essence since `Mime::subtype` excludes suffixes (e.g., `+xml`)
+        match &self.essence {
+            Some(essence) => mime.essence_str().eq_ignore_ascii_case(essence),
+            None => mime.type_().as_str().eq_ignore_ascii_case(&self.type_),
+        }
+    }
+}
 
 impl<S, B> Transform<S, ServiceRequest> for Compress
 where
@@ -88,12 +348,16 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(CompressMiddleware { service })
+        ok(CompressMiddleware {
+            service,
+            inner: Arc::clone(&self.inner),
+        })
     }
 }
 
 pub struct CompressMiddleware<S> {
     service: S,
+    inner: Arc<Inner>,
 }
 
 impl<S, B> Service<ServiceRequest> for CompressMiddleware<S>
@@ -119,6 +383,7 @@ where
                 return Either::left(CompressResponse {
                     encoding: Encoding::identity(),
                     fut: self.service.call(req),
+                    inner: Arc::clone(&self.inner),
                     _phantom: PhantomData,
                 })
             }
@@ -127,11 +392,11 @@ where
             Some(accept_encoding) => accept_encoding,
         };
 
-        match accept_encoding.negotiate(SUPPORTED_ENCODINGS.iter()) {
+        match self.inner.negotiate(&accept_encoding) {
             None => {
                 let mut res = HttpResponse::with_body(
                     StatusCode::NOT_ACCEPTABLE,
-                    SUPPORTED_ENCODINGS_STRING.as_str(),
+                    self.inner.encodings_string.clone(),
                 );
 
                 res.headers_mut()
@@ -146,6 +411,7 @@ where
             Some(encoding) => Either::left(CompressResponse {
                 fut: self.service.call(req),
                 encoding,
+                inner: Arc::clone(&self.inner),
                 _phantom: PhantomData,
             }),
         }
@@ -160,6 +426,7 @@ pin_project! {
         #[pin]
         fut: S::Future,
         encoding: Encoding,
+        inner: Arc<Inner>,
         _phantom: PhantomData<B>,
     }
 }
@@ -183,29 +450,18 @@ where
                     }
                 };
 
+                let inner = Arc::clone(this.inner);
+
                 Poll::Ready(Ok(resp.map_body(move |head, body| {
                     let content_type = head.headers.get(header::CONTENT_TYPE);
 
-                    fn default_compress_predicate(content_type: Option<&HeaderValue>) -> bool {
-                        match content_type {
-                            None => true,
-                            Some(hdr) => {
-                                match hdr.to_str().ok().and_then(|hdr| hdr.parse::<Mime>().ok()) {
-                                    Some(mime) if mime.type_().as_str() == "image" => false,
-                                    Some(mime) if mime.type_().as_str() == "video" => false,
-                                    _ => true,
-                                }
-                            }
-                        }
-                    }
-
-                    let enc = if default_compress_predicate(content_type) {
+                    let enc = if inner.should_compress(body.size(), content_type) {
                         enc
                     } else {
                         ContentEncoding::Identity
                     };
 
-                    EitherBody::left(Encoder::response(enc, head, body))
+                    EitherBody::left(Encoder::response_with_config(enc, inner.config, head, body))
                 })))
             }
 
@@ -214,54 +470,6 @@ where
     }
 }
 
-static SUPPORTED_ENCODINGS_STRING: Lazy<String> = Lazy::new(|| {
-    #[allow(unused_mut)] // only unused when no compress features enabled
-    let mut encoding: Vec<&str> = vec![];
-
-    #[cfg(feature = "compress-brotli")]
-    {
-        encoding.push("br");
-    }
-
-    #[cfg(feature = "compress-gzip")]
-    {
-        encoding.push("gzip");
-        encoding.push("deflate");
-    }
-
-    #[cfg(feature = "compress-zstd")]
-    {
-        encoding.push("zstd");
-    }
-
-    assert!(
-        !encoding.is_empty(),
-        "encoding can not be empty unless __compress feature has been explicitly enabled by itself"
-    );
-
-    encoding.join(", ")
-});
-
-static SUPPORTED_ENCODINGS: &[Encoding] = &[
-    Encoding::identity(),
-    #[cfg(feature = "compress-brotli")]
-    {
-        Encoding::brotli()
-    },
-    #[cfg(feature = "compress-gzip")]
-    {
-        Encoding::gzip()
-    },
-    #[cfg(feature = "compress-gzip")]
-    {
-        Encoding::deflate()
-    },
-    #[cfg(feature = "compress-zstd")]
-    {
-        Encoding::zstd()
-    },
-];
-
 // move cfg(feature) to prevents_double_compressing if more tests are added
 #[cfg(feature = "compress-gzip")]
 #[cfg(test)]
@@ -447,6 +655,133 @@ mod tests {
         assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
         assert!(test::read_body(res).await.is_empty());
     }
+
+    #[actix_rt::test]
+    async fn skips_small_bodies() {
+        let app = test::init_service({
+            App::new()
+                .wrap(Compress::new().min_size(TEXT_DATA.len() as u64 + 1))
+                .route(
+                    "/small",
+                    web::get().to(|| HttpResponse::Ok().body(TEXT_DATA)),
+                )
+                .route(
+                    "/large",
+                    web::get().to(|| HttpResponse::Ok().body(HTML_DATA)),
+                )
+        })
+        .await;
+
+        let req = test::TestRequest::with_uri("/small")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(test::read_body(res).await, TEXT_DATA.as_bytes());
+
+        let req = test::TestRequest::with_uri("/large")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+    }
+
+    #[actix_rt::test]
+    async fn content_type_rules() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Compress::new()
+                        .allow_content_type("text/*")
+                        .allow_content_type("image/svg+xml")
+                        .deny_content_type("text/csv"),
+                )
+                .configure(configure_predicate_test)
+                .route(
+                    "/svg",
+                    web::get().to(|| {
+                        HttpResponse::Ok()
+                            .content_type("image/svg+xml")
+                            .body(TEXT_DATA)
+                    }),
+                )
+                .route(
+                    "/csv",
+                    web::get().to(|| HttpResponse::Ok().content_type("text/csv").body(TEXT_DATA)),
+                )
+                .route(
+                    "/json",
+                    web::get().to(|| {
+                        HttpResponse::Ok()
+                            .content_type(ContentType::json())
+                            .body(TEXT_DATA)
+                    }),
+                ),
+        )
+        .await;
+
+        for (path, ct) in [("/html", "text/html"), ("/svg", "image/svg+xml")] {
+            let req =
+                test::TestRequest::with_uri(path).insert_header((header::ACCEPT_ENCODING, "gzip"));
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_successful_gzip_res_with_content_type(&res, ct);
+        }
+
+        for (path, ct) in [
+            ("/image", "image/jpeg"),
+            ("/csv", "text/csv"),
+            ("/json", "application/json"),
+        ] {
+            let req =
+                test::TestRequest::with_uri(path).insert_header((header::ACCEPT_ENCODING, "gzip"));
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_successful_identity_res_with_content_type(&res, ct);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn server_preference_and_levels() {
+        let app = test::init_service({
+            App::new()
+                .wrap(
+                    Compress::new()
+                        .encodings([ContentEncoding::Deflate, ContentEncoding::Gzip])
+                        .level(ContentEncoding::Gzip, 0),
+                )
+                .default_service(web::to(|| HttpResponse::Ok().body(TEXT_DATA)))
+        })
+        .await;
+
+        // equally-rated encodings use server preference
+        let req = test::TestRequest::default()
+            .insert_header((header::ACCEPT_ENCODING, "gzip, deflate"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "deflate"
+        );
+
+        // client preference takes priority
+        let req = test::TestRequest::default()
+            .insert_header((header::ACCEPT_ENCODING, "gzip, deflate;q=0.5"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+
+        // level 0 stores data uncompressed
+        let bytes = test::read_body(res).await;
+        assert!(bytes.len() > TEXT_DATA.len());
+        assert_eq!(gzip_decode(bytes), TEXT_DATA.as_bytes());
+
+        // disabled encodings are not acceptable
+        let req = test::TestRequest::default()
+            .insert_header((header::ACCEPT_ENCODING, "br, identity;q=0"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(test::read_body(res).await, "deflate, gzip");
+    }
 }
 
 #[cfg(feature = "compress-brotli")]
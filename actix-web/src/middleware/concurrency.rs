@@ -0,0 +1,409 @@
+//! For middleware documentation, see [`ConcurrencyLimit`].
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use actix_rt::time::timeout;
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use derive_more::derive::{Display, Error};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, StatusCode},
+    Error, HttpMessage as _, HttpResponse, ResponseError,
+};
+
+/// Error returned when a request is shed by [`ConcurrencyLimit`] middleware.
+///
+/// Responds with `503 Service Unavailable` and a `Retry-After` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+#[display("Server is at capacity")]
+pub struct ConcurrencyLimitError {
+    retry_after: Duration,
+}
+
+impl ResponseError for ConcurrencyLimitError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // round up so clients never retry earlier than advised
+        let secs = self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0);
+
+        HttpResponse::build(self.status_code())
+            .insert_header((header::RETRY_AFTER, secs))
+            .body(self.to_string())
+    }
+}
+
+/// Occupancy of a [`ConcurrencyLimit`] at the time a request was admitted.
+///
+/// Inserted into request extensions so it can be used, for example, by custom [`Logger`] fields.
+///
+/// [`Logger`]: super::Logger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConcurrencyOccupancy {
+    /// Number of requests in flight, including this one.
+    pub in_flight: usize,
+
+    /// Maximum number of requests allowed in flight.
+    pub limit: usize,
+
+    /// Number of requests waiting in the queue.
+    pub queued: usize,
+}
+
+/// Middleware for capping the number of in-flight requests.
+///
+/// When the limit is reached, further requests either wait in a bounded queue until a slot frees
+/// up, or are shed immediately with a [`ConcurrencyLimitError`] (`503 Service Unavailable` with a
+/// `Retry-After` header). Queued requests that do not get a slot within the maximum wait time are
+/// also shed. By default there is no queue.
+///
+/// Unlike [`HttpServer::max_connections`], which limits sockets, this limits concurrent work, which
+/// matters for HTTP/2 where a single connection can carry many requests. It can be used on an
+/// `App`, `Scope` or `Resource` to protect expensive endpoints individually.
+///
+/// Limits are enforced per worker thread. The occupancy seen by each admitted request is inserted
+/// into request extensions as a [`ConcurrencyOccupancy`].
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_web::{middleware::ConcurrencyLimit, web, App, HttpResponse};
+///
+/// let app = App::new().service(
+///     web::resource("/export")
+///         // at most 4 exports at once; up to 16 more may wait for up to 5 seconds
+///         .wrap(ConcurrencyLimit::new(4).queue(16, Duration::from_secs(5)))
+///         .to(HttpResponse::Ok),
+/// );
+/// ```
+///
+/// [`HttpServer::max_connections`]: crate::HttpServer::max_connections
+#[derive(Debug, Clone, Copy)]
+pub struct ConcurrencyLimit {
+    max_in_flight: usize,
+    max_queued: usize,
+    max_wait: Duration,
+    retry_after: Duration,
+}
+
+impl ConcurrencyLimit {
+    /// Constructs middleware allowing at most `max_in_flight` requests to be processed at once.
+    ///
+    /// # Panics
+    /// Panics if `max_in_flight` is zero.
+    pub fn new(max_in_flight: usize) -> Self {
+        assert!(max_in_flight > 0, "max_in_flight must be greater than zero");
+
+        Self {
+            max_in_flight,
+            max_queued: 0,
+            max_wait: Duration::ZERO,
+            retry_after: Duration::from_secs(1),
+        }
+    }
+
+    /// Allows up to `max_queued` requests to wait for at most `max_wait` when the limit is reached.
+    pub fn queue(mut self, max_queued: usize, max_wait: Duration) -> Self {
+        self.max_queued = max_queued;
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Sets the `Retry-After` duration advertised to shed requests. Defaults to 1 second.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ConcurrencyLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ConcurrencyLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ConcurrencyLimitMiddleware {
+            service: Rc::new(service),
+            limiter: Rc::new(Limiter {
+                config: *self,
+                state: RefCell::default(),
+            }),
+        }))
+    }
+}
+
+/// Service created by [`ConcurrencyLimit`] middleware.
+pub struct ConcurrencyLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: Rc<Limiter>,
+}
+
+impl<S, B> Service<ServiceRequest> for ConcurrencyLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let limiter = Rc::clone(&self.limiter);
+
+        Box::pin(async move {
+            let permit = limiter.acquire().await.ok_or(ConcurrencyLimitError {
+                retry_after: limiter.config.retry_after,
+            })?;
+
+            req.extensions_mut().insert(limiter.occupancy());
+
+            let res = service.call(req).await;
+
+            // slot is held until the inner service has produced a response
+            drop(permit);
+
+            res
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    in_flight: usize,
+    queue: VecDeque<Rc<Waiter>>,
+}
+
+#[derive(Default)]
+struct Waiter {
+    granted: Cell<bool>,
+    waker: Cell<Option<Waker>>,
+}
+
+struct Limiter {
+    config: ConcurrencyLimit,
+    state: RefCell<State>,
+}
+
+impl Limiter {
+    fn occupancy(&self) -> ConcurrencyOccupancy {
+        let state = self.state.borrow();
+
+        ConcurrencyOccupancy {
+            in_flight: state.in_flight,
+            limit: self.config.max_in_flight,
+            queued: state.queue.len(),
+        }
+    }
+
+    /// Waits for a free slot, returning `None` if the request should be shed.
+    async fn acquire(self: &Rc<Self>) -> Option<Permit> {
+        let waiter = {
+            let mut state = self.state.borrow_mut();
+
+            if state.in_flight < self.config.max_in_flight {
+                state.in_flight += 1;
+                return Some(Permit(Rc::clone(self)));
+            }
+
+            if state.queue.len() >= self.config.max_queued {
+                return None;
+            }
+
+            let waiter = Rc::new(Waiter::default());
+            state.queue.push_back(Rc::clone(&waiter));
+            waiter
+        };
+
+        let queued = Queued {
+            limiter: Rc::clone(self),
+            waiter,
+            done: false,
+        };
+
+        timeout(self.config.max_wait, queued).await.ok()
+    }
+
+    /// Hands a finished request's slot to the next queued request, or frees it.
+    fn release(&self) {
+        let mut state = self.state.borrow_mut();
+
+        match state.queue.pop_front() {
+            Some(waiter) => {
+                waiter.granted.set(true);
+
+                if let Some(waker) = waiter.waker.take() {
+                    waker.wake();
+                }
+            }
+
+            None => state.in_flight -= 1,
+        }
+    }
+}
+
+/// Holds an in-flight slot until dropped.
+struct Permit(Rc<Limiter>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+/// Future that resolves once a queued request has been granted a slot.
+struct Queued {
+    limiter: Rc<Limiter>,
+    waiter: Rc<Waiter>,
+    done: bool,
+}
+
+impl Future for Queued {
+    type Output = Permit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.waiter.granted.get() {
+            self.done = true;
+            return Poll::Ready(Permit(Rc::clone(&self.limiter)));
+        }
+
+        self.waiter.waker.set(Some(cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        if self.waiter.granted.get() {
+            // slot was handed over but never used; pass it on
+            self.limiter.release();
+        } else {
+            self.limiter
+                .state
+                .borrow_mut()
+                .queue
+                .retain(|waiter| !Rc::ptr_eq(waiter, &self.waiter));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_rt::time::sleep;
+    use futures_util::future::{join, join3};
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App, HttpRequest,
+    };
+
+    async fn slow(req: HttpRequest) -> HttpResponse {
+        let occupancy = *req.extensions().get::<ConcurrencyOccupancy>().unwrap();
+        sleep(Duration::from_millis(50)).await;
+        HttpResponse::Ok().body(occupancy.in_flight.to_string())
+    }
+
+    #[actix_rt::test]
+    async fn sheds_excess_requests() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ConcurrencyLimit::new(2).retry_after(Duration::from_millis(1500)))
+                .default_service(web::to(slow)),
+        )
+        .await;
+
+        let (res1, res2, res3) = join3(
+            test::call_service(&app, TestRequest::default().to_request()),
+            test::call_service(&app, TestRequest::default().to_request()),
+            test::try_call_service(&app, TestRequest::default().to_request()),
+        )
+        .await;
+
+        assert_eq!(res1.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res1).await, "1");
+        assert_eq!(res2.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res2).await, "2");
+
+        let res = res3.unwrap_err().error_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "2");
+
+        // slots are freed once responses are produced
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn queued_requests_wait_for_slot() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ConcurrencyLimit::new(1).queue(1, Duration::from_millis(500)))
+                .default_service(web::to(slow)),
+        )
+        .await;
+
+        let (res1, res2, res3) = join3(
+            test::try_call_service(&app, TestRequest::default().to_request()),
+            test::try_call_service(&app, TestRequest::default().to_request()),
+            test::try_call_service(&app, TestRequest::default().to_request()),
+        )
+        .await;
+
+        assert_eq!(res1.unwrap().status(), StatusCode::OK);
+        assert_eq!(res2.unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            res3.unwrap_err().as_response_error().status_code(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[actix_rt::test]
+    async fn queue_deadline() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ConcurrencyLimit::new(1).queue(4, Duration::from_millis(10)))
+                .default_service(web::to(slow)),
+        )
+        .await;
+
+        let (res1, res2) = join(
+            test::try_call_service(&app, TestRequest::default().to_request()),
+            test::try_call_service(&app, TestRequest::default().to_request()),
+        )
+        .await;
+
+        assert_eq!(res1.unwrap().status(), StatusCode::OK);
+        assert!(res2.is_err());
+
+        // timed out waiter must not leak a slot
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "1");
+    }
+}
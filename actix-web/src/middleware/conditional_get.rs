@@ -0,0 +1,303 @@
+//! For middleware documentation, see [`ConditionalGet`].
+
+use std::{fmt::Write as _, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+use sha2::{Digest as _, Sha256};
+
+use crate::{
+    body::{self, BodySize, EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    http::{
+        header::{self, EntityTag, HeaderValue, IfNoneMatch},
+        Method, StatusCode,
+    },
+    Error, HttpMessage as _,
+};
+
+/// Default size, in bytes, of the largest response body that will be buffered.
+const DEFAULT_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Middleware for adding `ETag`s to responses and answering conditional `GET` requests.
+///
+/// For `200 OK` responses to `GET` and `HEAD` requests, the response body is buffered and hashed
+/// to produce an `ETag` header. If the request's `If-None-Match` header matches that entity tag,
+/// a `304 Not Modified` response without a body is sent instead, saving clients from
+/// re-downloading representations they already have.
+///
+/// Only bodies of known size up to a limit (1MiB by default) are buffered; streaming and larger
+/// responses are passed through unchanged. Responses that already have an `ETag` header are not
+/// hashed, but that `ETag` is still used to evaluate `If-None-Match`.
+///
+/// Entity tags are strong by default since they are derived from the exact body bytes. If this
+/// middleware is wrapped by middleware that changes the body, such as [`Compress`], use
+/// [weak](Self::weak) entity tags instead.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::ConditionalGet, web, App, HttpResponse};
+///
+/// let app = App::new()
+///     .wrap(ConditionalGet::new())
+///     .route("/", web::get().to(|| async { HttpResponse::Ok().body("hello world") }));
+/// ```
+///
+/// [`Compress`]: super::Compress
+#[derive(Debug, Clone, Copy)]
+pub struct ConditionalGet {
+    weak: bool,
+    max_size: u64,
+}
+
+impl ConditionalGet {
+    /// Constructs conditional `GET` middleware that produces strong entity tags.
+    pub fn new() -> Self {
+        Self {
+            weak: false,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Sets whether generated entity tags are weak. Defaults to `false`.
+    pub fn weak(mut self, weak: bool) -> Self {
+        self.weak = weak;
+        self
+    }
+
+    /// Sets the size, in bytes, of the largest response body that will be buffered and hashed.
+    /// Defaults to 1MiB.
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+impl Default for ConditionalGet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ConditionalGet
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = ConditionalGetMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ConditionalGetMiddleware {
+            service: Rc::new(service),
+            config: *self,
+        }))
+    }
+}
+
+/// Service created by [`ConditionalGet`] middleware.
+pub struct ConditionalGetMiddleware<S> {
+    service: Rc<S>,
+    config: ConditionalGet,
+}
+
+impl<S, B> Service<ServiceRequest> for ConditionalGetMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let config = self.config;
+
+        Box::pin(async move {
+            if !matches!(*req.method(), Method::GET | Method::HEAD) {
+                return Ok(service.call(req).await?.map_into_left_body());
+            }
+
+            let if_none_match = req.get_header::<IfNoneMatch>();
+
+            let res = service.call(req).await?;
+
+            if res.status() != StatusCode::OK {
+                return Ok(res.map_into_left_body());
+            }
+
+            // respect entity tags set by handlers
+            if let Some(etag) = res.headers().get(header::ETAG) {
+                let etag = etag.to_str().ok().and_then(|etag| etag.parse().ok());
+
+                return Ok(match etag {
+                    Some(etag) if is_match(if_none_match.as_ref(), &etag) => not_modified(res),
+                    _ => res.map_into_left_body(),
+                });
+            }
+
+            match res.response().body().size() {
+                BodySize::Sized(size) if size <= config.max_size => {}
+                _ => return Ok(res.map_into_left_body()),
+            }
+
+            let (req, res) = res.into_parts();
+            let (mut res, body) = res.into_parts();
+
+            let body = match body.try_into_bytes() {
+                Ok(body) => body,
+                Err(body) => body::to_bytes(body)
+                    .await
+                    .map_err(|err| ErrorInternalServerError(err.into()))?,
+            };
+
+            let etag = entity_tag(&body, config.weak);
+            let val = HeaderValue::from_str(&etag.to_string())
+                .expect("entity tag should always be a valid header value");
+            res.headers_mut().insert(header::ETAG, val);
+
+            let res = ServiceResponse::new(req, res.set_body(body));
+
+            Ok(if is_match(if_none_match.as_ref(), &etag) {
+                not_modified(res)
+            } else {
+                res.map_into_boxed_body().map_into_right_body()
+            })
+        })
+    }
+}
+
+/// Derives an entity tag from the body length and a truncated SHA-256 digest of its contents.
+///
+/// The digest is stable across builds, so entity tags do not change between deployments.
+fn entity_tag(body: &[u8], weak: bool) -> EntityTag {
+    let digest = Sha256::digest(body);
+
+    let mut tag = format!("{:x}-", body.len());
+    for byte in &digest[..16] {
+        let _ = write!(tag, "{byte:02x}");
+    }
+
+    EntityTag::new(weak, tag)
+}
+
+/// Returns true if `If-None-Match` matches `etag`, using weak comparison.
+fn is_match(if_none_match: Option<&IfNoneMatch>, etag: &EntityTag) -> bool {
+    match if_none_match {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(items)) => items.iter().any(|item| item.weak_eq(etag)),
+        None => false,
+    }
+}
+
+/// Replaces the response with `304 Not Modified`, keeping headers relevant to caches.
+fn not_modified<B, B2>(res: ServiceResponse<B>) -> ServiceResponse<EitherBody<B2>> {
+    res.map_body(|head, _| {
+        head.status = StatusCode::NOT_MODIFIED;
+        head.headers.remove(header::CONTENT_TYPE);
+        head.headers.remove(header::CONTENT_LENGTH);
+
+        EitherBody::right(body::BoxBody::new(body::None::new()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[actix_rt::test]
+    async fn adds_etag_and_revalidates() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ConditionalGet::new())
+                .route("/", web::get().to(|| async { "hello world" }))
+                .route("/", web::post().to(|| async { "hello world" })),
+        )
+        .await;
+
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+
+        // entity tags are stable across builds
+        assert_eq!(etag, "\"b-b94d27b9934d3e08a52e52d7da7dabfa\"");
+        assert_eq!(test::read_body(res).await, "hello world");
+
+        let req = TestRequest::get()
+            .insert_header((header::IF_NONE_MATCH, etag.clone()))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), etag);
+        assert!(!res.headers().contains_key(header::CONTENT_TYPE));
+        assert!(test::read_body(res).await.is_empty());
+
+        let req = TestRequest::get()
+            .insert_header((header::IF_NONE_MATCH, "\"other\""))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .insert_header((header::IF_NONE_MATCH, "*"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res.headers().contains_key(header::ETAG));
+    }
+
+    #[actix_rt::test]
+    async fn weak_and_existing_etags() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ConditionalGet::new().weak(true).max_size(8))
+                .route("/", web::get().to(|| async { "hello" }))
+                .route("/large", web::get().to(|| async { "hello world" }))
+                .route(
+                    "/tagged",
+                    web::get().to(|| {
+                        HttpResponse::Ok()
+                            .insert_header((header::ETAG, "\"v1\""))
+                            .body("hello world")
+                    }),
+                ),
+        )
+        .await;
+
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        assert!(etag.to_str().unwrap().starts_with("W/"));
+
+        // weak comparison is used for If-None-Match
+        let strong = etag.to_str().unwrap().trim_start_matches("W/").to_owned();
+        let req = TestRequest::get()
+            .insert_header((header::IF_NONE_MATCH, strong))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::get().uri("/large").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(!res.headers().contains_key(header::ETAG));
+
+        let req = TestRequest::get()
+            .uri("/tagged")
+            .insert_header((header::IF_NONE_MATCH, "\"v1\""))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"v1\"");
+    }
+}
@@ -0,0 +1,819 @@
+//! For middleware documentation, see [`Cors`].
+
+use std::{collections::HashSet, fmt, rc::Rc};
+
+use actix_http::RequestHead;
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use derive_more::derive::{Display, Error};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::EitherBody,
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpResponse, ResponseError,
+};
+
+type OriginFn = dyn Fn(&HeaderValue, &RequestHead) -> bool;
+
+/// Errors that can occur when processing CORS requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+#[non_exhaustive]
+pub enum CorsError {
+    /// Preflight request did not include an `Access-Control-Request-Method` header.
+    #[display("Preflight request is missing the `Access-Control-Request-Method` header")]
+    MissingRequestMethod,
+
+    /// `Access-Control-Request-Method` header could not be parsed as a method.
+    #[display("Preflight request has an invalid `Access-Control-Request-Method` header")]
+    BadRequestMethod,
+
+    /// `Access-Control-Request-Headers` header could not be parsed as a list of header names.
+    #[display("Preflight request has an invalid `Access-Control-Request-Headers` header")]
+    BadRequestHeaders,
+
+    /// Request origin is not in the allowed set.
+    #[display("Origin is not allowed to make this request")]
+    OriginNotAllowed,
+
+    /// Requested method is not in the allowed set.
+    #[display("Requested method is not allowed")]
+    MethodNotAllowed,
+
+    /// One or more requested headers are not in the allowed set.
+    #[display("One or more requested headers are not allowed")]
+    HeadersNotAllowed,
+}
+
+impl ResponseError for CorsError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Set of origins allowed to make cross-origin requests.
+enum AllowedOrigins {
+    /// Any origin is allowed.
+    Any,
+
+    /// Only origins matching an exact string or one of the predicates are allowed.
+    Some {
+        exact: HashSet<HeaderValue>,
+        predicates: Vec<Rc<OriginFn>>,
+    },
+}
+
+impl fmt::Debug for AllowedOrigins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("Any"),
+            Self::Some { exact, predicates } => f
+                .debug_struct("Some")
+                .field("exact", exact)
+                .field("predicates", &predicates.len())
+                .finish(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    origins: AllowedOrigins,
+    methods: Option<HashSet<Method>>,
+    headers: Option<HashSet<HeaderName>>,
+    expose_headers: Vec<HeaderName>,
+    supports_credentials: bool,
+    max_age: Option<usize>,
+    block_on_origin_mismatch: bool,
+}
+
+impl Default for Inner {
+    fn default() -> Self {
+        Self {
+            origins: AllowedOrigins::Some {
+                exact: HashSet::new(),
+                predicates: Vec::new(),
+            },
+            methods: Some(HashSet::from([Method::GET, Method::HEAD, Method::POST])),
+            headers: Some(HashSet::new()),
+            expose_headers: Vec::new(),
+            supports_credentials: false,
+            max_age: None,
+            block_on_origin_mismatch: true,
+        }
+    }
+}
+
+/// Middleware for handling [Cross-Origin Resource Sharing] (CORS) requests.
+///
+/// Preflight requests (an `OPTIONS` request carrying both `Origin` and
+/// `Access-Control-Request-Method` headers) are answered directly by this middleware and never
+/// reach the wrapped service. Other requests carrying an `Origin` header have their origin checked
+/// before being passed on, and the appropriate `Access-Control-*` headers are added to the
+/// response.
+///
+/// # Defaults
+/// The default configuration is restrictive: no origins are allowed, only the CORS-safelisted
+/// methods (`GET`, `HEAD` and `POST`) are allowed, no non-safelisted request headers are allowed,
+/// credentials are not supported and no `Access-Control-Max-Age` is sent. Use
+/// [`permissive()`](Self::permissive) during development to allow everything.
+///
+/// # `Vary` Header
+/// Unless any origin is allowed and credentials are not supported (in which case the response
+/// uses `Access-Control-Allow-Origin: *` and does not vary), `Vary: Origin` is appended to every
+/// response passing through this middleware, including those to same-origin requests. This keeps
+/// shared caches from serving one origin's response to another.
+///
+/// # Credentials
+/// When [credentials are supported](Self::supports_credentials), the wildcard values for
+/// `Access-Control-Allow-Origin`, `Access-Control-Allow-Methods` and
+/// `Access-Control-Allow-Headers` are never sent since browsers ignore them on credentialed
+/// requests. The request's origin, method and headers are echoed back instead.
+///
+/// # Examples
+/// ```
+/// use actix_web::{http::{header, Method}, middleware::Cors, web, App, HttpResponse};
+///
+/// let cors = Cors::default()
+///     .allowed_origin("https://www.rust-lang.org")
+///     .allowed_origin_fn(|origin, _req_head| origin.as_bytes().ends_with(b".rust-lang.org"))
+///     .allowed_methods([Method::GET, Method::POST])
+///     .allowed_headers([header::AUTHORIZATION, header::ACCEPT])
+///     .allowed_header(header::CONTENT_TYPE)
+///     .supports_credentials()
+///     .max_age(3600);
+///
+/// let app = App::new()
+///     .wrap(cors)
+///     .default_service(web::to(|| HttpResponse::Ok()));
+/// ```
+///
+/// [Cross-Origin Resource Sharing]: https://fetch.spec.whatwg.org/#http-cors-protocol
+#[derive(Debug, Default)]
+pub struct Cors {
+    inner: Rc<Inner>,
+}
+
+impl Cors {
+    /// Constructs a very permissive set of defaults, useful for development.
+    ///
+    /// Any origin, method and request header is allowed and preflight results are cached for one
+    /// hour. Credentials are not supported. **This is not recommended for production use.**
+    pub fn permissive() -> Self {
+        Self::default()
+            .allow_any_origin()
+            .allow_any_method()
+            .allow_any_header()
+            .max_age(3600)
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("CORS must be configured before it is cloned")
+    }
+
+    /// Allows requests from the given origin.
+    ///
+    /// The origin is compared byte-for-byte with the `Origin` request header so it should be in
+    /// the serialized form of `scheme://host[:port]`, without a trailing slash.
+    ///
+    /// # Panics
+    /// Panics if `origin` is `*` (use [`allow_any_origin()`](Self::allow_any_origin) instead) or
+    /// is not a valid header value.
+    pub fn allowed_origin(mut self, origin: &str) -> Self {
+        assert!(
+            origin != "*",
+            "Wildcard origin is not allowed here; use `Cors::allow_any_origin()` instead"
+        );
+
+        let origin = HeaderValue::from_str(origin).expect("Invalid origin");
+
+        if let AllowedOrigins::Some { exact, .. } = &mut self.inner_mut().origins {
+            exact.insert(origin);
+        }
+
+        self
+    }
+
+    /// Allows requests from origins for which the given predicate returns true.
+    ///
+    /// The predicate receives the value of the `Origin` header and the request head. Predicates
+    /// are only consulted after the exact origins added with
+    /// [`allowed_origin()`](Self::allowed_origin) fail to match.
+    pub fn allowed_origin_fn<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HeaderValue, &RequestHead) -> bool + 'static,
+    {
+        if let AllowedOrigins::Some { predicates, .. } = &mut self.inner_mut().origins {
+            predicates.push(Rc::new(f));
+        }
+
+        self
+    }
+
+    /// Allows requests from any origin.
+    ///
+    /// Overrides any origins or predicates set previously.
+    pub fn allow_any_origin(mut self) -> Self {
+        self.inner_mut().origins = AllowedOrigins::Any;
+        self
+    }
+
+    /// Sets the methods allowed in cross-origin requests, replacing the defaults.
+    pub fn allowed_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.inner_mut().methods = Some(methods.into_iter().collect());
+        self
+    }
+
+    /// Allows any method in cross-origin requests.
+    pub fn allow_any_method(mut self) -> Self {
+        self.inner_mut().methods = None;
+        self
+    }
+
+    /// Adds a request header that may be used in cross-origin requests.
+    pub fn allowed_header(mut self, header: HeaderName) -> Self {
+        if let Some(headers) = &mut self.inner_mut().headers {
+            headers.insert(header);
+        }
+
+        self
+    }
+
+    /// Adds request headers that may be used in cross-origin requests.
+    pub fn allowed_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        if let Some(allowed) = &mut self.inner_mut().headers {
+            allowed.extend(headers);
+        }
+
+        self
+    }
+
+    /// Allows any request header in cross-origin requests.
+    pub fn allow_any_header(mut self) -> Self {
+        self.inner_mut().headers = None;
+        self
+    }
+
+    /// Adds response headers that browsers are allowed to expose to cross-origin scripts.
+    pub fn expose_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.inner_mut().expose_headers.extend(headers);
+        self
+    }
+
+    /// Allows cross-origin requests to include credentials (cookies, auth headers, and TLS client
+    /// certificates).
+    ///
+    /// Sends `Access-Control-Allow-Credentials: true` on allowed requests.
+    pub fn supports_credentials(mut self) -> Self {
+        self.inner_mut().supports_credentials = true;
+        self
+    }
+
+    /// Sets how long, in seconds, browsers may cache the result of a preflight request.
+    ///
+    /// Pass `None` to omit the `Access-Control-Max-Age` header, leaving it to browser defaults.
+    pub fn max_age(mut self, max_age: impl Into<Option<usize>>) -> Self {
+        self.inner_mut().max_age = max_age.into();
+        self
+    }
+
+    /// Sets whether requests from disallowed origins are rejected with an error response.
+    ///
+    /// When `false`, non-preflight requests from disallowed origins are passed on to the wrapped
+    /// service but no `Access-Control-*` headers are added to the response, leaving the browser
+    /// to block access to it. Preflight requests from disallowed origins are always rejected.
+    ///
+    /// Defaults to `true`.
+    pub fn block_on_origin_mismatch(mut self, block: bool) -> Self {
+        self.inner_mut().block_on_origin_mismatch = block;
+        self
+    }
+}
+
+impl Inner {
+    /// Returns true if the response should use the `*` origin wildcard and not vary by origin.
+    fn sends_wildcard(&self) -> bool {
+        matches!(self.origins, AllowedOrigins::Any) && !self.supports_credentials
+    }
+
+    fn validate_origin(&self, origin: &HeaderValue, head: &RequestHead) -> Result<(), CorsError> {
+        match &self.origins {
+            AllowedOrigins::Any => Ok(()),
+            AllowedOrigins::Some { exact, predicates } => {
+                if exact.contains(origin) || predicates.iter().any(|f| f(origin, head)) {
+                    Ok(())
+                } else {
+                    Err(CorsError::OriginNotAllowed)
+                }
+            }
+        }
+    }
+
+    fn validate_preflight(&self, head: &RequestHead) -> Result<(), CorsError> {
+        let method = head
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .ok_or(CorsError::MissingRequestMethod)?;
+
+        let method =
+            Method::from_bytes(method.as_bytes()).map_err(|_| CorsError::BadRequestMethod)?;
+
+        if let Some(methods) = &self.methods {
+            if !methods.contains(&method) {
+                return Err(CorsError::MethodNotAllowed);
+            }
+        }
+
+        if let Some(allowed) = &self.headers {
+            for name in requested_headers(head.headers())? {
+                if !allowed.contains(&name) {
+                    return Err(CorsError::HeadersNotAllowed);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Value for `Access-Control-Allow-Origin`, given a validated origin.
+    fn allow_origin_value(&self, origin: &HeaderValue) -> HeaderValue {
+        if self.sends_wildcard() {
+            HeaderValue::from_static("*")
+        } else {
+            origin.clone()
+        }
+    }
+
+    fn preflight_response(&self, head: &RequestHead, origin: &HeaderValue) -> HttpResponse {
+        let mut res = HttpResponse::Ok();
+
+        res.insert_header((
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            self.allow_origin_value(origin),
+        ));
+
+        let req_headers = head.headers();
+
+        match &self.methods {
+            Some(methods) => {
+                res.insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, join(methods)));
+            }
+            None => {
+                // echo requested method, which has already been validated as present
+                if let Some(method) = req_headers.get(header::ACCESS_CONTROL_REQUEST_METHOD) {
+                    res.insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, method.clone()));
+                }
+            }
+        }
+
+        match &self.headers {
+            Some(headers) if !headers.is_empty() => {
+                res.insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, join(headers)));
+            }
+            Some(_) => {}
+            None => {
+                if let Some(headers) = req_headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+                    res.insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, headers.clone()));
+                }
+            }
+        }
+
+        if self.supports_credentials {
+            res.insert_header((
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            ));
+        }
+
+        if let Some(max_age) = self.max_age {
+            res.insert_header((header::ACCESS_CONTROL_MAX_AGE, max_age));
+        }
+
+        let mut res = res.finish();
+
+        let headers = res.headers_mut();
+        if !self.sends_wildcard() {
+            add_vary(headers, "Origin");
+        }
+        add_vary(headers, "Access-Control-Request-Method");
+        add_vary(headers, "Access-Control-Request-Headers");
+
+        res
+    }
+
+    fn add_response_headers(&self, headers: &mut HeaderMap, origin: Option<&HeaderValue>) {
+        if let Some(origin) = origin {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                self.allow_origin_value(origin),
+            );
+
+            if self.supports_credentials {
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
+
+            if !self.expose_headers.is_empty() {
+                headers.insert(
+                    header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                    join(&self.expose_headers),
+                );
+            }
+        }
+
+        if !self.sends_wildcard() {
+            add_vary(headers, "Origin");
+        }
+    }
+}
+
+/// Parses the comma-separated `Access-Control-Request-Headers` header.
+fn requested_headers(headers: &HeaderMap) -> Result<Vec<HeaderName>, CorsError> {
+    let mut names = Vec::new();
+
+    for val in headers.get_all(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+        let val = val.to_str().map_err(|_| CorsError::BadRequestHeaders)?;
+
+        for name in val
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            names.push(HeaderName::try_from(name).map_err(|_| CorsError::BadRequestHeaders)?);
+        }
+    }
+
+    Ok(names)
+}
+
+/// Joins items into a sorted, comma-separated header value.
+fn join<'a, T>(items: impl IntoIterator<Item = &'a T>) -> HeaderValue
+where
+    T: AsRef<str> + 'a,
+{
+    let mut items = items.into_iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    items.sort_unstable();
+
+    HeaderValue::try_from(items.join(", ")).expect("method and header names are valid values")
+}
+
+/// Appends `field` to the `Vary` header unless it, or `*`, is already listed.
+fn add_vary(headers: &mut HeaderMap, field: &'static str) {
+    let already_varies = headers
+        .get_all(header::VARY)
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .map(str::trim)
+        .any(|val| val == "*" || val.eq_ignore_ascii_case(field));
+
+    if !already_varies {
+        headers.append(header::VARY, HeaderValue::from_static(field));
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Cors
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = CorsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorsMiddleware {
+            service,
+            inner: Rc::clone(&self.inner),
+        }))
+    }
+}
+
+/// Service created by [`Cors`] middleware.
+pub struct CorsMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for CorsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let origin = req.headers().get(header::ORIGIN).cloned();
+
+        let is_preflight = req.method() == Method::OPTIONS
+            && origin.is_some()
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+        if is_preflight {
+            let origin = origin.unwrap();
+
+            let res = self
+                .inner
+                .validate_origin(&origin, req.head())
+                .and_then(|_| self.inner.validate_preflight(req.head()));
+
+            let res = match res {
+                Ok(()) => {
+                    let res = self.inner.preflight_response(req.head(), &origin);
+                    req.into_response(res).map_into_right_body()
+                }
+                Err(err) => req.error_response(err).map_into_right_body(),
+            };
+
+            return Box::pin(ready(Ok(res)));
+        }
+
+        let origin = match origin {
+            Some(origin) => match self.inner.validate_origin(&origin, req.head()) {
+                Ok(()) => Some(origin),
+
+                Err(err) if self.inner.block_on_origin_mismatch => {
+                    let mut res = req.error_response(err);
+                    self.inner.add_response_headers(res.headers_mut(), None);
+                    return Box::pin(ready(Ok(res.map_into_right_body())));
+                }
+
+                // pass through without any CORS headers
+                Err(_) => None,
+            },
+
+            None => None,
+        };
+
+        let inner = Rc::clone(&self.inner);
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+            inner.add_response_headers(res.headers_mut(), origin.as_ref());
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+    use actix_utils::future::ok;
+
+    use super::*;
+    use crate::test::{self, TestRequest};
+
+    fn preflight(origin: &str, method: &str) -> TestRequest {
+        TestRequest::default()
+            .method(Method::OPTIONS)
+            .insert_header((header::ORIGIN, origin))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, method))
+    }
+
+    #[actix_rt::test]
+    async fn default_blocks_all_origins() {
+        let mw = Cors::default()
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::get()
+            .insert_header((header::ORIGIN, "https://example.com"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        // same-origin requests are passed through
+        let req = TestRequest::get().to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "Origin");
+    }
+
+    #[actix_rt::test]
+    async fn preflight_allowed() {
+        let mw = Cors::default()
+            .allowed_origin("https://example.com")
+            .allowed_methods([Method::GET, Method::PUT])
+            .allowed_header(header::CONTENT_TYPE)
+            .max_age(600)
+            .new_transform(test::status_service(StatusCode::NOT_FOUND))
+            .await
+            .unwrap();
+
+        let req = preflight("https://example.com", "PUT")
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+
+        // answered by middleware; inner service never called
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let headers = res.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://example.com"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap(),
+            "GET, PUT"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+            "content-type"
+        );
+        assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let vary = headers
+            .get_all(header::VARY)
+            .map(|v| v.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vary,
+            [
+                "Origin",
+                "Access-Control-Request-Method",
+                "Access-Control-Request-Headers"
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn preflight_rejected() {
+        let mw = Cors::default()
+            .allowed_origin("https://example.com")
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = preflight("https://evil.com", "GET").to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req = preflight("https://example.com", "DELETE").to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req = preflight("https://example.com", "GET")
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "x-custom"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // OPTIONS without request method header is not a preflight
+        let req = TestRequest::default()
+            .method(Method::OPTIONS)
+            .insert_header((header::ORIGIN, "https://example.com"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn origin_predicate() {
+        let mw = Cors::default()
+            .allowed_origin_fn(|origin, _| origin.as_bytes().ends_with(b".example.com"))
+            .expose_headers([header::ETAG])
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::get()
+            .insert_header((header::ORIGIN, "https://api.example.com"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "https://api.example.com"
+        );
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_EXPOSE_HEADERS)
+                .unwrap(),
+            "etag"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn wildcard_and_credentials() {
+        let mw = Cors::permissive()
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::get()
+            .insert_header((header::ORIGIN, "https://example.com"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "*"
+        );
+        assert!(!res.headers().contains_key(header::VARY));
+
+        let mw = Cors::permissive()
+            .supports_credentials()
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = preflight("https://example.com", "PATCH")
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "x-a, x-b"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        let headers = res.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://example.com"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap(),
+            "PATCH"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+            "x-a, x-b"
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .unwrap(),
+            "true"
+        );
+        assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+    }
+
+    #[actix_rt::test]
+    async fn non_blocking_mismatch() {
+        let mw = Cors::default()
+            .allowed_origin("https://example.com")
+            .block_on_origin_mismatch(false)
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::get()
+            .insert_header((header::ORIGIN, "https://evil.com"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "Origin");
+    }
+
+    #[actix_rt::test]
+    async fn vary_not_duplicated() {
+        let srv = |req: ServiceRequest| {
+            ok(req.into_response(
+                HttpResponse::Ok()
+                    .insert_header((header::VARY, "Accept-Encoding, origin"))
+                    .finish(),
+            ))
+        };
+
+        let mw = Cors::default()
+            .allowed_origin("https://example.com")
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::get()
+            .insert_header((header::ORIGIN, "https://example.com"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.headers().get_all(header::VARY).count(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wildcard_origin_string() {
+        let _ = Cors::default().allowed_origin("*");
+    }
+}
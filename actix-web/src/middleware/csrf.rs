@@ -0,0 +1,668 @@
+//! For middleware documentation, see [`Csrf`].
+
+use std::{borrow::Cow, collections::HashSet, fmt, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use cookie::{Cookie, SameSite};
+use derive_more::derive::{Display, Error};
+use futures_core::future::LocalBoxFuture;
+use rand::{distributions::Alphanumeric, Rng as _};
+
+use crate::{
+    body::EitherBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    helpers::buffer_payload,
+    http::{
+        header::{self, HeaderName},
+        Method, StatusCode,
+    },
+    Error, FromRequest, HttpMessage as _, HttpRequest, ResponseError,
+};
+
+/// Length of generated CSRF tokens.
+const TOKEN_LEN: usize = 43;
+
+/// Errors that can occur when validating requests for cross-site request forgery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+#[non_exhaustive]
+pub enum CsrfError {
+    /// `Origin` or `Referer` header does not match the request host or any trusted origin.
+    #[display("Cross-origin request rejected")]
+    OriginMismatch,
+
+    /// Request did not include a CSRF token cookie, or did not submit a token.
+    #[display("CSRF token missing")]
+    MissingToken,
+
+    /// Submitted CSRF token does not match the CSRF token cookie.
+    #[display("CSRF token mismatch")]
+    TokenMismatch,
+}
+
+impl ResponseError for CsrfError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+}
+
+/// CSRF token extractor.
+///
+/// Use this to render the token into forms as a hidden field or into pages for scripts to submit
+/// in a request header. See [`Csrf`] for details.
+///
+/// Extraction fails with a 500 error if the [`Csrf`] middleware is not in use.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::CsrfToken, web::Html};
+///
+/// async fn form(token: CsrfToken) -> Html {
+///     Html::new(format!(
+///         r#"<form method="post"><input type="hidden" name="csrf_token" value="{token}"></form>"#
+///     ))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    /// Returns the token as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Unwraps into inner token string.
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl FromRequest for CsrfToken {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<CsrfToken>().cloned().ok_or_else(|| {
+            log::error!("CsrfToken extractor used without Csrf middleware.");
+            ErrorInternalServerError("CSRF protection is not configured")
+        }))
+    }
+}
+
+struct Inner {
+    check_origin: bool,
+    trusted_origins: HashSet<String>,
+    check_token: bool,
+    cookie_name: Cow<'static, str>,
+    cookie_path: Cow<'static, str>,
+    cookie_domain: Option<Cow<'static, str>>,
+    cookie_secure: bool,
+    cookie_http_only: bool,
+    header_name: HeaderName,
+    form_field: Cow<'static, str>,
+}
+
+/// Middleware for protecting against cross-site request forgery (CSRF).
+///
+/// Requests using safe methods (`GET`, `HEAD`, `OPTIONS` and `TRACE`) are never rejected. Requests
+/// using any other method are subject to two independent checks, both enabled by default.
+///
+/// # Origin Check
+/// The `Origin` header (or, if absent, the `Referer` header) must name the same origin as the
+/// request, or one of the configured [trusted origins](Self::trusted_origin). The scheme, host and
+/// port of the request are determined by [`ConnectionInfo::scheme()`] and
+/// [`ConnectionInfo::host()`]. Requests with an `Origin` of `null` are rejected. Requests carrying
+/// neither header are not rejected by this check since they are not sent by browsers making
+/// cross-site requests.
+///
+/// # Double-Submit Token Check
+/// A random token is issued to each client in a cookie. Unsafe requests must submit the same token
+/// in either a request header (default: `X-CSRF-Token`) or, for URL-encoded forms, a form field
+/// (default: `csrf_token`). Since cross-site pages can neither read nor set the cookie, they
+/// cannot submit a matching token.
+///
+/// Handlers read the token using the [`CsrfToken`] extractor. If the client does not yet have a
+/// token cookie, a new token is generated and the cookie is set on the response.
+///
+/// Checking the form field requires buffering the request body, subject to the limit set by
+/// [`PayloadConfig`](crate::web::PayloadConfig). Compressed bodies are decoded and passed on to the
+/// wrapped service without their `Content-Encoding` header.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::Csrf, web, App, HttpResponse};
+///
+/// let app = App::new()
+///     .wrap(Csrf::new().trusted_origin("https://admin.example.com"))
+///     .route("/", web::post().to(HttpResponse::Ok));
+/// ```
+///
+/// [`ConnectionInfo::scheme()`]: crate::dev::ConnectionInfo::scheme
+/// [`ConnectionInfo::host()`]: crate::dev::ConnectionInfo::host
+pub struct Csrf {
+    inner: Rc<Inner>,
+}
+
+impl Csrf {
+    /// Constructs CSRF middleware with both origin and token checks enabled.
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                check_origin: true,
+                trusted_origins: HashSet::new(),
+                check_token: true,
+                cookie_name: Cow::Borrowed("csrf-token"),
+                cookie_path: Cow::Borrowed("/"),
+                cookie_domain: None,
+                cookie_secure: true,
+                cookie_http_only: false,
+                header_name: HeaderName::from_static("x-csrf-token"),
+                form_field: Cow::Borrowed("csrf_token"),
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Csrf must be configured before it is cloned")
+    }
+
+    /// Sets whether `Origin` and `Referer` headers are checked.
+    pub fn origin_check(mut self, enabled: bool) -> Self {
+        self.inner_mut().check_origin = enabled;
+        self
+    }
+
+    /// Allows unsafe requests originating from `origin`, in addition to the request host.
+    ///
+    /// The origin should be in the serialized form of `scheme://host[:port]`, without a trailing
+    /// slash.
+    pub fn trusted_origin(mut self, origin: impl Into<String>) -> Self {
+        let origin = origin.into().to_ascii_lowercase();
+        self.inner_mut().trusted_origins.insert(origin);
+        self
+    }
+
+    /// Sets whether the double-submit token is checked.
+    ///
+    /// When disabled, tokens are still issued so that templates can render them.
+    pub fn token_check(mut self, enabled: bool) -> Self {
+        self.inner_mut().check_token = enabled;
+        self
+    }
+
+    /// Sets the name of the token cookie. Defaults to `csrf-token`.
+    ///
+    /// Consider using a `__Host-` prefixed name to prevent the cookie being set by subdomains.
+    pub fn cookie_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().cookie_name = name.into();
+        self
+    }
+
+    /// Sets the `Path` attribute of the token cookie. Defaults to `/`.
+    pub fn cookie_path(mut self, path: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().cookie_path = path.into();
+        self
+    }
+
+    /// Sets the `Domain` attribute of the token cookie.
+    pub fn cookie_domain(mut self, domain: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().cookie_domain = Some(domain.into());
+        self
+    }
+
+    /// Sets whether the token cookie has the `Secure` attribute. Defaults to `true`.
+    pub fn cookie_secure(mut self, secure: bool) -> Self {
+        self.inner_mut().cookie_secure = secure;
+        self
+    }
+
+    /// Sets whether the token cookie has the `HttpOnly` attribute. Defaults to `false`.
+    ///
+    /// Enable this if the token is only ever rendered server-side and never read by scripts.
+    pub fn cookie_http_only(mut self, http_only: bool) -> Self {
+        self.inner_mut().cookie_http_only = http_only;
+        self
+    }
+
+    /// Sets the request header in which the token can be submitted. Defaults to `X-CSRF-Token`.
+    pub fn header_name(mut self, name: HeaderName) -> Self {
+        self.inner_mut().header_name = name;
+        self
+    }
+
+    /// Sets the URL-encoded form field in which the token can be submitted. Defaults to
+    /// `csrf_token`.
+    pub fn form_field(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().form_field = name.into();
+        self
+    }
+}
+
+impl Default for Csrf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Csrf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Csrf")
+            .field("check_origin", &self.inner.check_origin)
+            .field("trusted_origins", &self.inner.trusted_origins)
+            .field("check_token", &self.inner.check_token)
+            .field("cookie_name", &self.inner.cookie_name)
+            .field("header_name", &self.inner.header_name)
+            .field("form_field", &self.inner.form_field)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    fn validate_origin(&self, req: &ServiceRequest) -> Result<(), CsrfError> {
+        let headers = req.headers();
+
+        let origin = match headers.get(header::ORIGIN) {
+            Some(origin) => origin.to_str().ok().map(str::to_owned),
+
+            // fall back to origin of referer
+            None => match headers.get(header::REFERER) {
+                Some(referer) => referer
+                    .to_str()
+                    .ok()
+                    .and_then(|referer| url::Url::parse(referer).ok())
+                    .map(|url| url.origin().ascii_serialization()),
+
+                // neither header; not a cross-site browser request
+                None => return Ok(()),
+            },
+        };
+
+        let origin = origin
+            .filter(|origin| origin != "null")
+            .ok_or(CsrfError::OriginMismatch)?
+            .to_ascii_lowercase();
+
+        if self.trusted_origins.contains(&origin) {
+            return Ok(());
+        }
+
+        let conn_info = req.connection_info();
+        let request_origin =
+            url::Url::parse(&format!("{}://{}", conn_info.scheme(), conn_info.host()))
+                .map(|url| url.origin());
+
+        // compares scheme, host and port, ignoring default ports
+        match (url::Url::parse(&origin), request_origin) {
+            (Ok(url), Ok(request_origin)) if url.origin() == request_origin => Ok(()),
+            _ => Err(CsrfError::OriginMismatch),
+        }
+    }
+
+    /// Returns token submitted in request header or form field, buffering the body if needed.
+    async fn submitted_token(&self, req: &mut ServiceRequest) -> Result<Option<String>, Error> {
+        if let Some(token) = req.headers().get(&self.header_name) {
+            return Ok(token.to_str().ok().map(str::to_owned));
+        }
+
+        if req.content_type() != mime::APPLICATION_WWW_FORM_URLENCODED.essence_str() {
+            return Ok(None);
+        }
+
+        let body = buffer_payload(req).await?;
+
+        let token = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&body)
+            .ok()
+            .and_then(|fields| {
+                fields
+                    .into_iter()
+                    .find(|(name, _)| *name == self.form_field)
+                    .map(|(_, val)| val)
+            });
+
+        Ok(token)
+    }
+
+    fn cookie(&self, token: &str) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.cookie_name.clone(), token.to_owned());
+
+        cookie.set_path(self.cookie_path.clone());
+        cookie.set_secure(self.cookie_secure);
+        cookie.set_http_only(self.cookie_http_only);
+        cookie.set_same_site(SameSite::Strict);
+
+        if let Some(domain) = &self.cookie_domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        cookie
+    }
+}
+
+fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LEN)
+        .map(char::from)
+        .collect()
+}
+
+/// Compares byte slices in time independent of where they first differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Csrf
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = CsrfMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CsrfMiddleware {
+            service: Rc::new(service),
+            inner: Rc::clone(&self.inner),
+        }))
+    }
+}
+
+/// Service created by [`Csrf`] middleware.
+pub struct CsrfMiddleware<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let inner = Rc::clone(&self.inner);
+
+        Box::pin(async move {
+            let cookie_token = req
+                .cookie(&inner.cookie_name)
+                .map(|cookie| cookie.value().to_owned())
+                .filter(|token| !token.is_empty());
+
+            if !is_safe_method(req.method()) {
+                if inner.check_origin {
+                    if let Err(err) = inner.validate_origin(&req) {
+                        return Ok(req.error_response(err).map_into_right_body());
+                    }
+                }
+
+                if inner.check_token {
+                    let submitted = inner.submitted_token(&mut req).await?;
+
+                    let res = match (&cookie_token, submitted) {
+                        (Some(expected), Some(submitted)) => {
+                            if constant_time_eq(expected.as_bytes(), submitted.as_bytes()) {
+                                Ok(())
+                            } else {
+                                Err(CsrfError::TokenMismatch)
+                            }
+                        }
+                        _ => Err(CsrfError::MissingToken),
+                    };
+
+                    if let Err(err) = res {
+                        return Ok(req.error_response(err).map_into_right_body());
+                    }
+                }
+            }
+
+            let (token, is_new) = match cookie_token {
+                Some(token) => (token, false),
+                None => (generate_token(), true),
+            };
+
+            req.extensions_mut().insert(CsrfToken(token.clone()));
+
+            let mut res = service.call(req).await?;
+
+            if is_new {
+                res.response_mut().add_cookie(&inner.cookie(&token))?;
+            }
+
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    async fn echo_token(token: CsrfToken, body: Bytes) -> HttpResponse {
+        HttpResponse::Ok().body(format!("{}|{}", token, String::from_utf8_lossy(&body)))
+    }
+
+    fn token_cookie<B>(res: &ServiceResponse<B>) -> Option<Cookie<'static>> {
+        res.response()
+            .cookies()
+            .find(|cookie| cookie.name() == "csrf-token")
+            .map(Cookie::into_owned)
+    }
+
+    #[actix_rt::test]
+    async fn issues_token_on_safe_request() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Csrf::new())
+                .default_service(web::to(echo_token)),
+        )
+        .await;
+
+        let res = test::call_service(&app, TestRequest::get().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let cookie = token_cookie(&res).unwrap();
+        assert_eq!(cookie.value().len(), TOKEN_LEN);
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        let body = test::read_body(res).await;
+        assert_eq!(body, format!("{}|", cookie.value()));
+
+        // existing token is reused
+        let req = TestRequest::get().cookie(cookie.clone()).to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(token_cookie(&res).is_none());
+        let body = test::read_body(res).await;
+        assert_eq!(body, format!("{}|", cookie.value()));
+    }
+
+    #[actix_rt::test]
+    async fn double_submit() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Csrf::new().origin_check(false))
+                .default_service(web::to(echo_token)),
+        )
+        .await;
+
+        let cookie = Cookie::new("csrf-token", "abc");
+
+        let req = TestRequest::post().to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .insert_header(("x-csrf-token", "abd"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::post()
+            .insert_header(("x-csrf-token", "abc"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .insert_header(("x-csrf-token", "abc"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // form field is read and body is still available to handler
+        let req = TestRequest::post()
+            .cookie(cookie.clone())
+            .set_form([("name", "ferris"), ("csrf_token", "abc")])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = test::read_body(res).await;
+        assert_eq!(body, "abc|name=ferris&csrf_token=abc");
+
+        let req = TestRequest::post()
+            .cookie(cookie)
+            .set_form([("csrf_token", "xyz")])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    #[actix_rt::test]
+    async fn compressed_form() {
+        use std::{collections::HashMap, io::Write as _};
+
+        async fn name(form: web::Form<HashMap<String, String>>) -> HttpResponse {
+            HttpResponse::Ok().body(form["name"].clone())
+        }
+
+        let app = test::init_service(
+            App::new()
+                .wrap(Csrf::new().origin_check(false))
+                .default_service(web::to(name)),
+        )
+        .await;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(b"name=ferris&csrf_token=abc").unwrap();
+
+        let req = TestRequest::post()
+            .cookie(Cookie::new("csrf-token", "abc"))
+            .insert_header(header::ContentType::form_url_encoded())
+            .insert_header((header::CONTENT_ENCODING, "gzip"))
+            .set_payload(encoder.finish().unwrap())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "ferris");
+    }
+
+    #[actix_rt::test]
+    async fn origin_check() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Csrf::new()
+                        .token_check(false)
+                        .trusted_origin("https://admin.example.com"),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let post = || TestRequest::post().uri("https://example.com/");
+
+        let req = post()
+            .insert_header((header::ORIGIN, "https://example.com"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = post()
+            .insert_header((header::ORIGIN, "https://admin.example.com"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        // scheme and port must match too
+        for origin in ["http://example.com", "https://example.com:8443"] {
+            let req = post().insert_header((header::ORIGIN, origin)).to_request();
+            assert_eq!(
+                test::call_service(&app, req).await.status(),
+                StatusCode::FORBIDDEN
+            );
+        }
+
+        let req = TestRequest::post()
+            .uri("http://example.com:8080/")
+            .insert_header((header::ORIGIN, "http://example.com:8080"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = post()
+            .insert_header((header::ORIGIN, "https://evil.com"))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let req = post().insert_header((header::ORIGIN, "null")).to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let req = post()
+            .insert_header((header::REFERER, "https://example.com/form?a=b"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = post()
+            .insert_header((header::REFERER, "https://evil.com/example.com"))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+
+        // no origin information at all
+        let req = post().to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        // safe methods are never checked
+        let req = TestRequest::get()
+            .insert_header((header::ORIGIN, "https://evil.com"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn extractor_without_middleware() {
+        let req = TestRequest::default().to_http_request();
+        assert!(CsrfToken::extract(&req).await.is_err());
+    }
+}
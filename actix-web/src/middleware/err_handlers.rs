@@ -15,8 +15,9 @@ use pin_project_lite::pin_project;
 use crate::{
     body::EitherBody,
     dev::{ServiceRequest, ServiceResponse},
+    error::ProblemDetails,
     http::StatusCode,
-    Error, Result,
+    Error, ResponseError as _, Result,
 };
 
 /// Return type for [`ErrorHandlers`] custom handlers.
@@ -261,6 +262,37 @@ impl<B> ErrorHandlers<B> {
         }
     }
 
+    /// Constructs `ErrorHandlers` that render errors from the built-in extractors as
+    /// [`ProblemDetails`].
+    ///
+    /// Error responses caused by [`JsonPayloadError`], [`PathError`], [`QueryPayloadError`] and
+    /// [`UrlencodedError`] are replaced with `application/problem+json` documents that keep the
+    /// response's status code and, for client errors, use the error message as the `detail`.
+    /// Other responses pass by unchanged.
+    ///
+    /// This sets the [default handler](Self::default_handler); more specific handlers can still be
+    /// added.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::{middleware::ErrorHandlers, web, App};
+    ///
+    /// let app = App::new()
+    ///     .wrap(ErrorHandlers::problem_details())
+    ///     .route("/{id}", web::get().to(|id: web::Path<u32>| async move { id.to_string() }));
+    /// ```
+    ///
+    /// [`JsonPayloadError`]: crate::error::JsonPayloadError
+    /// [`PathError`]: crate::error::PathError
+    /// [`QueryPayloadError`]: crate::error::QueryPayloadError
+    /// [`UrlencodedError`]: crate::error::UrlencodedError
+    pub fn problem_details() -> Self
+    where
+        B: 'static,
+    {
+        Self::new().default_handler(problem_details_handler)
+    }
+
     /// Selects the most appropriate handler for the given status code.
     ///
     /// If the `handlers` map has an entry for that status code, that handler is returned.
@@ -279,6 +311,23 @@ impl<B> ErrorHandlers<B> {
     }
 }
 
+/// Replaces error responses from the built-in extractors with problem details.
+fn problem_details_handler<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> {
+    let problem = res
+        .response()
+        .error()
+        .and_then(|err| ProblemDetails::from_extractor_error(err, res.status()));
+
+    let res = match problem {
+        Some(problem) => res
+            .into_response(problem.error_response())
+            .map_into_right_body(),
+        None => res.map_into_left_body(),
+    };
+
+    Ok(ErrorHandlerResponse::Response(res))
+}
+
 impl<S, B> Transform<S, ServiceRequest> for ErrorHandlers<B>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
@@ -413,6 +462,7 @@ mod tests {
         body,
         http::header::{HeaderValue, CONTENT_TYPE},
         test::{self, TestRequest},
+        web, App, HttpResponse,
     };
 
     #[actix_rt::test]
@@ -621,4 +671,47 @@ mod tests {
         let resp = test::call_service(&mw_specific, TestRequest::default().to_srv_request()).await;
         assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "0003");
     }
+
+    #[actix_rt::test]
+    async fn problem_details() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ErrorHandlers::problem_details())
+                .route(
+                    "/{id}",
+                    web::post().to(|_: web::Path<u32>, _: web::Json<u32>| async { "ok" }),
+                )
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+
+        let req = TestRequest::post().uri("/abc").set_json(1).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["title"], "Not Found");
+        assert!(body["detail"].is_string());
+
+        let req = TestRequest::post()
+            .uri("/1")
+            .insert_header((CONTENT_TYPE, "text/plain"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+
+        // other error responses are unchanged
+        let req = TestRequest::get().uri("/a/b").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers().get(CONTENT_TYPE).is_none());
+    }
 }
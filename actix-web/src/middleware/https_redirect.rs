@@ -0,0 +1,377 @@
+//! For middleware documentation, see [`HttpsRedirect`].
+
+use std::{collections::HashMap, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::EitherBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        header::{self, StrictTransportSecurity, TryIntoHeaderValue as _},
+        StatusCode,
+    },
+    Error, HttpResponse,
+};
+
+/// Middleware for redirecting plain HTTP requests to HTTPS and enforcing HTTPS using HSTS.
+///
+/// Whether a request was made over HTTPS is determined by
+/// [`ConnectionInfo::scheme()`](crate::dev::ConnectionInfo::scheme), so this also works behind TLS
+/// terminating proxies that set the `Forwarded` or `X-Forwarded-Proto` header. Consider also using
+/// [`TrustedProxies`](crate::dev::TrustedProxies) in that case.
+///
+/// Plain HTTP requests are redirected to the same host, path and query using the `https` scheme.
+/// The redirect uses `308 Permanent Redirect` by default so that clients repeat the request method
+/// and body. Requests whose path starts with an [exempt](Self::exempt) prefix are passed through
+/// instead; this is commonly needed for ACME HTTP-01 challenges.
+///
+/// When an [HSTS policy](Self::hsts) is set, a `Strict-Transport-Security` header is added to
+/// responses to HTTPS requests, unless the response already has one.
+///
+/// # Ports
+/// Unless a [port mapping](Self::map_port) applies, the redirect URL omits the port so that the
+/// default HTTPS port is used.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_web::{
+///     http::header::StrictTransportSecurity, middleware::HttpsRedirect, web, App, HttpResponse,
+/// };
+///
+/// let app = App::new()
+///     .wrap(
+///         HttpsRedirect::new()
+///             .map_port(8080, 8443)
+///             .exempt("/.well-known/acme-challenge/")
+///             .hsts(StrictTransportSecurity::new(Duration::from_secs(31_536_000))),
+///     )
+///     .default_service(web::to(HttpResponse::Ok));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpsRedirect {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    status: StatusCode,
+    ports: HashMap<u16, u16>,
+    exempt: Vec<String>,
+    hsts: Option<header::HeaderValue>,
+}
+
+impl HttpsRedirect {
+    /// Constructs a new `HttpsRedirect` middleware that redirects all plain HTTP requests.
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                status: StatusCode::PERMANENT_REDIRECT,
+                ports: HashMap::new(),
+                exempt: Vec::new(),
+                hsts: None,
+            }),
+        }
+    }
+
+    /// Sets the status code used for redirects.
+    ///
+    /// # Panics
+    /// Panics if `status` is not a redirection (3xx) status code.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        assert!(
+            status.is_redirection(),
+            "HttpsRedirect status must be a redirection status code"
+        );
+
+        self.inner_mut().status = status;
+        self
+    }
+
+    /// Redirects requests received on `http_port` to `https_port`.
+    ///
+    /// The port is determined from the request's host, as reported by
+    /// [`ConnectionInfo::host()`](crate::dev::ConnectionInfo::host).
+    pub fn map_port(mut self, http_port: u16, https_port: u16) -> Self {
+        self.inner_mut().ports.insert(http_port, https_port);
+        self
+    }
+
+    /// Passes through plain HTTP requests whose path starts with `prefix`.
+    pub fn exempt(mut self, prefix: impl Into<String>) -> Self {
+        self.inner_mut().exempt.push(prefix.into());
+        self
+    }
+
+    /// Adds the given `Strict-Transport-Security` header to responses to HTTPS requests.
+    pub fn hsts(mut self, policy: StrictTransportSecurity) -> Self {
+        self.inner_mut().hsts = Some(policy.try_into_value().unwrap());
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("HttpsRedirect must be configured before cloning.")
+    }
+}
+
+impl Default for HttpsRedirect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inner {
+    /// Returns the HTTPS location for a plain HTTP request, unless its path is exempt.
+    fn redirect_location(&self, req: &ServiceRequest) -> Option<String> {
+        let path = req.path();
+
+        if self
+            .exempt
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+        {
+            return None;
+        }
+
+        let conn_info = req.connection_info();
+        let (host, port) = split_port(conn_info.host());
+
+        let mut location = format!("https://{host}");
+
+        if let Some(port) = port.and_then(|port| self.ports.get(&port)) {
+            if *port != 443 {
+                location.push_str(&format!(":{port}"));
+            }
+        }
+
+        match req.uri().path_and_query() {
+            Some(path_and_query) if path_and_query.as_str().starts_with('/') => {
+                location.push_str(path_and_query.as_str())
+            }
+            _ => location.push('/'),
+        }
+
+        Some(location)
+    }
+}
+
+/// Splits the port from a host, if it has one.
+fn split_port(host: &str) -> (&str, Option<u16>) {
+    // IPv6 addresses without a port end with a bracket
+    if host.ends_with(']') {
+        return (host, None);
+    }
+
+    match host.rsplit_once(':') {
+        Some((name, port)) => match port.parse() {
+            Ok(port) => (name, Some(port)),
+            Err(_) => (host, None),
+        },
+        None => (host, None),
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HttpsRedirect
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = HttpsRedirectMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HttpsRedirectMiddleware {
+            service: Rc::new(service),
+            inner: Rc::clone(&self.inner),
+        }))
+    }
+}
+
+/// Service created by [`HttpsRedirect`] middleware.
+pub struct HttpsRedirectMiddleware<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for HttpsRedirectMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let is_secure = req.connection_info().scheme() == "https";
+
+        if !is_secure {
+            if let Some(location) = self.inner.redirect_location(&req) {
+                let res = HttpResponse::build(self.inner.status)
+                    .insert_header((header::LOCATION, location))
+                    .finish();
+                let res = req.into_response(res).map_into_right_body();
+
+                return Box::pin(async move { Ok(res) });
+            }
+        }
+
+        let service = Rc::clone(&self.service);
+        let inner = Rc::clone(&self.inner);
+
+        Box::pin(async move {
+            let mut res = service.call(req).await?;
+
+            if let Some(hsts) = inner.hsts.as_ref().filter(|_| is_secure) {
+                let headers = res.headers_mut();
+
+                if !headers.contains_key(header::STRICT_TRANSPORT_SECURITY) {
+                    headers.insert(header::STRICT_TRANSPORT_SECURITY, hsts.clone());
+                }
+            }
+
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    #[actix_rt::test]
+    async fn redirects_plain_http() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    HttpsRedirect::new()
+                        .map_port(8080, 8443)
+                        .map_port(80, 443)
+                        .exempt("/.well-known/acme-challenge/"),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/a/b?c=d")
+            .insert_header((header::HOST, "example.com"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "https://example.com/a/b?c=d"
+        );
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header((header::HOST, "example.com:8080"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "https://example.com:8443/"
+        );
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "[::1]:80"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "https://[::1]/"
+        );
+
+        // unmapped ports are dropped
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "example.com:3000"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "https://example.com/"
+        );
+
+        let req = TestRequest::with_uri("/.well-known/acme-challenge/token").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("https://example.com/").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+    }
+
+    #[actix_rt::test]
+    async fn adds_hsts_header() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    HttpsRedirect::new()
+                        .status(StatusCode::MOVED_PERMANENTLY)
+                        .hsts(
+                            StrictTransportSecurity::new(Duration::from_secs(600))
+                                .include_subdomains(),
+                        ),
+                )
+                .route(
+                    "/custom",
+                    web::to(|| {
+                        HttpResponse::Ok()
+                            .insert_header(StrictTransportSecurity::new(Duration::ZERO))
+                            .finish()
+                    }),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("https://example.com/").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.headers()
+                .get(header::STRICT_TRANSPORT_SECURITY)
+                .unwrap(),
+            "max-age=600; includeSubDomains"
+        );
+
+        let req = TestRequest::with_uri("https://example.com/custom").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.headers()
+                .get(header::STRICT_TRANSPORT_SECURITY)
+                .unwrap(),
+            "max-age=0"
+        );
+
+        let req = TestRequest::with_uri("http://example.com/").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert!(!res
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+    }
+
+    #[test]
+    #[should_panic]
+    fn non_redirect_status() {
+        HttpsRedirect::new().status(StatusCode::OK);
+    }
+}
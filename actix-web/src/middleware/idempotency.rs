@@ -0,0 +1,894 @@
+//! For middleware documentation, see [`Idempotency`].
+
+use std::{
+    fmt,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use bytes::Bytes;
+use derive_more::derive::{Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::task::noop_waker_ref;
+use sha2::{Digest as _, Sha256};
+
+use crate::{
+    body::{self, BoxBody, EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    expiry::ExpiringMap,
+    helpers::buffer_payload,
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+    Error, HttpResponse, ResponseError,
+};
+
+/// Default time for which [`InMemoryIdempotencyStore`] keeps records.
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default time for which [`InMemoryIdempotencyStore`] keeps reservations of requests that are
+/// still in progress.
+const DEFAULT_IN_PROGRESS_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Maximum length, in bytes, of an idempotency key.
+const MAX_KEY_LEN: usize = 255;
+
+/// Errors returned by [`Idempotency`] middleware when a request cannot be processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+#[non_exhaustive]
+pub enum IdempotencyError {
+    /// Request did not include an idempotency key, but one is [required](Idempotency::required).
+    #[display("Idempotency key missing")]
+    MissingKey,
+
+    /// Idempotency key is empty, longer than 255 bytes or not visible ASCII.
+    #[display("Idempotency key invalid")]
+    InvalidKey,
+
+    /// A request with the same idempotency key is still being processed.
+    #[display("Request with the same idempotency key is in progress")]
+    InProgress,
+
+    /// Idempotency key was already used for a request with a different method, URI or body.
+    #[display("Idempotency key was used for a different request")]
+    KeyReused,
+}
+
+impl ResponseError for IdempotencyError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::MissingKey | Self::InvalidKey => StatusCode::BAD_REQUEST,
+            Self::InProgress => StatusCode::CONFLICT,
+            Self::KeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+/// A response stored by [`Idempotency`] middleware.
+#[derive(Debug, Clone)]
+pub struct IdempotentResponse {
+    /// Response status code.
+    pub status: StatusCode,
+
+    /// Response headers.
+    pub headers: HeaderMap,
+
+    /// Response body.
+    pub body: Bytes,
+}
+
+/// Record of a request made with an idempotency key.
+#[derive(Debug, Clone)]
+pub struct IdempotencyRecord {
+    /// SHA-256 digest of the request's method, URI and decoded body, in hex.
+    pub fingerprint: String,
+
+    /// Response to the request, or `None` while it is still being processed.
+    pub response: Option<IdempotentResponse>,
+}
+
+/// Storage backend for [`Idempotency`].
+///
+/// A key is first [reserved](Self::reserve) while its request is processed, then either
+/// [completed](Self::complete) with the response or [released](Self::release) so the request can
+/// be retried. Reservations are released when the request is cancelled, but stores should still
+/// expire them after a short time in case the server stops while processing a request. See
+/// [sharing state between workers](crate::middleware#sharing-state-between-workers).
+pub trait IdempotencyStore {
+    /// Reserves `key` for a request with the given fingerprint, unless a record for it exists.
+    ///
+    /// Returns `None` if the key was reserved, or the existing record otherwise. Reservation must
+    /// be atomic, so that only one of several concurrent requests with the same key succeeds.
+    fn reserve(
+        &self,
+        key: &str,
+        fingerprint: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<IdempotencyRecord>, Error>>;
+
+    /// Stores the response to the request that reserved `key`.
+    fn complete(
+        &self,
+        key: &str,
+        res: IdempotentResponse,
+    ) -> LocalBoxFuture<'static, Result<(), Error>>;
+
+    /// Removes the reservation of `key` without storing a response.
+    fn release(&self, key: &str) -> LocalBoxFuture<'static, Result<(), Error>>;
+}
+
+/// In-memory [`IdempotencyStore`] shared across all workers of a server.
+///
+/// Responses are kept for a fixed time after they were stored (24 hours by default). Reservations
+/// of requests still in progress expire separately, after 5 minutes by default, so that a key is
+/// not blocked for long if its request never completes. Expired records are dropped as new keys are
+/// reserved. Cloning this type produces a new handle to the same records.
+#[derive(Clone)]
+pub struct InMemoryIdempotencyStore {
+    records: Arc<Mutex<ExpiringMap<IdempotencyRecord>>>,
+    ttl: Duration,
+    in_progress_ttl: Duration,
+}
+
+impl InMemoryIdempotencyStore {
+    /// Constructs a new in-memory store that keeps responses for 24 hours.
+    pub fn new() -> Self {
+        Self::with_ttl(DEFAULT_TTL)
+    }
+
+    /// Constructs a new in-memory store that keeps responses for `ttl`.
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            records: Arc::default(),
+            ttl,
+            in_progress_ttl: DEFAULT_IN_PROGRESS_TTL,
+        }
+    }
+
+    /// Sets how long a key stays reserved while its request is in progress. Defaults to 5 minutes.
+    ///
+    /// This should be longer than requests take to process; once it elapses, a request with the
+    /// same key is processed again.
+    pub fn in_progress_ttl(mut self, ttl: Duration) -> Self {
+        self.in_progress_ttl = ttl;
+        self
+    }
+
+    /// Returns the number of stored records, including expired ones not yet dropped.
+    pub fn len(&self) -> usize {
+        self.records
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .len()
+    }
+
+    /// Returns true if no records are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryIdempotencyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for InMemoryIdempotencyStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryIdempotencyStore")
+            .field("len", &self.len())
+            .field("ttl", &self.ttl)
+            .field("in_progress_ttl", &self.in_progress_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    fn reserve(
+        &self,
+        key: &str,
+        fingerprint: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<IdempotencyRecord>, Error>> {
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap_or_else(|err| err.into_inner());
+        records.purge(now);
+
+        let existing = match records.get(key, now) {
+            Some(record) => Some(record.clone()),
+            None => {
+                let record = IdempotencyRecord {
+                    fingerprint: fingerprint.to_owned(),
+                    response: None,
+                };
+
+                records.insert(key.to_owned(), record, now + self.in_progress_ttl);
+                None
+            }
+        };
+
+        Box::pin(ready(Ok(existing)))
+    }
+
+    fn complete(
+        &self,
+        key: &str,
+        res: IdempotentResponse,
+    ) -> LocalBoxFuture<'static, Result<(), Error>> {
+        let mut records = self.records.lock().unwrap_or_else(|err| err.into_inner());
+
+        if let Some(mut record) = records.remove(key) {
+            record.response = Some(res);
+            records.insert(key.to_owned(), record, Instant::now() + self.ttl);
+        }
+
+        Box::pin(ready(Ok(())))
+    }
+
+    fn release(&self, key: &str) -> LocalBoxFuture<'static, Result<(), Error>> {
+        self.records
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(key);
+
+        Box::pin(ready(Ok(())))
+    }
+}
+
+type ScopeFn = dyn Fn(&ServiceRequest) -> Option<String>;
+
+/// Source of the scope that idempotency keys are confined to.
+#[derive(Clone)]
+enum Scope {
+    Authorization,
+    Custom(Rc<ScopeFn>),
+}
+
+impl Scope {
+    /// Returns a SHA-256 digest of the request's scope, in hex, or `None` if it has no scope.
+    fn digest(&self, req: &ServiceRequest) -> Option<String> {
+        let digest = match self {
+            Self::Authorization => Sha256::digest(req.headers().get(header::AUTHORIZATION)?),
+            Self::Custom(scope_fn) => Sha256::digest(scope_fn(req)?),
+        };
+
+        Some(format!("{digest:x}"))
+    }
+}
+
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Authorization => f.write_str("Authorization"),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+struct Inner {
+    store: Box<dyn IdempotencyStore>,
+    header_name: HeaderName,
+    methods: Vec<Method>,
+    required: bool,
+    scope: Scope,
+}
+
+/// Middleware implementing the `Idempotency-Key` request header, which lets clients safely retry
+/// requests that are not idempotent, such as payments.
+///
+/// The first `POST` or `PATCH` request with a given key is processed normally and its response is
+/// stored, including the body. Later requests with the same key, method, URI and body are not
+/// passed to the wrapped service; instead they receive the stored response, with an
+/// `Idempotent-Replayed: true` header added. Requests with the same key:
+/// - that arrive while the first is still being processed are rejected with `409 Conflict`;
+/// - that have a different method, URI or body are rejected with `422 Unprocessable Entity`.
+///
+/// Responses with a server error (5xx) status and errors returned by the wrapped service are not
+/// stored, so the request can be retried with the same key; nor are responses to requests that are
+/// cancelled, e.g., because the client disconnected. Requests without a key are passed through,
+/// unless a key is [required](Self::required). See [`IdempotencyError`] for all rejections.
+///
+/// Keys are scoped to the client, so that a client reusing another's key is not sent the other
+/// client's response. By default the scope is the request's `Authorization` header; applications
+/// that identify clients in other ways, e.g., using sessions, should set a scope using
+/// [`scope_by_fn`](Self::scope_by_fn).
+///
+/// Computing the request fingerprint requires buffering the request body, subject to the limit set
+/// by [`PayloadConfig`](crate::web::PayloadConfig). Compressed bodies are decoded before being
+/// fingerprinted and passed on to the wrapped service without their `Content-Encoding` header.
+/// Response bodies are buffered in order to be stored.
+///
+/// Errors from the store while reserving a key are returned as errors, so that requests are never
+/// processed twice; errors while storing or releasing a key are logged.
+///
+/// # Examples
+/// ```no_run
+/// use actix_web::{
+///     middleware::{Idempotency, InMemoryIdempotencyStore},
+///     web, App, HttpResponse, HttpServer,
+/// };
+///
+/// # async fn run() -> std::io::Result<()> {
+/// // one store shared by all workers
+/// let store = InMemoryIdempotencyStore::new();
+///
+/// HttpServer::new(move || {
+///     App::new().service(
+///         web::resource("/payments")
+///             .wrap(Idempotency::new(store.clone()).required())
+///             .route(web::post().to(|body: String| async move {
+///                 HttpResponse::Created().body(format!("charged: {body}"))
+///             })),
+///     )
+/// })
+/// .bind(("127.0.0.1", 8080))?
+/// .run()
+/// .await
+/// # }
+/// ```
+pub struct Idempotency {
+    inner: Rc<Inner>,
+}
+
+impl Idempotency {
+    /// Constructs idempotency middleware that stores responses in `store`.
+    pub fn new(store: impl IdempotencyStore + 'static) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                store: Box::new(store),
+                header_name: HeaderName::from_static("idempotency-key"),
+                methods: vec![Method::POST, Method::PATCH],
+                required: false,
+                scope: Scope::Authorization,
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Idempotency must be configured before it is cloned")
+    }
+
+    /// Sets the name of the idempotency key request header. Defaults to `Idempotency-Key`.
+    pub fn header_name(mut self, name: HeaderName) -> Self {
+        self.inner_mut().header_name = name;
+        self
+    }
+
+    /// Sets the request methods that idempotency keys apply to, replacing the defaults of `POST`
+    /// and `PATCH`.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.inner_mut().methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Rejects requests with [applicable methods](Self::methods) that do not have an idempotency
+    /// key with `400 Bad Request`.
+    pub fn required(mut self) -> Self {
+        self.inner_mut().required = true;
+        self
+    }
+
+    /// Scopes idempotency keys using a closure over the request, in place of the `Authorization`
+    /// header.
+    ///
+    /// Requests for which the closure returns `None` share a single, unscoped set of keys.
+    pub fn scope_by_fn<F>(mut self, scope_fn: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<String> + 'static,
+    {
+        self.inner_mut().scope = Scope::Custom(Rc::new(scope_fn));
+        self
+    }
+}
+
+impl fmt::Debug for Idempotency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Idempotency")
+            .field("header_name", &self.inner.header_name)
+            .field("methods", &self.inner.methods)
+            .field("required", &self.inner.required)
+            .field("scope", &self.inner.scope)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    /// Returns the store key for the request's idempotency key, if it has one and idempotency
+    /// applies to it.
+    ///
+    /// Scoped keys are prefixed with the scope digest and unscoped keys with only the separator, so
+    /// that the two can never collide.
+    fn key(&self, req: &ServiceRequest) -> Result<Option<String>, IdempotencyError> {
+        if !self.methods.contains(req.method()) {
+            return Ok(None);
+        }
+
+        let key = match req.headers().get(&self.header_name) {
+            Some(key) => match key.to_str() {
+                Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => key,
+                _ => return Err(IdempotencyError::InvalidKey),
+            },
+            None if self.required => return Err(IdempotencyError::MissingKey),
+            None => return Ok(None),
+        };
+
+        let scope = self.scope.digest(req).unwrap_or_default();
+        Ok(Some(format!("{scope}:{key}")))
+    }
+}
+
+/// Derives a fingerprint from the request method, URI and decoded body.
+fn fingerprint(req: &ServiceRequest, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(b" ");
+    hasher.update(req.uri().to_string());
+    hasher.update(b"\n");
+    hasher.update(body);
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Reservation of an idempotency key by a request that is being processed.
+///
+/// Unless the response is stored, the key is released when the reservation is dropped, including
+/// if the request future is dropped before completing, e.g., because the client disconnected, a
+/// [`Timeout`](super::Timeout) elapsed or a panic was [caught](super::CatchPanic).
+struct Reservation {
+    inner: Rc<Inner>,
+    key: String,
+    completed: bool,
+}
+
+impl Reservation {
+    /// Stores the response to the request, logging any error.
+    async fn complete(mut self, res: IdempotentResponse) {
+        // if storing is interrupted, leave the key reserved until it expires rather than risk
+        // processing the request again
+        self.completed = true;
+
+        if let Err(err) = self.inner.store.complete(&self.key, res).await {
+            log::warn!("Failed to store idempotent response: {}", err);
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+
+        let mut release = self.inner.store.release(&self.key);
+
+        // stores that cannot release the key immediately are driven to completion in the background
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match release.as_mut().poll(&mut cx) {
+            Poll::Ready(res) => log_release_error(res),
+            Poll::Pending => {
+                actix_rt::spawn(async move { log_release_error(release.await) });
+            }
+        }
+    }
+}
+
+fn log_release_error(res: Result<(), Error>) {
+    if let Err(err) = res {
+        log::warn!("Failed to release idempotency key: {}", err);
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = IdempotencyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotencyMiddleware {
+            service: Rc::new(service),
+            inner: Rc::clone(&self.inner),
+        }))
+    }
+}
+
+/// Service created by [`Idempotency`] middleware.
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let inner = Rc::clone(&self.inner);
+
+        Box::pin(async move {
+            let key = match inner.key(&req)? {
+                Some(key) => key,
+                None => return Ok(service.call(req).await?.map_into_left_body()),
+            };
+
+            let body = buffer_payload(&mut req).await?;
+            let fingerprint = fingerprint(&req, &body);
+
+            if let Some(record) = inner.store.reserve(&key, &fingerprint).await? {
+                if record.fingerprint != fingerprint {
+                    return Err(IdempotencyError::KeyReused.into());
+                }
+
+                let stored = record.response.ok_or(IdempotencyError::InProgress)?;
+
+                let mut res = HttpResponse::with_body(stored.status, stored.body);
+                *res.headers_mut() = stored.headers;
+                res.headers_mut().insert(
+                    HeaderName::from_static("idempotent-replayed"),
+                    HeaderValue::from_static("true"),
+                );
+
+                return Ok(req
+                    .into_response(res.map_into_boxed_body())
+                    .map_into_right_body());
+            }
+
+            let reservation = Reservation {
+                inner: Rc::clone(&inner),
+                key,
+                completed: false,
+            };
+
+            // returning early drops the reservation, releasing the key
+            let res = match service.call(req).await {
+                Ok(res) if !res.status().is_server_error() => res,
+                res => return Ok(res?.map_into_left_body()),
+            };
+
+            let (req, res) = res.into_parts();
+            let (res, body) = res.into_parts();
+
+            let body = match body.try_into_bytes() {
+                Ok(body) => body,
+                Err(body) => body::to_bytes(body).await.map_err(Into::into)?,
+            };
+
+            reservation
+                .complete(IdempotentResponse {
+                    status: res.status(),
+                    headers: res.headers().clone(),
+                    body: body.clone(),
+                })
+                .await;
+
+            Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))).map_into_right_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use actix_service::IntoService as _;
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    async fn charge(calls: web::Data<AtomicU32>, body: String) -> HttpResponse {
+        let charge = calls.fetch_add(1, Ordering::SeqCst) + 1;
+
+        HttpResponse::Created()
+            .insert_header(("x-charge", charge))
+            .body(format!("charged {body}"))
+    }
+
+    #[actix_rt::test]
+    async fn replays_response() {
+        let calls = web::Data::new(AtomicU32::new(0));
+
+        let app = test::init_service(
+            App::new()
+                .app_data(calls.clone())
+                .wrap(Idempotency::new(InMemoryIdempotencyStore::new()))
+                .route("/pay", web::post().to(charge)),
+        )
+        .await;
+
+        let pay = |key: &str, amount: &'static str| {
+            TestRequest::post()
+                .uri("/pay")
+                .insert_header(("idempotency-key", key))
+                .set_payload(amount)
+                .to_request()
+        };
+
+        let res = test::call_service(&app, pay("a", "10")).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert!(!res.headers().contains_key("idempotent-replayed"));
+        assert_eq!(test::read_body(res).await, "charged 10");
+
+        let res = test::call_service(&app, pay("a", "10")).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers().get("x-charge").unwrap(), "1");
+        assert_eq!(res.headers().get("idempotent-replayed").unwrap(), "true");
+        assert_eq!(test::read_body(res).await, "charged 10");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // same key with a different body
+        let err = app.call(pay("a", "20")).await.unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let res = test::call_service(&app, pay("b", "20")).await;
+        assert_eq!(test::read_body(res).await, "charged 20");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // requests without a key are not deduplicated
+        let req = TestRequest::post()
+            .uri("/pay")
+            .set_payload("10")
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = TestRequest::post()
+            .uri("/pay")
+            .set_payload("10")
+            .to_request();
+        test::call_service(&app, req).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[actix_rt::test]
+    async fn concurrent_and_failed_requests() {
+        let store = InMemoryIdempotencyStore::new();
+
+        let srv = |req: ServiceRequest| async move {
+            let status = match req.path() {
+                "/error" => StatusCode::SERVICE_UNAVAILABLE,
+                "/hang" => std::future::pending().await,
+                _ => StatusCode::OK,
+            };
+
+            Ok(req.into_response(HttpResponse::new(status)))
+        };
+
+        let mw = Idempotency::new(store.clone())
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = |uri: &str| {
+            TestRequest::post()
+                .uri(uri)
+                .insert_header(("idempotency-key", "k"))
+                .to_srv_request()
+        };
+
+        // a request with the same key is already in progress
+        assert!(store
+            .reserve(":k", &fingerprint(&req("/"), b""))
+            .await
+            .unwrap()
+            .is_none());
+        let err = mw.call(req("/")).await.unwrap_err();
+        assert_eq!(err.as_response_error().status_code(), StatusCode::CONFLICT);
+
+        // server errors release the key
+        store.release(":k").await.unwrap();
+        let res = mw.call(req("/error")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(store.is_empty());
+
+        // cancelled requests release the key
+        let fut = mw.call(req("/hang"));
+        actix_rt::time::timeout(Duration::from_millis(10), fut)
+            .await
+            .unwrap_err();
+        assert!(store.is_empty());
+
+        let res = mw.call(req("/")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn keys_are_scoped() {
+        let calls = web::Data::new(AtomicU32::new(0));
+
+        let app = test::init_service(
+            App::new()
+                .app_data(calls.clone())
+                .wrap(Idempotency::new(InMemoryIdempotencyStore::new()))
+                .route("/pay", web::post().to(charge)),
+        )
+        .await;
+
+        let pay = |auth: &str| {
+            TestRequest::post()
+                .uri("/pay")
+                .insert_header(("idempotency-key", "k"))
+                .insert_header((header::AUTHORIZATION, auth))
+                .set_payload("10")
+                .to_request()
+        };
+
+        for auth in ["Bearer alice", "Bearer bob", "Bearer alice"] {
+            test::call_service(&app, pay(auth)).await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // unscoped keys are separate from scoped ones
+        let req = TestRequest::post()
+            .uri("/pay")
+            .insert_header(("idempotency-key", "k"))
+            .set_payload("10")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(!res.headers().contains_key("idempotent-replayed"));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(calls.clone())
+                .wrap(
+                    Idempotency::new(InMemoryIdempotencyStore::new()).scope_by_fn(|req| {
+                        Some(req.headers().get("x-tenant")?.to_str().ok()?.to_owned())
+                    }),
+                )
+                .route("/pay", web::post().to(charge)),
+        )
+        .await;
+
+        for auth in ["Bearer alice", "Bearer bob"] {
+            let req = TestRequest::post()
+                .uri("/pay")
+                .insert_header(("idempotency-key", "k"))
+                .insert_header((header::AUTHORIZATION, auth))
+                .insert_header(("x-tenant", "acme"))
+                .set_payload("10")
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    #[actix_rt::test]
+    async fn compressed_body() {
+        use std::io::Write as _;
+
+        let calls = web::Data::new(AtomicU32::new(0));
+
+        let app = test::init_service(
+            App::new()
+                .app_data(calls.clone())
+                .wrap(Idempotency::new(InMemoryIdempotencyStore::new()))
+                .route("/pay", web::post().to(charge)),
+        )
+        .await;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(b"10").unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let pay = |body: Vec<u8>, encoding: Option<&str>| {
+            let mut req = TestRequest::post()
+                .uri("/pay")
+                .insert_header(("idempotency-key", "k"))
+                .set_payload(body);
+
+            if let Some(encoding) = encoding {
+                req = req.insert_header((header::CONTENT_ENCODING, encoding));
+            }
+
+            req.to_request()
+        };
+
+        let res = test::call_service(&app, pay(gzipped.clone(), Some("gzip"))).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(test::read_body(res).await, "charged 10");
+
+        // the same request, compressed or not, is replayed
+        let res = test::call_service(&app, pay(gzipped, Some("gzip"))).await;
+        assert_eq!(res.headers().get("idempotent-replayed").unwrap(), "true");
+        let res = test::call_service(&app, pay(b"10".to_vec(), None)).await;
+        assert_eq!(res.headers().get("idempotent-replayed").unwrap(), "true");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn stable_fingerprint() {
+        let req = TestRequest::post().uri("/pay").to_srv_request();
+        assert_eq!(
+            fingerprint(&req, b"10"),
+            "bfe72a38deb763178da1a807d904fae0d68ff4ea6d02d5655cc7d25ad5df7da5"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn key_validation() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Idempotency::new(InMemoryIdempotencyStore::new()).required())
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::post().to_request();
+        let err = app.call(req).await.unwrap_err();
+        assert_eq!(
+            err.as_error::<IdempotencyError>(),
+            Some(&IdempotencyError::MissingKey)
+        );
+
+        let req = TestRequest::post()
+            .insert_header(("idempotency-key", "x".repeat(256)))
+            .to_request();
+        let err = app.call(req).await.unwrap_err();
+        assert_eq!(
+            err.as_error::<IdempotencyError>(),
+            Some(&IdempotencyError::InvalidKey)
+        );
+
+        // other methods are not affected
+        let req = TestRequest::get().to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::put()
+            .insert_header(("idempotency-key", "k"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn records_expire() {
+        let store = InMemoryIdempotencyStore::with_ttl(Duration::from_millis(100))
+            .in_progress_ttl(Duration::from_millis(20));
+
+        assert!(store.reserve("k", "a").await.unwrap().is_none());
+        assert!(store.reserve("k", "b").await.unwrap().is_some());
+
+        // reservations expire sooner than responses
+        actix_rt::time::sleep(Duration::from_millis(30)).await;
+        assert!(store.reserve("k", "b").await.unwrap().is_none());
+
+        let res = IdempotentResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+        };
+        store.complete("k", res).await.unwrap();
+
+        actix_rt::time::sleep(Duration::from_millis(30)).await;
+        let record = store.reserve("k", "c").await.unwrap().unwrap();
+        assert!(record.response.is_some());
+
+        actix_rt::time::sleep(Duration::from_millis(100)).await;
+        assert!(store.reserve("k", "c").await.unwrap().is_none());
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -0,0 +1,454 @@
+//! For middleware documentation, see [`IpFilter`].
+
+use std::{
+    fmt,
+    net::IpAddr,
+    rc::Rc,
+    sync::{Arc, RwLock, RwLockWriteGuard},
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use derive_more::derive::{Display, Error};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::EitherBody,
+    dev::{ServiceRequest, ServiceResponse},
+    guard::{Guard, GuardContext},
+    info::{parse_ip, IpRange},
+    Error, HttpResponse,
+};
+
+/// Error returned when an IP address range could not be parsed.
+#[derive(Debug, Display, Error)]
+#[display("Invalid IP address range: {range}")]
+pub struct InvalidIpRange {
+    #[error(not(source))]
+    range: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Rules {
+    allow: Vec<IpRange>,
+    deny: Vec<IpRange>,
+}
+
+impl Rules {
+    fn is_allowed(&self, ip: Option<IpAddr>) -> bool {
+        let ip = match ip {
+            Some(ip) => ip.to_canonical(),
+            // fail closed, since an unknown address could be in any range
+            None => return self.allow.is_empty() && self.deny.is_empty(),
+        };
+
+        if self.deny.iter().any(|range| range.contains(ip)) {
+            return false;
+        }
+
+        self.allow.is_empty() || self.allow.iter().any(|range| range.contains(ip))
+    }
+}
+
+fn parse_ranges<I, S>(ranges: I) -> Result<Vec<IpRange>, InvalidIpRange>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    ranges
+        .into_iter()
+        .map(|range| {
+            let range = range.as_ref();
+            range.parse().map_err(|_| InvalidIpRange {
+                range: range.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Handle for replacing the rules of an [`IpFilter`] at runtime.
+///
+/// Rules replaced through a handle take effect for every filter obtained from it using
+/// [`filter()`](Self::filter), across all workers.
+#[derive(Clone)]
+pub struct IpFilterHandle {
+    rules: Arc<RwLock<Rules>>,
+}
+
+impl IpFilterHandle {
+    /// Returns a filter that uses the rules of this handle.
+    pub fn filter(&self) -> IpFilter {
+        IpFilter {
+            rules: Arc::clone(&self.rules),
+            use_realip: false,
+        }
+    }
+
+    /// Replaces the allow list. An empty list allows all addresses that are not denied.
+    ///
+    /// If any range is invalid, the rules are left unchanged.
+    pub fn set_allow<I, S>(&self, ranges: I) -> Result<(), InvalidIpRange>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let ranges = parse_ranges(ranges)?;
+        self.rules
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .allow = ranges;
+        Ok(())
+    }
+
+    /// Replaces the deny list.
+    ///
+    /// If any range is invalid, the rules are left unchanged.
+    pub fn set_deny<I, S>(&self, ranges: I) -> Result<(), InvalidIpRange>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let ranges = parse_ranges(ranges)?;
+        self.rules
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .deny = ranges;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for IpFilterHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IpFilterHandle").finish_non_exhaustive()
+    }
+}
+
+/// Guard and middleware for restricting access by client IP address.
+///
+/// Addresses are checked against lists of allowed and denied IPv4 and IPv6 ranges given in CIDR
+/// notation, such as `10.0.0.0/8` or `fd00::/8`; single addresses are also accepted. An address is
+/// allowed if it is not in a denied range and either the allow list is empty or it is in an
+/// allowed range. If the client address is unknown, it is only allowed when no rules are set.
+///
+/// By default, the peer address of the connection is checked. To check the client address
+/// resolved from forwarding headers, use [`use_realip_remote_addr`](Self::use_realip_remote_addr)
+/// together with [`TrustedProxies`] so that the address cannot be spoofed.
+///
+/// When used as middleware, denied requests are rejected with a `403 Forbidden` response. When used
+/// as a [`Guard`], denied requests do not match the route.
+///
+/// Cloning an `IpFilter` copies its rules. To replace the rules at runtime, get an
+/// [`IpFilterHandle`] using [`handle()`](Self::handle) and obtain filters that share its rules
+/// using [`IpFilterHandle::filter()`].
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::IpFilter, web, App, HttpResponse};
+///
+/// let handle = IpFilter::new().allow("10.0.0.0/8").allow("::1").handle();
+///
+/// let app = App::new()
+///     .service(
+///         web::scope("/admin")
+///             .wrap(handle.filter())
+///             .default_service(web::to(HttpResponse::Ok)),
+///     )
+///     .route("/metrics", web::get().guard(handle.filter()).to(HttpResponse::Ok));
+///
+/// // later, e.g. when configuration changes
+/// handle.set_allow(["10.1.0.0/16"]).unwrap();
+/// ```
+///
+/// [`TrustedProxies`]: crate::dev::TrustedProxies
+pub struct IpFilter {
+    rules: Arc<RwLock<Rules>>,
+    use_realip: bool,
+}
+
+impl IpFilter {
+    /// Constructs a filter that allows all addresses.
+    pub fn new() -> Self {
+        Self {
+            rules: Arc::default(),
+            use_realip: false,
+        }
+    }
+
+    /// Adds a range to the allow list.
+    ///
+    /// # Panics
+    /// Panics if `range` is not a valid IP address or CIDR range.
+    pub fn allow(self, range: &str) -> Self {
+        let range = parse_ranges([range]).unwrap_or_else(|err| panic!("{err}"));
+        self.write_rules().allow.extend(range);
+        self
+    }
+
+    /// Adds a range to the deny list.
+    ///
+    /// # Panics
+    /// Panics if `range` is not a valid IP address or CIDR range.
+    pub fn deny(self, range: &str) -> Self {
+        let range = parse_ranges([range]).unwrap_or_else(|err| panic!("{err}"));
+        self.write_rules().deny.extend(range);
+        self
+    }
+
+    /// Checks the client address resolved by
+    /// [`ConnectionInfo::realip_remote_addr()`](crate::dev::ConnectionInfo::realip_remote_addr)
+    /// instead of the peer address.
+    pub fn use_realip_remote_addr(mut self) -> Self {
+        self.use_realip = true;
+        self
+    }
+
+    /// Returns a handle for replacing the filter's rules at runtime.
+    ///
+    /// The handle only affects this filter and filters obtained from the handle, not clones of
+    /// this filter.
+    pub fn handle(&self) -> IpFilterHandle {
+        IpFilterHandle {
+            rules: Arc::clone(&self.rules),
+        }
+    }
+
+    fn write_rules(&self) -> RwLockWriteGuard<'_, Rules> {
+        self.rules.write().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Returns true if the request's client address is allowed.
+    fn is_allowed(&self, req: &ServiceRequest) -> bool {
+        let ip = if self.use_realip {
+            req.connection_info()
+                .realip_remote_addr()
+                .and_then(parse_ip)
+        } else {
+            req.peer_addr().map(|addr| addr.ip())
+        };
+
+        self.rules
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .is_allowed(ip)
+    }
+}
+
+impl Clone for IpFilter {
+    fn clone(&self) -> Self {
+        Self {
+            rules: Arc::new(RwLock::new(
+                self.rules
+                    .read()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .clone(),
+            )),
+            use_realip: self.use_realip,
+        }
+    }
+}
+
+impl Default for IpFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for IpFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IpFilter")
+            .field("use_realip", &self.use_realip)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Guard for IpFilter {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        self.is_allowed(ctx.req)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for IpFilter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = IpFilterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IpFilterMiddleware {
+            service: Rc::new(service),
+            filter: IpFilter {
+                rules: Arc::clone(&self.rules),
+                use_realip: self.use_realip,
+            },
+        }))
+    }
+}
+
+/// Service created by [`IpFilter`] middleware.
+pub struct IpFilterMiddleware<S> {
+    service: Rc<S>,
+    filter: IpFilter,
+}
+
+impl<S, B> Service<ServiceRequest> for IpFilterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if !self.filter.is_allowed(&req) {
+            let res = req.into_response(HttpResponse::Forbidden().finish());
+            return Box::pin(async move { Ok(res.map_into_right_body()) });
+        }
+
+        let service = Rc::clone(&self.service);
+        Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dev::TrustedProxies,
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    fn peer(addr: &str) -> TestRequest {
+        TestRequest::default().peer_addr(addr.parse().unwrap())
+    }
+
+    #[actix_rt::test]
+    async fn middleware() {
+        let handle = IpFilter::new()
+            .allow("10.0.0.0/8")
+            .deny("10.0.0.13")
+            .handle();
+
+        let app = test::init_service(
+            App::new()
+                .wrap(handle.filter())
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res = test::call_service(&app, peer("10.1.2.3:1234").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = test::call_service(&app, peer("10.0.0.13:1234").to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = test::call_service(&app, peer("192.0.2.1:1234").to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = test::call_service(&app, peer("[::ffff:10.1.2.3]:1234").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // unknown address
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // rules can be replaced at runtime
+        assert!(handle.set_allow(["192.0.2.0/24", "nope"]).is_err());
+        handle.set_allow(["192.0.2.0/24"]).unwrap();
+
+        let res = test::call_service(&app, peer("192.0.2.1:1234").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = test::call_service(&app, peer("10.1.2.3:1234").to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        handle.set_allow(Vec::<String>::new()).unwrap();
+        handle.set_deny(["2001:db8::/32"]).unwrap();
+
+        let res = test::call_service(&app, peer("[2001:db8::1]:1234").to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // unknown addresses are denied while any rules are set
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        handle.set_deny(Vec::<String>::new()).unwrap();
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn clones_copy_rules() {
+        let base = IpFilter::new().deny("192.0.2.0/24");
+        let _ = base.clone().allow("10.0.0.0/8");
+
+        let rules = base.rules.read().unwrap();
+        assert!(rules.allow.is_empty());
+        assert_eq!(rules.deny.len(), 1);
+        drop(rules);
+
+        let handle = base.handle();
+        let copy = base.clone();
+        handle.set_deny(Vec::<String>::new()).unwrap();
+        assert!(base.rules.read().unwrap().deny.is_empty());
+        assert_eq!(copy.rules.read().unwrap().deny.len(), 1);
+        assert!(handle.filter().rules.read().unwrap().deny.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn guard_and_realip() {
+        let filter = IpFilter::new()
+            .allow("203.0.113.0/24")
+            .use_realip_remote_addr();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(TrustedProxies::new().trust("10.0.0.0/8"))
+                .route("/", web::get().guard(filter).to(HttpResponse::Ok))
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+
+        let req = peer("10.0.0.1:1234")
+            .insert_header(("x-forwarded-for", "203.0.113.7"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // forwarding headers from untrusted peers are ignored
+        let req = peer("192.0.2.1:1234")
+            .insert_header(("x-forwarded-for", "203.0.113.7"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // deny-only filters cannot be bypassed with unknown or obfuscated addresses
+        let app = test::init_service(
+            App::new()
+                .app_data(TrustedProxies::new().trust("10.0.0.0/8"))
+                .wrap(
+                    IpFilter::new()
+                        .deny("192.0.2.0/24")
+                        .use_realip_remote_addr(),
+                )
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for forwarded in ["for=unknown", "for=_hidden"] {
+            let req = peer("10.0.0.1:1234")
+                .insert_header(("forwarded", forwarded))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        }
+    }
+}
@@ -26,9 +26,10 @@ use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
 use crate::{
     body::{BodySize, MessageBody},
-    http::header::HeaderName,
+    http::header::{self, HeaderName},
     service::{ServiceRequest, ServiceResponse},
-    Error, Result,
+    types::RequestId,
+    Error, HttpMessage as _, Result,
 };
 
 /// Middleware for logging request and response summaries to the terminal.
@@ -71,12 +72,25 @@ use crate::{
 /// `%D` | Time taken to serve the request, in milliseconds
 /// `%U` | Request URL
 /// `%{r}a` | "Real IP" remote address **\***
+/// `%{request_id}` | Request ID assigned by the [`RequestId`](super::RequestId) middleware
 /// `%{FOO}i` | `request.headers["FOO"]`
 /// `%{FOO}o` | `response.headers["FOO"]`
 /// `%{FOO}e` | `env_var["FOO"]`
 /// `%{FOO}xi` | [Custom request replacement](Logger::custom_request_replace) labelled "FOO"
 /// `%{FOO}xo` | [Custom response replacement](Logger::custom_response_replace) labelled "FOO"
 ///
+/// # Structured Output
+/// The [`json`](Logger::json) Logger emits one JSON object per request instead of a line of text,
+/// with typed fields that do not need to be parsed back out of the log line:
+///
+/// ```plain
+/// {"method":"GET","path":"/test","status":200,"latency_us":1074,"bytes_sent":20,"peer_addr":"127.0.0.1","user_agent":"HTTPie/2.2.0","request_id":null}
+/// ```
+///
+/// Missing values are logged as `null`. Custom fields can be added using
+/// [`custom_request_replace`](Logger::custom_request_replace) and
+/// [`custom_response_replace`](Logger::custom_response_replace).
+///
 /// # Security
 /// **\*** "Real IP" remote address is calculated using
 /// [`ConnectionInfo::realip_remote_addr()`](crate::dev::ConnectionInfo::realip_remote_addr())
@@ -105,6 +119,31 @@ impl Logger {
         }))
     }
 
+    /// Create `Logger` middleware that logs a JSON object per request.
+    ///
+    /// The object contains the fields `method`, `path`, `status`, `latency_us` (time taken to serve
+    /// the request, in microseconds), `bytes_sent`, `peer_addr`, `user_agent` and `request_id`
+    /// (see [`RequestId`](super::RequestId)).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::{middleware::Logger, App};
+    ///
+    /// let app = App::new().wrap(
+    ///     Logger::json().custom_request_replace("tenant", |req| {
+    ///         req.match_info().get("tenant").unwrap_or("-").to_owned()
+    ///     }),
+    /// );
+    /// ```
+    pub fn json() -> Logger {
+        Logger(Rc::new(Inner {
+            format: Format::json(),
+            exclude: HashSet::new(),
+            exclude_regex: Vec::new(),
+            log_target: Cow::Borrowed(module_path!()),
+        }))
+    }
+
     /// Ignore and do not log access info for specified path.
     pub fn exclude<T: Into<String>>(mut self, path: T) -> Self {
         Rc::get_mut(&mut self.0)
@@ -143,6 +182,8 @@ impl Logger {
     /// log line. The label passed as the first argument should match a replacement substring in
     /// the logger format like `%{label}xi`.
     ///
+    /// For [JSON loggers](Logger::json), the label is used as the name of a new string field.
+    ///
     /// It is convention to print "-" to indicate no output instead of an empty string.
     ///
     /// # Examples
@@ -160,7 +201,7 @@ impl Logger {
     ) -> Self {
         let inner = Rc::get_mut(&mut self.0).unwrap();
 
-        let ft = inner.format.0.iter_mut().find(
+        let ft = inner.format.units_mut().find(
             |ft| matches!(ft, FormatText::CustomRequest(unit_label, _) if label == unit_label),
         );
 
@@ -169,6 +210,15 @@ impl Logger {
             request_fn.replace(CustomRequestFn {
                 inner_fn: Rc::new(f),
             });
+        } else if inner.format.is_structured() {
+            let request_fn = CustomRequestFn {
+                inner_fn: Rc::new(f),
+            };
+
+            inner.format.0.push(FormatText::field(
+                label,
+                FormatText::CustomRequest(label.to_owned(), Some(request_fn)),
+            ));
         } else {
             // non-printed request replacement function diagnostic
             debug!(
@@ -186,6 +236,8 @@ impl Logger {
     /// The label passed as the first argument should match a replacement substring in
     /// the logger format like `%{label}xo`.
     ///
+    /// For [JSON loggers](Logger::json), the label is used as the name of a new string field.
+    ///
     /// It is convention to print "-" to indicate no output instead of an empty string.
     ///
     /// The replacement function does not have access to the response body.
@@ -211,7 +263,7 @@ impl Logger {
     ) -> Self {
         let inner = Rc::get_mut(&mut self.0).unwrap();
 
-        let ft = inner.format.0.iter_mut().find(
+        let ft = inner.format.units_mut().find(
             |ft| matches!(ft, FormatText::CustomResponse(unit_label, _) if label == unit_label),
         );
 
@@ -219,6 +271,15 @@ impl Logger {
             *res_fn = Some(CustomResponseFn {
                 inner_fn: Rc::new(f),
             });
+        } else if inner.format.is_structured() {
+            let res_fn = CustomResponseFn {
+                inner_fn: Rc::new(f),
+            };
+
+            inner.format.0.push(FormatText::field(
+                label,
+                FormatText::CustomResponse(label.to_owned(), Some(res_fn)),
+            ));
         } else {
             debug!(
                 "Attempted to register custom response logging function for non-existent label: {}",
@@ -258,7 +319,7 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        for unit in &self.0.format.0 {
+        for unit in self.0.format.units() {
             if let FormatText::CustomRequest(label, None) = unit {
                 warn!(
                     "No custom request replacement function was registered for label: {}",
@@ -415,10 +476,7 @@ pin_project! {
         fn drop(this: Pin<&mut Self>) {
             if let Some(ref format) = this.format {
                 let render = |fmt: &mut fmt::Formatter<'_>| {
-                    for unit in &format.0 {
-                        unit.render(fmt, this.size, this.time)?;
-                    }
-                    Ok(())
+                    format.render(fmt, this.size, this.time)
                 };
 
                 log::info!(
@@ -472,7 +530,9 @@ impl Format {
     /// Returns `None` if the format string syntax is incorrect.
     pub fn new(s: &str) -> Format {
         log::trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([aioe]|x[io])|[%atPrUsbTD]?)").unwrap();
+        let fmt =
+            Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([aioe]|x[io])|\{request_id\}|[%atPrUsbTD]?)")
+                .unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -512,6 +572,7 @@ impl Format {
                     "U" => FormatText::UrlPath,
                     "T" => FormatText::Time,
                     "D" => FormatText::TimeMillis,
+                    "{request_id}" => FormatText::RequestId,
                     _ => FormatText::Str(m.as_str().to_owned()),
                 });
             }
@@ -522,6 +583,68 @@ impl Format {
 
         Format(results)
     }
+
+    /// Create a structured `Format` consisting of JSON fields.
+    fn json() -> Format {
+        Format(vec![
+            FormatText::field("method", FormatText::Method),
+            FormatText::field("path", FormatText::UrlPath),
+            FormatText::field("status", FormatText::ResponseStatus),
+            FormatText::field("latency_us", FormatText::TimeMicros),
+            FormatText::field("bytes_sent", FormatText::ResponseSize),
+            FormatText::field("peer_addr", FormatText::RemoteAddr),
+            FormatText::field("user_agent", FormatText::RequestHeader(header::USER_AGENT)),
+            FormatText::field("request_id", FormatText::RequestId),
+        ])
+    }
+
+    /// Returns true if this format renders a JSON object.
+    fn is_structured(&self) -> bool {
+        matches!(self.0.first(), Some(FormatText::Field { .. }))
+    }
+
+    /// Iterates over all units, including those wrapped in JSON fields.
+    fn units(&self) -> impl Iterator<Item = &FormatText> {
+        self.0.iter().map(|unit| match unit {
+            FormatText::Field { unit, .. } => unit,
+            unit => unit,
+        })
+    }
+
+    /// Iterates mutably over all units, including those wrapped in JSON fields.
+    fn units_mut(&mut self) -> impl Iterator<Item = &mut FormatText> {
+        self.0.iter_mut().map(|unit| match unit {
+            FormatText::Field { unit, .. } => unit,
+            unit => unit,
+        })
+    }
+
+    fn render(
+        &self,
+        fmt: &mut fmt::Formatter<'_>,
+        size: usize,
+        entry_time: OffsetDateTime,
+    ) -> Result<(), fmt::Error> {
+        if !self.is_structured() {
+            for unit in &self.0 {
+                unit.render(fmt, size, entry_time)?;
+            }
+
+            return Ok(());
+        }
+
+        fmt.write_str("{")?;
+
+        for (idx, unit) in self.0.iter().enumerate() {
+            if idx > 0 {
+                fmt.write_str(",")?;
+            }
+
+            unit.render(fmt, size, entry_time)?;
+        }
+
+        fmt.write_str("}")
+    }
 }
 
 /// A string of text to be logged.
@@ -531,6 +654,8 @@ impl Format {
 #[derive(Debug, Clone)]
 enum FormatText {
     Str(String),
+    /// A value that is not available, rendered as "-" or as JSON `null`.
+    Missing,
     Percent,
     RequestLine,
     RequestTime,
@@ -541,11 +666,19 @@ enum FormatText {
     RemoteAddr,
     RealIpRemoteAddr,
     UrlPath,
+    RequestId,
+    Method,
+    TimeMicros,
     RequestHeader(HeaderName),
     ResponseHeader(HeaderName),
     EnvironHeader(String),
     CustomRequest(String, Option<CustomRequestFn>),
     CustomResponse(String, Option<CustomResponseFn>),
+    Field {
+        name: String,
+        numeric: bool,
+        unit: Box<FormatText>,
+    },
 }
 
 #[derive(Clone)]
@@ -583,6 +716,20 @@ impl fmt::Debug for CustomResponseFn {
 }
 
 impl FormatText {
+    /// Wraps `unit` in a named JSON field.
+    fn field(name: &str, unit: FormatText) -> FormatText {
+        let numeric = matches!(
+            unit,
+            FormatText::ResponseStatus | FormatText::ResponseSize | FormatText::TimeMicros
+        );
+
+        FormatText::Field {
+            name: name.to_owned(),
+            numeric,
+            unit: Box::new(unit),
+        }
+    }
+
     fn render(
         &self,
         fmt: &mut fmt::Formatter<'_>,
@@ -591,6 +738,7 @@ impl FormatText {
     ) -> Result<(), fmt::Error> {
         match self {
             FormatText::Str(ref string) => fmt.write_str(string),
+            FormatText::Missing => "-".fmt(fmt),
             FormatText::Percent => "%".fmt(fmt),
             FormatText::ResponseSize => size.fmt(fmt),
             FormatText::Time => {
@@ -603,6 +751,31 @@ impl FormatText {
                 let rt = (rt.whole_nanoseconds() as f64) / 1_000_000.0;
                 fmt.write_fmt(format_args!("{:.6}", rt))
             }
+            FormatText::TimeMicros => {
+                let rt = OffsetDateTime::now_utc() - entry_time;
+                rt.whole_microseconds().fmt(fmt)
+            }
+            FormatText::Field {
+                name,
+                numeric,
+                unit,
+            } => {
+                write_json_str(fmt, name)?;
+                fmt.write_str(":")?;
+
+                if matches!(**unit, FormatText::Missing) {
+                    return fmt.write_str("null");
+                }
+
+                let render = |fmt: &mut fmt::Formatter<'_>| unit.render(fmt, size, entry_time);
+                let val = FormatDisplay(&render).to_string();
+
+                if *numeric {
+                    fmt.write_str(&val)
+                } else {
+                    write_json_str(fmt, &val)
+                }
+            }
             FormatText::EnvironHeader(ref name) => {
                 if let Ok(val) = env::var(name) {
                     fmt.write_fmt(format_args!("{}", val))
@@ -621,18 +794,27 @@ impl FormatText {
             }
 
             FormatText::ResponseHeader(ref name) => {
-                let s = if let Some(val) = res.headers().get(name) {
-                    val.to_str().unwrap_or("-")
-                } else {
-                    "-"
+                *self = match res.headers().get(name).and_then(|val| val.to_str().ok()) {
+                    Some(val) => FormatText::Str(val.to_owned()),
+                    None => FormatText::Missing,
                 };
-                *self = FormatText::Str(s.to_string())
             }
 
+            // rendered with the response so that the ID is available regardless of whether the
+            // RequestId middleware is registered inside or outside of the logger
+            FormatText::RequestId => {
+                *self = match res.request().extensions().get::<RequestId>() {
+                    Some(req_id) => FormatText::Str(req_id.to_string()),
+                    None => FormatText::Missing,
+                };
+            }
+
+            FormatText::Field { unit, .. } => unit.render_response(res),
+
             FormatText::CustomResponse(_, res_fn) => {
                 let text = match res_fn {
                     Some(res_fn) => FormatText::Str(res_fn.call(res)),
-                    None => FormatText::Str("-".to_owned()),
+                    None => FormatText::Missing,
                 };
 
                 *self = text;
@@ -663,20 +845,20 @@ impl FormatText {
                 };
             }
             FormatText::UrlPath => *self = FormatText::Str(req.path().to_string()),
+            FormatText::Method => *self = FormatText::Str(req.method().to_string()),
+            FormatText::Field { unit, .. } => unit.render_request(now, req),
             FormatText::RequestTime => *self = FormatText::Str(now.format(&Rfc3339).unwrap()),
             FormatText::RequestHeader(ref name) => {
-                let s = if let Some(val) = req.headers().get(name) {
-                    val.to_str().unwrap_or("-")
-                } else {
-                    "-"
+                *self = match req.headers().get(name).and_then(|val| val.to_str().ok()) {
+                    Some(val) => FormatText::Str(val.to_owned()),
+                    None => FormatText::Missing,
                 };
-                *self = FormatText::Str(s.to_string());
             }
             FormatText::RemoteAddr => {
                 let s = if let Some(peer) = req.connection_info().peer_addr() {
                     FormatText::Str((*peer).to_string())
                 } else {
-                    FormatText::Str("-".to_string())
+                    FormatText::Missing
                 };
                 *self = s;
             }
@@ -684,14 +866,14 @@ impl FormatText {
                 let s = if let Some(remote) = req.connection_info().realip_remote_addr() {
                     FormatText::Str(remote.to_string())
                 } else {
-                    FormatText::Str("-".to_string())
+                    FormatText::Missing
                 };
                 *self = s;
             }
             FormatText::CustomRequest(_, request_fn) => {
                 let s = match request_fn {
                     Some(f) => FormatText::Str(f.call(req)),
-                    None => FormatText::Str("-".to_owned()),
+                    None => FormatText::Missing,
                 };
 
                 *self = s;
@@ -701,6 +883,13 @@ impl FormatText {
     }
 }
 
+/// Writes `val` as a JSON string literal.
+fn write_json_str(fmt: &mut fmt::Formatter<'_>, val: &str) -> Result<(), fmt::Error> {
+    // serializing a str cannot fail
+    let json = serde_json::to_string(val).map_err(|_| fmt::Error)?;
+    fmt.write_str(&json)
+}
+
 /// Converter to get a String from something that writes to a Formatter.
 pub(crate) struct FormatDisplay<'a>(&'a dyn Fn(&mut fmt::Formatter<'_>) -> Result<(), fmt::Error>);
 
@@ -717,7 +906,7 @@ mod tests {
 
     use super::*;
     use crate::{
-        http::{header, StatusCode},
+        http::{header, Method, StatusCode},
         test::{self, TestRequest},
         HttpResponse,
     };
@@ -1000,4 +1189,104 @@ mod tests {
         let req = TestRequest::default().to_srv_request();
         srv.call(req).await.unwrap();
     }
+
+    #[actix_rt::test]
+    async fn test_request_id_format() {
+        let mut format = Format::new("%{request_id} %s");
+
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(RequestId::new("abc-123"));
+        let res = ServiceResponse::new(req, HttpResponse::Ok().finish());
+
+        let now = OffsetDateTime::now_utc();
+        for unit in &mut format.0 {
+            unit.render_response(&res);
+        }
+
+        let render = |fmt: &mut fmt::Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        assert_eq!(FormatDisplay(&render).to_string(), "abc-123 200");
+
+        let mut format = Format::new("%{request_id}");
+        let req = TestRequest::default().to_http_request();
+        let res = ServiceResponse::new(req, HttpResponse::Ok().finish());
+        format.0[0].render_response(&res);
+
+        let render = |fmt: &mut fmt::Formatter<'_>| format.0[0].render(fmt, 1024, now);
+        assert_eq!(FormatDisplay(&render).to_string(), "-");
+    }
+
+    #[actix_rt::test]
+    async fn test_json_format() {
+        let mut logger = Logger::json()
+            .custom_request_replace("tenant", |_req| "acme".to_owned())
+            .custom_response_replace("cache", |_res| "-".to_owned());
+        let mut format = Rc::get_mut(&mut logger.0).unwrap().format.clone();
+
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .uri("/test?q=1")
+            .peer_addr("127.0.0.1:8081".parse().unwrap())
+            .insert_header((header::USER_AGENT, r#"quoted "agent""#))
+            .to_srv_request();
+
+        let now = OffsetDateTime::now_utc();
+        for unit in &mut format.0 {
+            unit.render_request(now, &req);
+        }
+
+        let req = req.request().clone();
+        let res = ServiceResponse::new(req, HttpResponse::NotFound().finish());
+        for unit in &mut format.0 {
+            unit.render_response(&res);
+        }
+
+        let render = |fmt: &mut fmt::Formatter<'_>| format.render(fmt, 1024, now);
+        let log_output = FormatDisplay(&render).to_string();
+
+        let json = serde_json::from_str::<serde_json::Value>(&log_output).unwrap();
+        assert_eq!(json["method"], "POST");
+        assert_eq!(json["path"], "/test");
+        assert_eq!(json["status"], 404);
+        assert!(json["latency_us"].is_u64());
+        assert_eq!(json["bytes_sent"], 1024);
+        assert_eq!(json["peer_addr"], "127.0.0.1");
+        assert_eq!(json["user_agent"], r#"quoted "agent""#);
+        assert!(json["request_id"].is_null());
+        assert_eq!(json["tenant"], "acme");
+
+        // only missing values are null
+        assert_eq!(json["cache"], "-");
+    }
+
+    #[actix_rt::test]
+    async fn test_json_format_dash_values() {
+        let mut format = Format::json();
+
+        let req = TestRequest::default()
+            .insert_header((header::USER_AGENT, "-"))
+            .to_srv_request();
+
+        let now = OffsetDateTime::now_utc();
+        for unit in &mut format.0 {
+            unit.render_request(now, &req);
+        }
+
+        let req = req.request().clone();
+        let res = ServiceResponse::new(req, HttpResponse::Ok().finish());
+        for unit in &mut format.0 {
+            unit.render_response(&res);
+        }
+
+        let render = |fmt: &mut fmt::Formatter<'_>| format.render(fmt, 0, now);
+        let log_output = FormatDisplay(&render).to_string();
+
+        let json = serde_json::from_str::<serde_json::Value>(&log_output).unwrap();
+        assert_eq!(json["user_agent"], "-");
+        assert!(json["peer_addr"].is_null());
+    }
 }
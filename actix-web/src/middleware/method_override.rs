@@ -0,0 +1,347 @@
+//! For middleware documentation, see [`MethodOverride`].
+
+use std::{borrow::Cow, fmt, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    dev::{ServiceRequest, ServiceResponse},
+    helpers::buffer_payload,
+    http::{header::HeaderName, Method},
+    Error, HttpMessage as _,
+};
+
+/// Middleware for overriding the method of `POST` requests.
+///
+/// HTML forms and some clients can only send `GET` and `POST` requests. This middleware lets them
+/// tunnel other methods through `POST` requests by setting, in order of precedence:
+/// - the `X-HTTP-Method-Override` request header;
+/// - a `_method` query string parameter; or
+/// - a `_method` field in a URL-encoded form body.
+///
+/// The method is only changed if the override names one of the [allowed
+/// methods](Self::allowed_methods), which by default are `PUT`, `PATCH` and `DELETE`; otherwise
+/// the request is passed on unchanged. Requests with methods other than `POST` are never changed.
+///
+/// The method is changed before routing, so this middleware needs to be registered on the `App` or
+/// on a scope that contains the routes that should match the new method.
+///
+/// Checking the form field requires buffering the request body, subject to the limit set by
+/// [`PayloadConfig`](crate::web::PayloadConfig). Compressed bodies are decoded and passed on to the
+/// wrapped service without their `Content-Encoding` header.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::MethodOverride, web, App, HttpResponse};
+///
+/// // <form method="post" action="/posts/1">
+/// //   <input type="hidden" name="_method" value="DELETE">
+/// // </form>
+/// let app = App::new()
+///     .wrap(MethodOverride::new())
+///     .route("/posts/{id}", web::delete().to(HttpResponse::NoContent));
+/// ```
+#[derive(Clone)]
+pub struct MethodOverride {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    allowed_methods: Vec<Method>,
+    header_name: HeaderName,
+    field_name: Cow<'static, str>,
+    header_override: bool,
+    query_override: bool,
+    form_override: bool,
+}
+
+impl MethodOverride {
+    /// Constructs method override middleware that checks the header, query string and form body.
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                allowed_methods: vec![Method::PUT, Method::PATCH, Method::DELETE],
+                header_name: HeaderName::from_static("x-http-method-override"),
+                field_name: Cow::Borrowed("_method"),
+                header_override: true,
+                query_override: true,
+                form_override: true,
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("MethodOverride must be configured before it is cloned")
+    }
+
+    /// Sets the methods that requests may be changed to, replacing the defaults.
+    pub fn allowed_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.inner_mut().allowed_methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Sets whether the method can be overridden using a request header.
+    pub fn header_override(mut self, enabled: bool) -> Self {
+        self.inner_mut().header_override = enabled;
+        self
+    }
+
+    /// Sets whether the method can be overridden using a query string parameter.
+    pub fn query_override(mut self, enabled: bool) -> Self {
+        self.inner_mut().query_override = enabled;
+        self
+    }
+
+    /// Sets whether the method can be overridden using a URL-encoded form field.
+    pub fn form_override(mut self, enabled: bool) -> Self {
+        self.inner_mut().form_override = enabled;
+        self
+    }
+
+    /// Sets the name of the override request header. Defaults to `X-HTTP-Method-Override`.
+    pub fn header_name(mut self, name: HeaderName) -> Self {
+        self.inner_mut().header_name = name;
+        self
+    }
+
+    /// Sets the name of the override query string parameter and form field. Defaults to `_method`.
+    pub fn field_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().field_name = name.into();
+        self
+    }
+}
+
+impl Default for MethodOverride {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MethodOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodOverride")
+            .field("allowed_methods", &self.inner.allowed_methods)
+            .field("header_name", &self.inner.header_name)
+            .field("field_name", &self.inner.field_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    /// Returns the overriding method, if any, buffering the body if needed.
+    async fn override_method(&self, req: &mut ServiceRequest) -> Result<Option<Method>, Error> {
+        if self.header_override {
+            if let Some(val) = req.headers().get(&self.header_name) {
+                return Ok(val.to_str().ok().and_then(|val| self.allowed_method(val)));
+            }
+        }
+
+        if self.query_override {
+            if let Some(val) = self.field(req.query_string().as_bytes()) {
+                return Ok(self.allowed_method(&val));
+            }
+        }
+
+        if !self.form_override
+            || req.content_type() != mime::APPLICATION_WWW_FORM_URLENCODED.essence_str()
+        {
+            return Ok(None);
+        }
+
+        let body = buffer_payload(req).await?;
+        Ok(self.field(&body).and_then(|val| self.allowed_method(&val)))
+    }
+
+    /// Returns the value of the override field in URL-encoded data.
+    fn field(&self, data: &[u8]) -> Option<String> {
+        serde_urlencoded::from_bytes::<Vec<(String, String)>>(data)
+            .ok()?
+            .into_iter()
+            .find(|(name, _)| *name == self.field_name)
+            .map(|(_, val)| val)
+    }
+
+    /// Parses a method name case-insensitively and returns it if it is allowed.
+    fn allowed_method(&self, val: &str) -> Option<Method> {
+        let method = Method::from_bytes(val.trim().to_ascii_uppercase().as_bytes()).ok()?;
+        self.allowed_methods.contains(&method).then_some(method)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for MethodOverride
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = MethodOverrideMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MethodOverrideMiddleware {
+            service: Rc::new(service),
+            inner: Rc::clone(&self.inner),
+        }))
+    }
+}
+
+/// Service created by [`MethodOverride`] middleware.
+pub struct MethodOverrideMiddleware<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for MethodOverrideMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let inner = Rc::clone(&self.inner);
+
+        Box::pin(async move {
+            if req.method() == Method::POST {
+                if let Some(method) = inner.override_method(&mut req).await? {
+                    req.head_mut().method = method;
+                }
+            }
+
+            service.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[actix_rt::test]
+    async fn overrides_post() {
+        let app = test::init_service(
+            App::new()
+                .wrap(MethodOverride::new())
+                .route("/", web::post().to(|| async { "post" }))
+                .route("/", web::put().to(|| async { "put" }))
+                .route(
+                    "/",
+                    web::delete().to(|body: String| async move { format!("delete {body}") }),
+                )
+                .route("/", web::patch().to(|| async { "patch" }))
+                .route("/", web::get().to(|| async { "get" })),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .insert_header(("x-http-method-override", "put"))
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "put");
+
+        let req = TestRequest::post().uri("/?_method=PATCH").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "patch");
+
+        // form body is still available to the handler
+        let req = TestRequest::post()
+            .set_form([("_method", "DELETE"), ("id", "1")])
+            .to_request();
+        assert_eq!(
+            test::call_and_read_body(&app, req).await,
+            "delete _method=DELETE&id=1"
+        );
+
+        // header takes precedence
+        let req = TestRequest::post()
+            .uri("/?_method=PATCH")
+            .insert_header(("x-http-method-override", "PUT"))
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "put");
+
+        // methods that are not allowed are ignored
+        let req = TestRequest::post().uri("/?_method=GET").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "post");
+
+        // only POST requests are changed
+        let req = TestRequest::get().uri("/?_method=DELETE").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "get");
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    #[actix_rt::test]
+    async fn compressed_form() {
+        use std::io::Write as _;
+
+        use crate::http::header;
+
+        let app = test::init_service(App::new().wrap(MethodOverride::new()).route(
+            "/",
+            web::delete().to(|body: String| async move { format!("delete {body}") }),
+        ))
+        .await;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(b"_method=delete&id=1").unwrap();
+
+        let req = TestRequest::post()
+            .insert_header(header::ContentType::form_url_encoded())
+            .insert_header((header::CONTENT_ENCODING, "gzip"))
+            .set_payload(encoder.finish().unwrap())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "delete _method=delete&id=1");
+    }
+
+    #[actix_rt::test]
+    async fn configured_sources() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    MethodOverride::new()
+                        .allowed_methods([Method::DELETE])
+                        .query_override(false)
+                        .form_override(false)
+                        .header_name(HeaderName::from_static("x-method")),
+                )
+                .route("/", web::post().to(HttpResponse::Ok))
+                .route("/", web::delete().to(HttpResponse::NoContent)),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .insert_header(("x-method", "DELETE"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let req = TestRequest::post()
+            .insert_header(("x-method", "PUT"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::post().uri("/?_method=DELETE").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .set_form([("_method", "DELETE")])
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,568 @@
+//! For middleware documentation, see [`Metrics`].
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Write as _},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Instant,
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header::ContentType, Method, StatusCode},
+    web, Error, HttpResponse, Route,
+};
+
+/// Default latency histogram buckets, in seconds.
+const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Route label used for requests that did not match any resource.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Labels identifying a request series.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct SeriesKey {
+    method: &'static str,
+    status: &'static str,
+    route: String,
+}
+
+/// Labels identifying an in-flight gauge.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct GaugeKey {
+    method: &'static str,
+    route: String,
+}
+
+/// Latency histogram of a request series, updated without locking.
+#[derive(Debug)]
+struct Histogram {
+    /// Non-cumulative count of observations per bucket.
+    buckets: Vec<AtomicU64>,
+    sum_nanos: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, bounds: &[f64], started: Instant) {
+        let elapsed = started.elapsed();
+        let secs = elapsed.as_secs_f64();
+
+        if let Some(idx) = bounds.iter().position(|&bound| secs <= bound) {
+            self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        }
+
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.sum_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Registry of all series.
+///
+/// Locks are only taken to look up series that a worker has not seen before and to render
+/// metrics; recording a request only updates atomic counters.
+#[derive(Debug)]
+struct Registry {
+    namespace: Option<String>,
+    buckets: Vec<f64>,
+    exclude: HashSet<String>,
+    requests: RwLock<BTreeMap<SeriesKey, Arc<Histogram>>>,
+    in_flight: RwLock<BTreeMap<GaugeKey, Arc<AtomicU64>>>,
+}
+
+/// Middleware for recording Prometheus metrics.
+///
+/// The following metrics are recorded:
+/// - `http_requests_total`: counter of finished requests;
+/// - `http_request_duration_seconds`: histogram of time taken to produce a response; and
+/// - `http_requests_in_flight`: gauge of requests currently being handled.
+///
+/// Requests are labelled by `method`, `route` and (except for the in-flight gauge) `status`. To
+/// keep the number of series bounded, `route` is the matched resource pattern (e.g.,
+/// `/users/{id}`) rather than the request path, or `unmatched` if no resource matched; `status` is
+/// the status class (e.g., `2xx`); and non-standard methods are recorded as `OTHER`.
+///
+/// Metrics are exposed in the Prometheus text format by the route returned from
+/// [`endpoint`](Self::endpoint). Cloning this type produces a new handle to the same metrics, so
+/// that all workers can record to them; see
+/// [sharing state between workers](crate::middleware#sharing-state-between-workers).
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::Metrics, web, App, HttpResponse};
+///
+/// let metrics = Metrics::new().namespace("myapp").exclude("/metrics");
+///
+/// let app = App::new()
+///     .wrap(metrics.clone())
+///     .route("/metrics", metrics.endpoint())
+///     .route("/users/{id}", web::get().to(HttpResponse::Ok));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    registry: Arc<Registry>,
+}
+
+impl Metrics {
+    /// Constructs a new, empty set of metrics.
+    pub fn new() -> Self {
+        Self {
+            registry: Arc::new(Registry {
+                namespace: None,
+                buckets: DEFAULT_BUCKETS.to_vec(),
+                exclude: HashSet::new(),
+                requests: RwLock::default(),
+                in_flight: RwLock::default(),
+            }),
+        }
+    }
+
+    fn registry_mut(&mut self) -> &mut Registry {
+        Arc::get_mut(&mut self.registry).expect("Metrics must be configured before it is cloned")
+    }
+
+    /// Prefixes metric names with `namespace` and an underscore.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.registry_mut().namespace = Some(namespace.into());
+        self
+    }
+
+    /// Sets the upper bounds, in seconds, of the latency histogram buckets.
+    ///
+    /// Bounds are sorted and de-duplicated; a `+Inf` bucket is always included.
+    pub fn buckets(mut self, buckets: impl Into<Vec<f64>>) -> Self {
+        let mut buckets = buckets.into();
+        buckets.retain(|bound| bound.is_finite());
+        buckets.sort_by(f64::total_cmp);
+        buckets.dedup();
+
+        self.registry_mut().buckets = buckets;
+        self
+    }
+
+    /// Excludes requests to `path` from metrics, such as the metrics endpoint itself.
+    pub fn exclude(mut self, path: impl Into<String>) -> Self {
+        self.registry_mut().exclude.insert(path.into());
+        self
+    }
+
+    /// Returns a route that renders metrics in the Prometheus text exposition format.
+    pub fn endpoint(&self) -> Route {
+        let metrics = self.clone();
+
+        web::get().to(move || {
+            let res = HttpResponse::Ok()
+                .insert_header(ContentType(
+                    "text/plain; version=0.0.4; charset=utf-8".parse().unwrap(),
+                ))
+                .body(metrics.render());
+
+            ready(res)
+        })
+    }
+
+    /// Renders metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        // writing to a String cannot fail
+        let _ = self.registry.render_into(&mut out);
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    fn name(&self, name: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{namespace}_{name}"),
+            None => name.to_owned(),
+        }
+    }
+
+    fn histogram(&self, key: &SeriesKey) -> Arc<Histogram> {
+        let requests = self.requests.read().unwrap_or_else(|err| err.into_inner());
+
+        if let Some(histogram) = requests.get(key) {
+            return Arc::clone(histogram);
+        }
+
+        drop(requests);
+
+        let mut requests = self.requests.write().unwrap_or_else(|err| err.into_inner());
+        let histogram = requests.entry(key.clone()).or_insert_with(|| {
+            Arc::new(Histogram {
+                buckets: self.buckets.iter().map(|_| AtomicU64::new(0)).collect(),
+                sum_nanos: AtomicU64::new(0),
+                count: AtomicU64::new(0),
+            })
+        });
+
+        Arc::clone(histogram)
+    }
+
+    fn gauge(&self, key: &GaugeKey) -> Arc<AtomicU64> {
+        let in_flight = self.in_flight.read().unwrap_or_else(|err| err.into_inner());
+
+        if let Some(gauge) = in_flight.get(key) {
+            return Arc::clone(gauge);
+        }
+
+        drop(in_flight);
+
+        let mut in_flight = self
+            .in_flight
+            .write()
+            .unwrap_or_else(|err| err.into_inner());
+        Arc::clone(in_flight.entry(key.clone()).or_default())
+    }
+
+    fn render_into(&self, out: &mut String) -> fmt::Result {
+        let requests = self.requests.read().unwrap_or_else(|err| err.into_inner());
+
+        let name = self.name("http_requests_total");
+        writeln!(out, "# HELP {name} Total number of HTTP requests.")?;
+        writeln!(out, "# TYPE {name} counter")?;
+        for (key, histogram) in requests.iter() {
+            let count = histogram.count.load(Ordering::Relaxed);
+            writeln!(out, "{name}{{{}}} {count}", key.labels())?;
+        }
+
+        let duration = self.name("http_request_duration_seconds");
+        writeln!(out, "# HELP {duration} HTTP request latency in seconds.")?;
+        writeln!(out, "# TYPE {duration} histogram")?;
+        for (key, histogram) in requests.iter() {
+            let labels = key.labels();
+            let mut cumulative = 0;
+
+            for (bound, count) in self.buckets.iter().zip(&histogram.buckets) {
+                cumulative += count.load(Ordering::Relaxed);
+                writeln!(
+                    out,
+                    "{duration}_bucket{{{labels},le=\"{bound}\"}} {cumulative}"
+                )?;
+            }
+
+            // an observation may be counted in its bucket before the total count is updated
+            let count = histogram.count.load(Ordering::Relaxed).max(cumulative);
+            let sum = histogram.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+
+            writeln!(out, "{duration}_bucket{{{labels},le=\"+Inf\"}} {count}")?;
+            writeln!(out, "{duration}_sum{{{labels}}} {sum}")?;
+            writeln!(out, "{duration}_count{{{labels}}} {count}")?;
+        }
+
+        drop(requests);
+
+        let in_flight = self.name("http_requests_in_flight");
+        writeln!(
+            out,
+            "# HELP {in_flight} Number of HTTP requests being handled."
+        )?;
+        writeln!(out, "# TYPE {in_flight} gauge")?;
+        for (key, count) in self
+            .in_flight
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+        {
+            writeln!(
+                out,
+                "{in_flight}{{method=\"{}\",route=\"{}\"}} {}",
+                key.method,
+                escape_label(&key.route),
+                count.load(Ordering::Relaxed)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SeriesKey {
+    fn labels(&self) -> String {
+        format!(
+            "method=\"{}\",status=\"{}\",route=\"{}\"",
+            self.method,
+            self.status,
+            escape_label(&self.route)
+        )
+    }
+}
+
+fn escape_label(val: &str) -> String {
+    val.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::CONNECT => "CONNECT",
+        Method::OPTIONS => "OPTIONS",
+        Method::TRACE => "TRACE",
+        Method::PATCH => "PATCH",
+        _ => "OTHER",
+    }
+}
+
+fn status_label(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Decrements the in-flight gauge when dropped, including if the request is cancelled.
+struct InFlightGuard(Arc<AtomicU64>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Per-worker cache of series handles, so that the registry's locks are not taken per request.
+#[derive(Default)]
+struct LocalSeries {
+    requests: RefCell<HashMap<SeriesKey, Arc<Histogram>>>,
+    in_flight: RefCell<HashMap<GaugeKey, Arc<AtomicU64>>>,
+}
+
+impl LocalSeries {
+    fn histogram(&self, registry: &Registry, key: SeriesKey) -> Arc<Histogram> {
+        let mut requests = self.requests.borrow_mut();
+
+        if let Some(histogram) = requests.get(&key) {
+            return Arc::clone(histogram);
+        }
+
+        let histogram = registry.histogram(&key);
+        requests.insert(key, Arc::clone(&histogram));
+        histogram
+    }
+
+    fn gauge(&self, registry: &Registry, key: GaugeKey) -> Arc<AtomicU64> {
+        let mut in_flight = self.in_flight.borrow_mut();
+
+        if let Some(gauge) = in_flight.get(&key) {
+            return Arc::clone(gauge);
+        }
+
+        let gauge = registry.gauge(&key);
+        in_flight.insert(key, Arc::clone(&gauge));
+        gauge
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Metrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = MetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsMiddleware {
+            service: Rc::new(service),
+            registry: Arc::clone(&self.registry),
+            local: Rc::default(),
+        }))
+    }
+}
+
+/// Service created by [`Metrics`] middleware.
+pub struct MetricsMiddleware<S> {
+    service: Rc<S>,
+    registry: Arc<Registry>,
+    local: Rc<LocalSeries>,
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if self.registry.exclude.contains(req.path()) {
+            return Box::pin(self.service.call(req));
+        }
+
+        let service = Rc::clone(&self.service);
+        let registry = Arc::clone(&self.registry);
+        let local = Rc::clone(&self.local);
+
+        let method = method_label(req.method());
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_owned());
+
+        Box::pin(async move {
+            let started = Instant::now();
+
+            let gauge = local.gauge(
+                &registry,
+                GaugeKey {
+                    method,
+                    route: route.clone(),
+                },
+            );
+            gauge.fetch_add(1, Ordering::Relaxed);
+            let guard = InFlightGuard(gauge);
+
+            let res = service.call(req).await;
+
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(err) => err.as_response_error().status_code(),
+            };
+
+            let key = SeriesKey {
+                method,
+                status: status_label(status),
+                route,
+            };
+            local
+                .histogram(&registry, key)
+                .observe(&registry.buckets, started);
+
+            drop(guard);
+
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        App,
+    };
+
+    #[actix_rt::test]
+    async fn records_and_renders() {
+        let metrics = Metrics::new()
+            .namespace("test")
+            .buckets([0.1, 1.0])
+            .exclude("/metrics");
+
+        let app = test::init_service(
+            App::new()
+                .wrap(metrics.clone())
+                .route("/metrics", metrics.endpoint())
+                .route("/users/{id}", web::get().to(HttpResponse::Ok))
+                .route("/fail", web::get().to(HttpResponse::InternalServerError)),
+        )
+        .await;
+
+        for path in ["/users/1", "/users/2", "/fail", "/not/found"] {
+            let req = TestRequest::get().uri(path).to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let req = TestRequest::get().uri("/metrics").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res
+            .headers()
+            .get("content-type")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain; version=0.0.4"));
+
+        let body = test::read_body(res).await;
+        let body = std::str::from_utf8(&body).unwrap();
+
+        assert!(body.contains("# TYPE test_http_requests_total counter\n"));
+        assert!(body.contains(
+            "test_http_requests_total{method=\"GET\",status=\"2xx\",route=\"/users/{id}\"} 2\n"
+        ));
+        assert!(body.contains(
+            "test_http_requests_total{method=\"GET\",status=\"5xx\",route=\"/fail\"} 1\n"
+        ));
+        assert!(body.contains(
+            "test_http_requests_total{method=\"GET\",status=\"4xx\",route=\"unmatched\"} 1\n"
+        ));
+        assert!(body.contains(
+            "test_http_request_duration_seconds_bucket{method=\"GET\",status=\"2xx\",route=\"/users/{id}\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(body.contains(
+            "test_http_request_duration_seconds_count{method=\"GET\",status=\"2xx\",route=\"/users/{id}\"} 2\n"
+        ));
+        assert!(
+            body.contains("test_http_requests_in_flight{method=\"GET\",route=\"/users/{id}\"} 0\n")
+        );
+
+        // excluded paths are not recorded
+        assert!(!body.contains("route=\"/metrics\""));
+    }
+
+    #[actix_rt::test]
+    async fn shared_between_apps() {
+        let metrics = Metrics::new();
+
+        // each app stands in for a worker with its own cache of series handles
+        for _ in 0..2 {
+            let app = test::init_service(
+                App::new()
+                    .wrap(metrics.clone())
+                    .route("/", web::get().to(HttpResponse::Ok)),
+            )
+            .await;
+
+            let req = TestRequest::get().uri("/").to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let body = metrics.render();
+        assert!(body.contains("http_requests_total{method=\"GET\",status=\"2xx\",route=\"/\"} 2\n"));
+        assert!(body.contains("http_requests_in_flight{method=\"GET\",route=\"/\"} 0\n"));
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(status_label(StatusCode::NOT_MODIFIED), "3xx");
+        assert_eq!(
+            method_label(&Method::from_bytes(b"PURGE").unwrap()),
+            "OTHER"
+        );
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -233,6 +233,36 @@
 //! # }
 //! ```
 //!
+//! # Sharing State Between Workers
+//!
+//! [`HttpServer`](crate::HttpServer) calls its app factory closure once for each worker thread, so
+//! middleware constructed inside the closure is not shared between workers. Middleware that needs
+//! state visible to all workers, such as rate limit counters or cached responses, keeps it in a
+//! store (or, for [`Metrics`], a registry) that is a cheaply cloneable handle onto shared state.
+//! The store should be constructed once, outside the closure, with a clone moved into each
+//! worker's app; a store constructed inside the closure would give each worker its own state.
+//!
+//! ```no_run
+//! use actix_web::{
+//!     middleware::{InMemoryRateLimitStore, Quota, RateLimiter},
+//!     App, HttpServer,
+//! };
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let store = InMemoryRateLimitStore::new();
+//!
+//! HttpServer::new(move || {
+//!     App::new().wrap(RateLimiter::new(store.clone(), Quota::per_minute(60)))
+//! })
+//! .bind(("127.0.0.1", 8080))?
+//! .run()
+//! .await
+//! # }
+//! ```
+//!
+//! The in-memory stores in this module, and the in-memory session store, work this way. Custom
+//! store implementations should too, e.g., by wrapping a connection pool for an external database.
+//!
 //! [`Future`]: std::future::Future
 //! [`App`]: crate::App
 //! [`FromRequest`]: crate::FromRequest
@@ -242,28 +272,69 @@
 //! [`new_transform`]: crate::dev::Transform::new_transform()
 //! [`from_fn`]: crate
 
+mod authentication;
+mod body_limit;
+mod cache;
+mod catch_panic;
 mod compat;
 #[cfg(feature = "__compress")]
 mod compress;
+mod concurrency;
 mod condition;
+mod conditional_get;
+mod cors;
+#[cfg(feature = "secure-cookies")]
+mod csrf;
 mod default_headers;
 mod err_handlers;
 mod from_fn;
+mod https_redirect;
+mod idempotency;
 mod identity;
+mod ip_filter;
 mod logger;
+mod method_override;
+mod metrics;
 mod normalize;
+mod rate_limit;
+mod request_id;
+mod timeout;
+mod tracing;
 
 #[cfg(feature = "__compress")]
 pub use self::compress::Compress;
+#[cfg(feature = "secure-cookies")]
+pub use self::csrf::{Csrf, CsrfError, CsrfToken};
 pub use self::{
+    authentication::HttpAuthentication,
+    body_limit::BodyLimit,
+    cache::{CachedResponse, InMemoryResponseCacheStore, ResponseCache, ResponseCacheStore},
+    catch_panic::CatchPanic,
     compat::Compat,
+    concurrency::{ConcurrencyLimit, ConcurrencyLimitError, ConcurrencyOccupancy},
     condition::Condition,
+    conditional_get::ConditionalGet,
+    cors::{Cors, CorsError},
     default_headers::DefaultHeaders,
     err_handlers::{ErrorHandlerResponse, ErrorHandlers},
     from_fn::{from_fn, Next},
+    https_redirect::HttpsRedirect,
+    idempotency::{
+        Idempotency, IdempotencyError, IdempotencyRecord, IdempotencyStore, IdempotentResponse,
+        InMemoryIdempotencyStore,
+    },
     identity::Identity,
+    ip_filter::{InvalidIpRange, IpFilter, IpFilterHandle},
     logger::Logger,
-    normalize::{NormalizePath, TrailingSlash},
+    method_override::MethodOverride,
+    metrics::Metrics,
+    normalize::{NormalizePath, NormalizePathRedirect, TrailingSlash},
+    rate_limit::{InMemoryRateLimitStore, Quota, RateLimitStatus, RateLimitStore, RateLimiter},
+    request_id::RequestId,
+    timeout::{Timeout, TimeoutError},
+    tracing::{
+        InMemorySpanExporter, SpanData, SpanExporter, SpanId, TraceContext, TraceId, Tracing,
+    },
 };
 
 #[cfg(test)]
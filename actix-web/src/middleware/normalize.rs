@@ -1,17 +1,27 @@
 //! For middleware documentation, see [`NormalizePath`].
 
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
 use actix_http::uri::{PathAndQuery, Uri};
 use actix_service::{Service, Transform};
 use actix_utils::future::{ready, Ready};
 use bytes::Bytes;
+use futures_core::ready;
+use pin_project_lite::pin_project;
 #[cfg(feature = "unicode")]
 use regex::Regex;
 #[cfg(not(feature = "unicode"))]
 use regex_lite::Regex;
 
 use crate::{
+    body::EitherBody,
+    http::{header, Method, StatusCode},
     service::{ServiceRequest, ServiceResponse},
-    Error,
+    Error, HttpResponse,
 };
 
 /// Determines the behavior of the [`NormalizePath`] middleware.
@@ -40,11 +50,22 @@ pub enum TrailingSlash {
 /// Middleware for normalizing a request's path so that routes can be matched more flexibly.
 ///
 /// # Normalization Steps
+/// - Optionally, decodes percent-encoded unreserved characters and uppercases the hex digits of
+///   all other percent-encoded octets. (See [`decode_unreserved`](Self::decode_unreserved).)
+/// - Optionally, lowercases the path. (See [`lowercase`](Self::lowercase).)
+/// - Optionally, resolves `.` and `..` segments. (See
+///   [`resolve_dot_segments`](Self::resolve_dot_segments).)
 /// - Merges consecutive slashes into one. (For example, `/path//one` always becomes `/path/one`.)
 /// - Appends a trailing slash if one is not present, removes one if present, or keeps trailing
 ///   slashes as-is, depending on which [`TrailingSlash`] variant is supplied
 ///   to [`new`](NormalizePath::new()).
 ///
+/// # Rewriting vs. Redirecting
+/// By default, the normalized path is written back to the request before it is routed, so all
+/// variations of a URL are served with the same response. To instead send clients to the
+/// canonical URL, use the [`NormalizePathRedirect`] middleware returned by
+/// [`redirect`](Self::redirect).
+///
 /// # Default Behavior
 /// The default constructor chooses to strip trailing slashes from the end of paths with them
 /// ([`TrailingSlash::Trim`]). The implication is that route definitions should be defined without
@@ -84,7 +105,12 @@ pub enum TrailingSlash {
 /// # })
 /// ```
 #[derive(Debug, Clone, Copy)]
-pub struct NormalizePath(TrailingSlash);
+pub struct NormalizePath {
+    trailing_slash: TrailingSlash,
+    resolve_dot_segments: bool,
+    decode_unreserved: bool,
+    lowercase: bool,
+}
 
 impl Default for NormalizePath {
     fn default() -> Self {
@@ -93,14 +119,19 @@ impl Default for NormalizePath {
             in v4 from `Always` to `Trim`. Update your call to `NormalizePath::new(...)`."
         );
 
-        Self(TrailingSlash::Trim)
+        Self::new(TrailingSlash::Trim)
     }
 }
 
 impl NormalizePath {
     /// Create new `NormalizePath` middleware with the specified trailing slash style.
     pub fn new(trailing_slash_style: TrailingSlash) -> Self {
-        Self(trailing_slash_style)
+        Self {
+            trailing_slash: trailing_slash_style,
+            resolve_dot_segments: false,
+            decode_unreserved: false,
+            lowercase: false,
+        }
     }
 
     /// Constructs a new `NormalizePath` middleware with [trim](TrailingSlash::Trim) semantics.
@@ -109,6 +140,42 @@ impl NormalizePath {
     pub fn trim() -> Self {
         Self::new(TrailingSlash::Trim)
     }
+
+    /// Returns middleware that redirects requests for non-canonical paths instead of rewriting
+    /// them, using this normalization.
+    ///
+    /// See [`NormalizePathRedirect`] for details.
+    pub fn redirect(self) -> NormalizePathRedirect {
+        NormalizePathRedirect { config: self }
+    }
+
+    /// Resolves `.` and `..` path segments, as described in [RFC 3986 §5.2.4].
+    ///
+    /// [RFC 3986 §5.2.4]: https://www.rfc-editor.org/rfc/rfc3986#section-5.2.4
+    pub fn resolve_dot_segments(mut self) -> Self {
+        self.resolve_dot_segments = true;
+        self
+    }
+
+    /// Normalizes percent-encoding, as described in [RFC 3986 §6.2.2].
+    ///
+    /// Percent-encoded unreserved characters (letters, digits, `-`, `.`, `_` and `~`) are decoded
+    /// and the hex digits of all other percent-encoded octets are uppercased. For example,
+    /// `/%7euser/a%2fb` becomes `/~user/a%2Fb`.
+    ///
+    /// [RFC 3986 §6.2.2]: https://www.rfc-editor.org/rfc/rfc3986#section-6.2.2
+    pub fn decode_unreserved(mut self) -> Self {
+        self.decode_unreserved = true;
+        self
+    }
+
+    /// Lowercases ASCII letters in the path. Percent-encoded octets are not affected.
+    ///
+    /// Only use this if all routes are defined in lowercase.
+    pub fn lowercase(mut self) -> Self {
+        self.lowercase = true;
+        self
+    }
 }
 
 impl<S, B> Transform<S, ServiceRequest> for NormalizePath
@@ -125,16 +192,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(NormalizePathNormalization {
             service,
-            merge_slash: Regex::new("//+").unwrap(),
-            trailing_slash_behavior: self.0,
+            normalizer: Normalizer::new(*self),
         }))
     }
 }
 
 pub struct NormalizePathNormalization<S> {
     service: S,
-    merge_slash: Regex,
-    trailing_slash_behavior: TrailingSlash,
+    normalizer: Normalizer,
 }
 
 impl<S, B> Service<ServiceRequest> for NormalizePathNormalization<S>
@@ -149,56 +214,314 @@ where
     actix_service::forward_ready!(service);
 
     fn call(&self, mut req: ServiceRequest) -> Self::Future {
-        let head = req.head_mut();
+        if let Some(path) = self.normalizer.normalized_path(&req) {
+            let path = with_query(path, &req);
+
+            let mut parts = req.head().uri.clone().into_parts();
+            parts.path_and_query =
+                Some(PathAndQuery::from_maybe_shared(Bytes::from(path)).unwrap());
+
+            let uri = Uri::from_parts(parts).unwrap();
+            req.match_info_mut().get_mut().update(&uri);
+            req.head_mut().uri = uri;
+        }
+
+        self.service.call(req)
+    }
+}
+
+/// Middleware for redirecting requests to the normalized form of their path.
+///
+/// Constructed using [`NormalizePath::redirect()`], which determines how paths are normalized.
+/// Requests for non-canonical paths are not passed to the wrapped service; instead, `GET` and
+/// `HEAD` requests are redirected with `301 Moved Permanently` and requests with other methods are
+/// redirected with `308 Permanent Redirect`, so that clients repeat the method and body. The query
+/// string is preserved.
+///
+/// Requests whose normalized path contains a backslash are passed through without redirecting,
+/// since browsers may follow a `Location` such as `/\example.com` to another host.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::NormalizePath, web, App};
+///
+/// let app = App::new()
+///     .wrap(
+///         NormalizePath::trim()
+///             .resolve_dot_segments()
+///             .decode_unreserved()
+///             .lowercase()
+///             .redirect(),
+///     )
+///     .route("/docs/getting-started", web::get().to(|| async { "docs" }));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizePathRedirect {
+    config: NormalizePath,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for NormalizePathRedirect
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = NormalizePathRedirectMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(NormalizePathRedirectMiddleware {
+            service,
+            normalizer: Normalizer::new(self.config),
+        }))
+    }
+}
+
+/// Service created by [`NormalizePathRedirect`] middleware.
+pub struct NormalizePathRedirectMiddleware<S> {
+    service: S,
+    normalizer: Normalizer,
+}
+
+impl<S, B> Service<ServiceRequest> for NormalizePathRedirectMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = NormalizePathFuture<S::Future>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let path = self
+            .normalizer
+            .normalized_path(&req)
+            .filter(|path| !path.contains('\\') && !path.starts_with("//"));
+
+        if let Some(path) = path {
+            let status = match *req.method() {
+                Method::GET | Method::HEAD => StatusCode::MOVED_PERMANENTLY,
+                _ => StatusCode::PERMANENT_REDIRECT,
+            };
+
+            let res = HttpResponse::build(status)
+                .insert_header((header::LOCATION, with_query(path, &req)))
+                .finish();
+
+            return NormalizePathFuture::Redirect {
+                res: Some(req.into_response(res)),
+            };
+        }
+
+        NormalizePathFuture::Service {
+            fut: self.service.call(req),
+        }
+    }
+}
+
+pin_project! {
+    #[doc(hidden)]
+    #[project = NormalizePathProj]
+    pub enum NormalizePathFuture<F> {
+        Service { #[pin] fut: F, },
+        Redirect { res: Option<ServiceResponse>, },
+    }
+}
+
+impl<F, B> Future for NormalizePathFuture<F>
+where
+    F: Future<Output = Result<ServiceResponse<B>, Error>>,
+{
+    type Output = Result<ServiceResponse<EitherBody<B>>, Error>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = match self.project() {
+            NormalizePathProj::Service { fut } => ready!(fut.poll(cx))?.map_into_left_body(),
+            NormalizePathProj::Redirect { res } => res
+                .take()
+                .expect("NormalizePathFuture polled after completion")
+                .map_into_right_body(),
+        };
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+struct Normalizer {
+    merge_slash: Regex,
+    config: NormalizePath,
+}
+
+impl Normalizer {
+    fn new(config: NormalizePath) -> Self {
+        Self {
+            merge_slash: Regex::new("//+").unwrap(),
+            config,
+        }
+    }
 
-        let original_path = head.uri.path();
+    /// Returns the normalized path of the request, if it differs from the original path.
+    fn normalized_path(&self, req: &ServiceRequest) -> Option<String> {
+        let original_path = req.head().uri.path();
 
         // An empty path here means that the URI has no valid path. We skip normalization in this
         // case, because adding a path can make the URI invalid
-        if !original_path.is_empty() {
-            // Either adds a string to the end (duplicates will be removed anyways) or trims all
-            // slashes from the end
-            let path = match self.trailing_slash_behavior {
-                TrailingSlash::Always => format!("{}/", original_path),
-                TrailingSlash::MergeOnly => original_path.to_string(),
-                TrailingSlash::Trim => original_path.trim_end_matches('/').to_string(),
-            };
+        if original_path.is_empty() {
+            return None;
+        }
+
+        let path = self.normalize(original_path);
+
+        // Check whether the path has been changed
+        //
+        // This check was previously implemented as string length comparison
+        //
+        // That approach fails when a trailing slash is added,
+        // and a duplicate slash is removed,
+        // since the length of the strings remains the same
+        //
+        // For example, the path "/v1//s" will be normalized to "/v1/s/"
+        // Both of the paths have the same length,
+        // so the change can not be deduced from the length comparison
+        (path != original_path).then_some(path)
+    }
+
+    /// Returns the normalized form of a non-empty `path`.
+    fn normalize(&self, path: &str) -> String {
+        let mut path = if self.config.decode_unreserved {
+            normalize_percent_encoding(path)
+        } else {
+            path.to_owned()
+        };
+
+        if self.config.lowercase {
+            path = lowercase(&path);
+        }
 
-            // normalize multiple /'s to one /
-            let path = self.merge_slash.replace_all(&path, "/");
-
-            // Ensure root paths are still resolvable. If resulting path is blank after previous
-            // step it means the path was one or more slashes. Reduce to single slash.
-            let path = if path.is_empty() { "/" } else { path.as_ref() };
-
-            // Check whether the path has been changed
-            //
-            // This check was previously implemented as string length comparison
-            //
-            // That approach fails when a trailing slash is added,
-            // and a duplicate slash is removed,
-            // since the length of the strings remains the same
-            //
-            // For example, the path "/v1//s" will be normalized to "/v1/s/"
-            // Both of the paths have the same length,
-            // so the change can not be deduced from the length comparison
-            if path != original_path {
-                let mut parts = head.uri.clone().into_parts();
-                let query = parts.path_and_query.as_ref().and_then(|pq| pq.query());
-
-                let path = match query {
-                    Some(q) => Bytes::from(format!("{}?{}", path, q)),
-                    None => Bytes::copy_from_slice(path.as_bytes()),
-                };
-                parts.path_and_query = Some(PathAndQuery::from_maybe_shared(path).unwrap());
-
-                let uri = Uri::from_parts(parts).unwrap();
-                req.match_info_mut().get_mut().update(&uri);
-                req.head_mut().uri = uri;
+        if self.config.resolve_dot_segments && path.starts_with('/') {
+            path = remove_dot_segments(&path);
+        }
+
+        // Either adds a string to the end (duplicates will be removed anyways) or trims all
+        // slashes from the end
+        let path = match self.config.trailing_slash {
+            TrailingSlash::Always => format!("{}/", path),
+            TrailingSlash::MergeOnly => path,
+            TrailingSlash::Trim => path.trim_end_matches('/').to_string(),
+        };
+
+        // normalize multiple /'s to one /
+        let path = self.merge_slash.replace_all(&path, "/");
+
+        // Ensure root paths are still resolvable. If resulting path is blank after previous
+        // step it means the path was one or more slashes. Reduce to single slash.
+        if path.is_empty() {
+            "/".to_owned()
+        } else {
+            path.into_owned()
+        }
+    }
+}
+
+/// Appends the request's query string, if any, to `path`.
+fn with_query(path: String, req: &ServiceRequest) -> String {
+    match req.head().uri.query() {
+        Some(q) => format!("{}?{}", path, q),
+        None => path,
+    }
+}
+
+/// Decodes percent-encoded unreserved characters and uppercases the hex digits of all other
+/// percent-encoded octets.
+fn normalize_percent_encoding(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut normalized = String::with_capacity(path.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            if let (Some(&hi), Some(&lo)) = (bytes.get(idx + 1), bytes.get(idx + 2)) {
+                if let (Some(hi), Some(lo)) = (hex_value(hi), hex_value(lo)) {
+                    let octet = hi << 4 | lo;
+
+                    if octet.is_ascii_alphanumeric() || matches!(octet, b'-' | b'.' | b'_' | b'~') {
+                        normalized.push(octet as char);
+                    } else {
+                        normalized.push_str(&format!("%{octet:02X}"));
+                    }
+
+                    idx += 3;
+                    continue;
+                }
             }
         }
-        self.service.call(req)
+
+        // request paths are always ASCII
+        normalized.push(bytes[idx] as char);
+        idx += 1;
     }
+
+    normalized
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|val| val as u8)
+}
+
+/// Lowercases ASCII letters, except for the hex digits of percent-encoded octets.
+fn lowercase(path: &str) -> String {
+    let mut lowercased = String::with_capacity(path.len());
+    let mut escaped = 0;
+
+    for ch in path.chars() {
+        if escaped > 0 {
+            escaped -= 1;
+            lowercased.push(ch);
+        } else {
+            if ch == '%' {
+                escaped = 2;
+            }
+            lowercased.push(ch.to_ascii_lowercase());
+        }
+    }
+
+    lowercased
+}
+
+/// Resolves `.` and `..` segments of an absolute path.
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments = Vec::new();
+    let mut trailing_slash = false;
+
+    for segment in path[1..].split('/') {
+        trailing_slash = matches!(segment, "." | "..");
+
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut resolved = String::with_capacity(path.len());
+
+    for segment in segments {
+        resolved.push('/');
+        resolved.push_str(segment);
+    }
+
+    if trailing_slash || resolved.is_empty() {
+        resolved.push('/');
+    }
+
+    resolved
 }
 
 #[cfg(test)]
@@ -209,6 +532,7 @@ mod tests {
     use super::*;
     use crate::{
         guard::fn_guard,
+        http::header,
         test::{call_service, init_service, TestRequest},
         web, App, HttpResponse,
     };
@@ -253,7 +577,7 @@ mod tests {
     async fn trim_trailing_slashes() {
         let app = init_service(
             App::new()
-                .wrap(NormalizePath(TrailingSlash::Trim))
+                .wrap(NormalizePath::new(TrailingSlash::Trim))
                 .service(web::resource("/").to(HttpResponse::Ok))
                 .service(web::resource("/v1/something").to(HttpResponse::Ok))
                 .service(
@@ -289,11 +613,13 @@ mod tests {
     #[actix_rt::test]
     async fn trim_root_trailing_slashes_with_query() {
         let app = init_service(
-            App::new().wrap(NormalizePath(TrailingSlash::Trim)).service(
-                web::resource("/")
-                    .guard(fn_guard(|ctx| ctx.head().uri.query() == Some("query=test")))
-                    .to(HttpResponse::Ok),
-            ),
+            App::new()
+                .wrap(NormalizePath::new(TrailingSlash::Trim))
+                .service(
+                    web::resource("/")
+                        .guard(fn_guard(|ctx| ctx.head().uri.query() == Some("query=test")))
+                        .to(HttpResponse::Ok),
+                ),
         )
         .await;
 
@@ -310,7 +636,7 @@ mod tests {
     async fn ensure_trailing_slash() {
         let app = init_service(
             App::new()
-                .wrap(NormalizePath(TrailingSlash::Always))
+                .wrap(NormalizePath::new(TrailingSlash::Always))
                 .service(web::resource("/").to(HttpResponse::Ok))
                 .service(web::resource("/v1/something/").to(HttpResponse::Ok))
                 .service(
@@ -347,7 +673,7 @@ mod tests {
     async fn ensure_root_trailing_slash_with_query() {
         let app = init_service(
             App::new()
-                .wrap(NormalizePath(TrailingSlash::Always))
+                .wrap(NormalizePath::new(TrailingSlash::Always))
                 .service(
                     web::resource("/")
                         .guard(fn_guard(|ctx| ctx.head().uri.query() == Some("query=test")))
@@ -369,7 +695,7 @@ mod tests {
     async fn keep_trailing_slash_unchanged() {
         let app = init_service(
             App::new()
-                .wrap(NormalizePath(TrailingSlash::MergeOnly))
+                .wrap(NormalizePath::new(TrailingSlash::MergeOnly))
                 .service(web::resource("/").to(HttpResponse::Ok))
                 .service(web::resource("/v1/something").to(HttpResponse::Ok))
                 .service(web::resource("/v1/").to(HttpResponse::Ok))
@@ -483,4 +809,88 @@ mod tests {
         let res = normalize.call(req).await.unwrap();
         assert!(res.status().is_success());
     }
+
+    #[actix_rt::test]
+    async fn redirect() {
+        let app = init_service(
+            App::new()
+                .wrap(NormalizePath::trim().redirect())
+                .service(web::resource("/v1/something").to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/v1/something").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("//v1//something/?query=test").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "/v1/something?query=test"
+        );
+
+        let req = TestRequest::post().uri("/v1/something/").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "/v1/something"
+        );
+        // paths that browsers could follow to another host are not redirected
+        let req = TestRequest::with_uri("/\\evil.com/").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(!res.headers().contains_key(header::LOCATION));
+    }
+
+    #[actix_rt::test]
+    async fn canonicalization() {
+        let srv = |req: ServiceRequest| {
+            assert_eq!("/v1/~user/a%2Fb", req.path());
+            ready(Ok(req.into_response(HttpResponse::Ok().finish())))
+        };
+
+        let normalize = NormalizePath::trim()
+            .resolve_dot_segments()
+            .decode_unreserved()
+            .lowercase()
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let test_uris = vec![
+            "/v1/~user/a%2Fb",
+            "/V1/%7Euser/A%2fb",
+            "/v1/./~user/x/../a%2fb/",
+            "/v2/../v1//~USER/a%2Fb/.",
+            "/%2e%2E/v1/%7e%75ser/a%2fb",
+        ];
+
+        for uri in test_uris {
+            let req = TestRequest::with_uri(uri).to_srv_request();
+            let res = normalize.call(req).await.unwrap();
+            assert!(res.status().is_success(), "Failed uri: {}", uri);
+        }
+    }
+
+    #[test]
+    fn dot_segments() {
+        assert_eq!(remove_dot_segments("/"), "/");
+        assert_eq!(remove_dot_segments("/a/b/c/./../../g"), "/a/g");
+        assert_eq!(remove_dot_segments("/a/b/.."), "/a/");
+        assert_eq!(remove_dot_segments("/a/b/."), "/a/b/");
+        assert_eq!(remove_dot_segments("/../../a"), "/a");
+        assert_eq!(remove_dot_segments("/.."), "/");
+        assert_eq!(remove_dot_segments("/a/..b/.c"), "/a/..b/.c");
+    }
+
+    #[test]
+    fn percent_encoding() {
+        assert_eq!(normalize_percent_encoding("/%41%7a%2D%2e%5F%7E"), "/Az-._~");
+        assert_eq!(normalize_percent_encoding("/%2f%c3%a9"), "/%2F%C3%A9");
+        assert_eq!(normalize_percent_encoding("/%zz/%4"), "/%zz/%4");
+        assert_eq!(lowercase("/ABC%2Fd"), "/abc%2Fd");
+    }
 }
@@ -0,0 +1,573 @@
+//! For middleware documentation, see [`RateLimiter`].
+
+use std::{
+    fmt,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::EitherBody,
+    expiry::ExpiringMap,
+    http::header::{self, HeaderMap, HeaderName, HeaderValue},
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpResponse,
+};
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+const DEFAULT_SHARDS: usize = 16;
+
+/// A rate limit quota: a number of requests allowed per period.
+///
+/// Quotas are enforced as a token bucket holding up to `max_requests` tokens which refills evenly
+/// over `period`. A client that has been idle may therefore burst up to `max_requests` requests
+/// before being limited to the steady refill rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    max_requests: u32,
+    period: Duration,
+}
+
+impl Quota {
+    /// Constructs a quota allowing `max_requests` per `period`.
+    ///
+    /// # Panics
+    /// Panics if `max_requests` or `period` is zero.
+    pub fn new(max_requests: u32, period: Duration) -> Self {
+        assert!(max_requests > 0, "Quota must allow at least one request");
+        assert!(!period.is_zero(), "Quota period must be non-zero");
+
+        Self {
+            max_requests,
+            period,
+        }
+    }
+
+    /// Constructs a quota allowing `max_requests` per second.
+    pub fn per_second(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(1))
+    }
+
+    /// Constructs a quota allowing `max_requests` per minute.
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60))
+    }
+
+    /// Constructs a quota allowing `max_requests` per hour.
+    pub fn per_hour(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60 * 60))
+    }
+
+    /// Returns the maximum number of requests allowed per period.
+    pub fn max_requests(&self) -> u32 {
+        self.max_requests
+    }
+
+    /// Returns the period over which the quota fully replenishes.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Time taken to replenish a single request.
+    fn replenish_interval(&self) -> Duration {
+        self.period / self.max_requests
+    }
+}
+
+/// Outcome of a rate limit check for a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// Whether the request is within quota.
+    pub allowed: bool,
+
+    /// Maximum number of requests allowed per period.
+    pub limit: u32,
+
+    /// Number of requests remaining before the client is limited.
+    pub remaining: u32,
+
+    /// Time until the quota is fully replenished.
+    pub reset: Duration,
+
+    /// Time until the next request would be allowed. Only set when `allowed` is false.
+    pub retry_after: Option<Duration>,
+}
+
+/// Storage backend for [`RateLimiter`] counters.
+///
+/// Implementors decide how counters are stored and expired; the middleware only asks a store to
+/// account for one request against a key's quota. See
+/// [sharing state between workers](crate::middleware#sharing-state-between-workers).
+pub trait RateLimitStore {
+    /// Accounts for one request made by `key` and reports whether it is within `quota`.
+    ///
+    /// Requests that are not allowed must not consume quota.
+    fn check(
+        &self,
+        key: &str,
+        quota: Quota,
+    ) -> LocalBoxFuture<'static, Result<RateLimitStatus, Error>>;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// Theoretical arrival time of the next request; the bucket is full when this is in the past.
+    tat: Instant,
+}
+
+/// In-memory [`RateLimitStore`] shared across all workers of a server.
+///
+/// Counters are split across a number of independently locked shards to reduce contention. Keys
+/// are evicted once their quota has fully replenished. Cloning this type produces a new handle to
+/// the same counters.
+#[derive(Clone)]
+pub struct InMemoryRateLimitStore {
+    shards: Arc<[Mutex<ExpiringMap<Bucket>>]>,
+    hasher: ahash::RandomState,
+}
+
+impl InMemoryRateLimitStore {
+    /// Constructs a new in-memory store with a default number of shards.
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+
+    /// Constructs a new in-memory store with the given number of shards.
+    ///
+    /// # Panics
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "Rate limit store needs at least one shard");
+
+        Self {
+            shards: (0..shards).map(|_| Mutex::default()).collect(),
+            hasher: ahash::RandomState::new(),
+        }
+    }
+
+    fn check_at(&self, key: &str, quota: Quota, now: Instant) -> RateLimitStatus {
+        let idx = self.hasher.hash_one(key) as usize % self.shards.len();
+        let mut buckets = self.shards[idx]
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        buckets.purge(now);
+
+        // generic cell rate algorithm; equivalent to a token bucket that is refilled continuously
+        let interval = quota.replenish_interval();
+        let tolerance = quota.period - interval;
+
+        // buckets expire once full, i.e., when their theoretical arrival time has passed
+        let tat = buckets.get(key, now).map_or(now, |bucket| bucket.tat);
+
+        let allow_at = tat.checked_sub(tolerance).unwrap_or(now);
+
+        let (allowed, tat) = if allow_at <= now {
+            (true, tat + interval)
+        } else {
+            (false, tat)
+        };
+
+        if allowed {
+            buckets.insert(key.to_owned(), Bucket { tat }, tat);
+        }
+
+        let reset = tat.saturating_duration_since(now);
+
+        // number of whole intervals of headroom left before the next request would be rejected
+        let headroom = quota.period.saturating_sub(reset);
+        let remaining = (headroom.as_nanos() / interval.as_nanos().max(1)) as u32;
+
+        RateLimitStatus {
+            allowed,
+            limit: quota.max_requests,
+            remaining: remaining.min(quota.max_requests),
+            reset,
+            retry_after: (!allowed).then(|| allow_at.saturating_duration_since(now)),
+        }
+    }
+}
+
+impl Default for InMemoryRateLimitStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for InMemoryRateLimitStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryRateLimitStore")
+            .field("shards", &self.shards.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RateLimitStore for InMemoryRateLimitStore {
+    fn check(
+        &self,
+        key: &str,
+        quota: Quota,
+    ) -> LocalBoxFuture<'static, Result<RateLimitStatus, Error>> {
+        let status = self.check_at(key, quota, Instant::now());
+        Box::pin(ready(Ok(status)))
+    }
+}
+
+type KeyFn = dyn Fn(&ServiceRequest) -> Option<String>;
+
+/// Source of the key identifying a client for rate limiting purposes.
+#[derive(Clone)]
+enum KeyExtractor {
+    RealIp,
+    Header(HeaderName),
+    Custom(Rc<KeyFn>),
+}
+
+impl KeyExtractor {
+    fn extract(&self, req: &ServiceRequest) -> Option<String> {
+        match self {
+            Self::RealIp => req
+                .connection_info()
+                .realip_remote_addr()
+                .map(str::to_owned),
+
+            Self::Header(name) => req
+                .headers()
+                .get(name)
+                .and_then(|val| val.to_str().ok())
+                .map(str::to_owned),
+
+            Self::Custom(key_fn) => key_fn(req),
+        }
+    }
+}
+
+impl fmt::Debug for KeyExtractor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RealIp => f.write_str("RealIp"),
+            Self::Header(name) => f.debug_tuple("Header").field(name).finish(),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+struct Inner {
+    store: Box<dyn RateLimitStore>,
+    quota: Quota,
+    key: KeyExtractor,
+}
+
+/// Middleware for limiting the rate of requests made by each client.
+///
+/// Each request is attributed to a client key which, by default, is the
+/// [real IP address](crate::dev::ConnectionInfo::realip_remote_addr) of the client. Keys can
+/// instead be taken from a request header using [`key_by_header`](Self::key_by_header) or computed
+/// by a closure using [`key_by_fn`](Self::key_by_fn). Requests for which no key can be determined
+/// are not limited.
+///
+/// Requests within quota have `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset`
+/// headers added to their responses. Requests over quota are rejected with a
+/// `429 Too Many Requests` response which also carries a `Retry-After` header.
+///
+/// # Sharing Counters Between Workers
+/// Counters live in a [`RateLimitStore`], which must be
+/// [shared between workers](crate::middleware#sharing-state-between-workers) to enforce a single
+/// limit across the server. Otherwise each worker enforces its own limit and the effective limit is
+/// multiplied by the number of workers.
+///
+/// # Security
+/// By default, keys are derived from [`ConnectionInfo::realip_remote_addr()`] which can be spoofed
+/// by clients sending `Forwarded` or `X-Forwarded-For` headers unless requests are only accepted
+/// from trusted proxies or [`TrustedProxies`] are configured.
+///
+/// # Examples
+/// ```no_run
+/// use actix_web::{
+///     middleware::{InMemoryRateLimitStore, Quota, RateLimiter},
+///     web, App, HttpResponse, HttpServer,
+/// };
+///
+/// # async fn run() -> std::io::Result<()> {
+/// // one store shared by all workers
+/// let store = InMemoryRateLimitStore::new();
+///
+/// HttpServer::new(move || {
+///     App::new()
+///         .wrap(RateLimiter::new(store.clone(), Quota::per_minute(60)))
+///         .default_service(web::to(HttpResponse::Ok))
+/// })
+/// .bind(("127.0.0.1", 8080))?
+/// .run()
+/// .await
+/// # }
+/// ```
+///
+/// [`ConnectionInfo::realip_remote_addr()`]: crate::dev::ConnectionInfo::realip_remote_addr
+/// [`TrustedProxies`]: crate::dev::TrustedProxies
+pub struct RateLimiter {
+    inner: Rc<Inner>,
+}
+
+impl RateLimiter {
+    /// Constructs a rate limiter enforcing `quota` using counters kept in `store`.
+    pub fn new(store: impl RateLimitStore + 'static, quota: Quota) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                store: Box::new(store),
+                quota,
+                key: KeyExtractor::RealIp,
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("RateLimiter must be configured before it is cloned")
+    }
+
+    /// Identifies clients by the value of the given request header.
+    ///
+    /// Requests without the header, or with a non-visible-ASCII value, are not limited.
+    pub fn key_by_header(mut self, name: HeaderName) -> Self {
+        self.inner_mut().key = KeyExtractor::Header(name);
+        self
+    }
+
+    /// Identifies clients using a closure over the request.
+    ///
+    /// Requests for which the closure returns `None` are not limited.
+    pub fn key_by_fn<F>(mut self, key_fn: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<String> + 'static,
+    {
+        self.inner_mut().key = KeyExtractor::Custom(Rc::new(key_fn));
+        self
+    }
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("quota", &self.inner.quota)
+            .field("key", &self.inner.key)
+            .finish_non_exhaustive()
+    }
+}
+
+fn insert_status_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(status.limit));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(status.remaining));
+    headers.insert(RATELIMIT_RESET, HeaderValue::from(ceil_secs(status.reset)));
+}
+
+/// Rounds duration up to the nearest whole second.
+fn ceil_secs(dur: Duration) -> u64 {
+    dur.as_secs() + u64::from(dur.subsec_nanos() > 0)
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware {
+            service: Rc::new(service),
+            inner: Rc::clone(&self.inner),
+        }))
+    }
+}
+
+/// Service created by [`RateLimiter`] middleware.
+pub struct RateLimiterMiddleware<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let inner = Rc::clone(&self.inner);
+
+        Box::pin(async move {
+            let key = match inner.key.extract(&req) {
+                Some(key) => key,
+                None => return service.call(req).await.map(|res| res.map_into_left_body()),
+            };
+
+            let status = inner.store.check(&key, inner.quota).await?;
+
+            if !status.allowed {
+                let retry_after = ceil_secs(status.retry_after.unwrap_or_default()).max(1);
+
+                let mut res = HttpResponse::TooManyRequests()
+                    .insert_header((header::RETRY_AFTER, retry_after))
+                    .finish();
+                insert_status_headers(res.headers_mut(), &status);
+
+                return Ok(req.into_response(res).map_into_right_body());
+            }
+
+            let mut res = service.call(req).await?;
+            insert_status_headers(res.headers_mut(), &status);
+
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+    };
+
+    #[test]
+    fn token_bucket() {
+        let store = InMemoryRateLimitStore::with_shards(1);
+        let quota = Quota::new(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        let status = store.check_at("a", quota, now);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 1);
+        assert_eq!(status.reset, Duration::from_secs(5));
+
+        let status = store.check_at("a", quota, now);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.reset, Duration::from_secs(10));
+
+        let status = store.check_at("a", quota, now);
+        assert!(!status.allowed);
+        assert_eq!(status.retry_after, Some(Duration::from_secs(5)));
+
+        // other keys are unaffected
+        assert!(store.check_at("b", quota, now).allowed);
+
+        // one request replenished after interval
+        let later = now + Duration::from_secs(5);
+        let status = store.check_at("a", quota, later);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 0);
+        assert!(!store.check_at("a", quota, later).allowed);
+
+        // fully replenished, but no more than the maximum
+        let much_later = now + Duration::from_secs(60);
+        let status = store.check_at("a", quota, much_later);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 1);
+    }
+
+    #[test]
+    fn shared_between_clones() {
+        let store = InMemoryRateLimitStore::new();
+        let clone = store.clone();
+        let quota = Quota::per_minute(1);
+        let now = Instant::now();
+
+        assert!(store.check_at("a", quota, now).allowed);
+        assert!(!clone.check_at("a", quota, now).allowed);
+    }
+
+    #[actix_rt::test]
+    async fn over_limit_response() {
+        let mw = RateLimiter::new(InMemoryRateLimitStore::new(), Quota::per_minute(1))
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(RATELIMIT_LIMIT).unwrap(), "1");
+        assert_eq!(res.headers().get(RATELIMIT_REMAINING).unwrap(), "0");
+        assert_eq!(res.headers().get(RATELIMIT_RESET).unwrap(), "60");
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8081".parse().unwrap())
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "60");
+        assert_eq!(res.headers().get(RATELIMIT_REMAINING).unwrap(), "0");
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn custom_keys() {
+        let mw = RateLimiter::new(InMemoryRateLimitStore::new(), Quota::per_minute(1))
+            .key_by_header(HeaderName::from_static("x-api-key"))
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            let req = TestRequest::default().to_srv_request();
+            let res = mw.call(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            assert!(!res.headers().contains_key(RATELIMIT_LIMIT));
+        }
+
+        let req = TestRequest::default()
+            .insert_header(("x-api-key", "abc"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .insert_header(("x-api-key", "abc"))
+            .to_srv_request();
+        let res = mw.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let mw = RateLimiter::new(InMemoryRateLimitStore::new(), Quota::per_minute(1))
+            .key_by_fn(|req| Some(req.path().to_owned()))
+            .new_transform(test::ok_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/a").to_srv_request();
+        assert_eq!(mw.call(req).await.unwrap().status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/b").to_srv_request();
+        assert_eq!(mw.call(req).await.unwrap().status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/a").to_srv_request();
+        assert_eq!(
+            mw.call(req).await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
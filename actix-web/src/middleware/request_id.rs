@@ -0,0 +1,271 @@
+//! For middleware documentation, see [`RequestId`].
+
+use std::{fmt, rc::Rc};
+
+use actix_http::trace::TraceId;
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    types::RequestId as ReqId,
+    Error, HttpMessage as _,
+};
+
+/// Longest incoming request ID that will be accepted.
+const MAX_INCOMING_LEN: usize = 128;
+
+type GeneratorFn = dyn Fn() -> String;
+
+struct Inner {
+    header_name: HeaderName,
+    trust_incoming: bool,
+    generator: Rc<GeneratorFn>,
+}
+
+/// Middleware for assigning an ID to each request.
+///
+/// The ID is read from the incoming `X-Request-Id` header, if present, so that IDs assigned by
+/// upstream services or proxies are preserved. Otherwise, a new random ID is generated. Incoming
+/// IDs are only accepted if they consist of at most 128 visible ASCII characters.
+///
+/// The ID is:
+/// - available to handlers through the [`web::RequestId`] extractor;
+/// - echoed on the response in the same header; and
+/// - available to [`Logger`] formats using the `%{request_id}` token.
+///
+/// This middleware should be registered after (that is, wrap) any middleware that needs the ID
+/// while processing the request, but its position relative to `Logger` does not matter.
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     middleware::{Logger, RequestId},
+///     web, App,
+/// };
+///
+/// async fn index(req_id: web::RequestId) -> String {
+///     format!("request {req_id}")
+/// }
+///
+/// let app = App::new()
+///     .wrap(Logger::new(r#"%{request_id} "%r" %s"#))
+///     .wrap(RequestId::default())
+///     .route("/", web::get().to(index));
+/// ```
+///
+/// [`web::RequestId`]: crate::web::RequestId
+/// [`Logger`]: super::Logger
+pub struct RequestId {
+    inner: Rc<Inner>,
+}
+
+impl RequestId {
+    /// Constructs request ID middleware using the `X-Request-Id` header.
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                header_name: HeaderName::from_static("x-request-id"),
+                trust_incoming: true,
+                generator: Rc::new(generate_request_id),
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("RequestId must be configured before it is cloned")
+    }
+
+    /// Sets the header used to read incoming IDs and echo IDs on responses.
+    pub fn header_name(mut self, name: HeaderName) -> Self {
+        self.inner_mut().header_name = name;
+        self
+    }
+
+    /// Sets whether IDs from incoming requests are used. Defaults to `true`.
+    ///
+    /// Disable this if clients are untrusted and IDs must always be generated by this service.
+    pub fn trust_incoming(mut self, trust: bool) -> Self {
+        self.inner_mut().trust_incoming = trust;
+        self
+    }
+
+    /// Sets the function used to generate new request IDs.
+    ///
+    /// By default, IDs are 32 random hexadecimal characters.
+    pub fn generator(mut self, generator: impl Fn() -> String + 'static) -> Self {
+        self.inner_mut().generator = Rc::new(generator);
+        self
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestId")
+            .field("header_name", &self.inner.header_name)
+            .field("trust_incoming", &self.inner.trust_incoming)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    fn request_id(&self, req: &ServiceRequest) -> ReqId {
+        let incoming = self
+            .trust_incoming
+            .then(|| req.headers().get(&self.header_name))
+            .flatten()
+            .and_then(|val| val.to_str().ok())
+            .filter(|id| is_valid_request_id(id));
+
+        match incoming {
+            Some(id) => ReqId::new(id),
+            None => ReqId::new((self.generator)()),
+        }
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_INCOMING_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Generates a random 128-bit ID, formatted as hex, using the same generator as trace IDs.
+fn generate_request_id() -> String {
+    TraceId::random().to_string()
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware {
+            service: Rc::new(service),
+            inner: Rc::clone(&self.inner),
+        }))
+    }
+}
+
+/// Service created by [`RequestId`] middleware.
+pub struct RequestIdMiddleware<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let inner = Rc::clone(&self.inner);
+
+        Box::pin(async move {
+            let req_id = inner.request_id(&req);
+            req.extensions_mut().insert(req_id.clone());
+
+            let mut res = service.call(req).await?;
+
+            // generated IDs may contain anything; skip echoing invalid header values
+            if let Ok(val) = HeaderValue::from_str(req_id.as_str()) {
+                res.headers_mut().insert(inner.header_name.clone(), val);
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    async fn echo_id(req_id: ReqId) -> String {
+        req_id.to_string()
+    }
+
+    #[actix_rt::test]
+    async fn generates_and_echoes() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId::default())
+                .default_service(web::to(echo_id)),
+        )
+        .await;
+
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let header = res.headers().get("x-request-id").unwrap().clone();
+        assert_eq!(header.len(), 32);
+        assert_eq!(test::read_body(res).await, header.as_bytes());
+
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_ne!(res.headers().get("x-request-id").unwrap(), header);
+    }
+
+    #[actix_rt::test]
+    async fn incoming_ids() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId::new().header_name(HeaderName::from_static("x-correlation-id")))
+                .default_service(web::to(echo_id)),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header(("x-correlation-id", "abc-123"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get("x-correlation-id").unwrap(), "abc-123");
+        assert_eq!(test::read_body(res).await, "abc-123");
+
+        let req = TestRequest::default()
+            .insert_header(("x-correlation-id", "a".repeat(MAX_INCOMING_LEN + 1)))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get("x-correlation-id").unwrap().len(), 32);
+
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    RequestId::new()
+                        .trust_incoming(false)
+                        .generator(|| "generated".to_owned()),
+                )
+                .default_service(web::to(echo_id)),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header(("x-request-id", "abc-123"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "generated");
+    }
+}
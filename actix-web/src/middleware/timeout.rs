@@ -0,0 +1,301 @@
+//! For middleware documentation, see [`Timeout`].
+
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::time::{sleep_until, Instant, Sleep};
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use derive_more::derive::{Display, Error};
+use pin_project_lite::pin_project;
+
+use crate::{
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    Error, HttpMessage as _, ResponseError,
+};
+
+/// Error returned when a request handler does not complete within the configured time limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+#[display("Request handler timed out")]
+pub struct TimeoutError {
+    status: StatusCode,
+}
+
+impl ResponseError for TimeoutError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Deadline {
+    at: Instant,
+    status: StatusCode,
+}
+
+/// Deadline shared between nested timeout middleware for the same request.
+///
+/// The outermost middleware owns the timer; inner middleware only replace the deadline.
+#[derive(Clone)]
+struct SharedDeadline {
+    started: Instant,
+    deadline: Rc<Cell<Deadline>>,
+}
+
+/// Middleware for limiting how long request handlers may run.
+///
+/// If the wrapped service has not produced a response once the timeout elapses, its future is
+/// dropped (cancelling the handler) and a [`TimeoutError`] is returned. The error responds with
+/// `504 Gateway Timeout` by default; use [`status_code`](Self::status_code) to change this, for
+/// example to `503 Service Unavailable`.
+///
+/// Time is measured from when the outermost `Timeout` middleware receives the request. This
+/// middleware can be used on an `App`, `Scope` or `Resource`. When nested, the innermost timeout
+/// that applies to a request replaces any outer one, allowing scopes or resources to either shorten
+/// or extend an app-wide default.
+///
+/// Note that this only limits request handling up to the point a response is returned; it does not
+/// limit the time taken to stream the response body.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_web::{http::StatusCode, middleware::Timeout, web, App, HttpResponse};
+///
+/// let app = App::new()
+///     .wrap(Timeout::new(Duration::from_secs(5)))
+///     .service(
+///         web::scope("/reports")
+///             // report generation is slow; allow it more time and respond with 503 if exceeded
+///             .wrap(
+///                 Timeout::new(Duration::from_secs(60))
+///                     .status_code(StatusCode::SERVICE_UNAVAILABLE),
+///             )
+///             .route("/yearly", web::get().to(HttpResponse::Ok)),
+///     );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Timeout {
+    duration: Duration,
+    status: StatusCode,
+}
+
+impl Timeout {
+    /// Constructs timeout middleware that allows handlers to run for at most `duration`.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            status: StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+
+    /// Sets the status code of responses for timed out requests. Defaults to
+    /// `504 Gateway Timeout`.
+    pub fn status_code(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Timeout
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = TimeoutMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TimeoutMiddleware {
+            service,
+            duration: self.duration,
+            status: self.status,
+        }))
+    }
+}
+
+/// Service created by [`Timeout`] middleware.
+pub struct TimeoutMiddleware<S> {
+    service: S,
+    duration: Duration,
+    status: StatusCode,
+}
+
+impl<S, B> Service<ServiceRequest> for TimeoutMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = TimeoutFuture<S>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let existing = req.extensions().get::<SharedDeadline>().cloned();
+
+        match existing {
+            // an outer timeout is already running; override its deadline
+            Some(shared) => {
+                shared.deadline.set(Deadline {
+                    at: shared.started + self.duration,
+                    status: self.status,
+                });
+
+                TimeoutFuture {
+                    fut: self.service.call(req),
+                    sleep: None,
+                    deadline: shared.deadline,
+                }
+            }
+
+            None => {
+                let started = Instant::now();
+                let deadline = Deadline {
+                    at: started + self.duration,
+                    status: self.status,
+                };
+
+                let shared = SharedDeadline {
+                    started,
+                    deadline: Rc::new(Cell::new(deadline)),
+                };
+                req.extensions_mut().insert(shared.clone());
+
+                TimeoutFuture {
+                    fut: self.service.call(req),
+                    sleep: Some(sleep_until(deadline.at)),
+                    deadline: shared.deadline,
+                }
+            }
+        }
+    }
+}
+
+pin_project! {
+    pub struct TimeoutFuture<S>
+    where
+        S: Service<ServiceRequest>,
+    {
+        #[pin]
+        fut: S::Future,
+        #[pin]
+        sleep: Option<Sleep>,
+        deadline: Rc<Cell<Deadline>>,
+    }
+}
+
+impl<S, B> Future for TimeoutFuture<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(res) = this.fut.poll(cx) {
+            return Poll::Ready(res);
+        }
+
+        let Some(mut sleep) = this.sleep.as_pin_mut() else {
+            return Poll::Pending;
+        };
+
+        // inner timeout middleware may have replaced the deadline while the service was polled
+        let deadline = this.deadline.get();
+        if deadline.at != sleep.deadline() {
+            sleep.as_mut().reset(deadline.at);
+        }
+
+        if sleep.poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        Poll::Ready(Err(TimeoutError {
+            status: deadline.status,
+        }
+        .into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_rt::time::sleep;
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    async fn slow() -> HttpResponse {
+        sleep(Duration::from_millis(200)).await;
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn times_out_slow_handler() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Timeout::new(Duration::from_millis(50)))
+                .route("/slow", web::get().to(slow))
+                .route("/fast", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/fast").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::get().uri("/slow").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+    }
+
+    #[actix_rt::test]
+    async fn scope_overrides_app_default() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Timeout::new(Duration::from_millis(50)))
+                .service(
+                    web::scope("/long")
+                        .wrap(Timeout::new(Duration::from_millis(500)))
+                        .route("/slow", web::get().to(slow)),
+                )
+                .service(
+                    web::scope("/short")
+                        .wrap(
+                            Timeout::new(Duration::from_millis(10))
+                                .status_code(StatusCode::SERVICE_UNAVAILABLE),
+                        )
+                        .route("/slow", web::get().to(slow)),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/long/slow").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::get().uri("/short/slow").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! For middleware documentation, see [`Tracing`].
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+
+pub use actix_http::trace::{SpanId, TraceContext, TraceId};
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::ready;
+use pin_project_lite::pin_project;
+
+use crate::{
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    http::{Method, StatusCode},
+    Error, FromRequest, HttpMessage as _, HttpRequest,
+};
+
+/// A finished server span, as passed to a [`SpanExporter`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SpanData {
+    /// Span name; the request method followed by the matched resource pattern, if any.
+    pub name: String,
+
+    /// Trace context of this span.
+    pub context: TraceContext,
+
+    /// Span ID of the remote parent span, if the request carried a valid `traceparent` header.
+    pub parent_span_id: Option<SpanId>,
+
+    /// Request method.
+    pub method: Method,
+
+    /// Request path.
+    pub path: String,
+
+    /// Matched resource pattern, if any.
+    pub route: Option<String>,
+
+    /// Time at which the request was received.
+    pub start_time: SystemTime,
+
+    /// Time taken to produce a response.
+    pub duration: Duration,
+
+    /// Response status code.
+    pub status: StatusCode,
+
+    /// Description of the error that produced the response, if any.
+    pub error: Option<String>,
+}
+
+/// Destination for finished spans recorded by [`Tracing`] middleware.
+///
+/// Implementations will typically buffer spans and send them to a collector in the background.
+pub trait SpanExporter {
+    /// Exports a finished span.
+    fn export(&self, span: SpanData);
+}
+
+/// Span exporter that keeps spans in memory.
+///
+/// Useful for testing. Cloning this type produces a new handle to the same collection of spans.
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySpanExporter {
+    spans: Arc<Mutex<Vec<SpanData>>>,
+}
+
+impl InMemorySpanExporter {
+    /// Constructs a new, empty exporter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns all spans exported so far.
+    pub fn spans(&self) -> Vec<SpanData> {
+        self.spans
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Removes all exported spans.
+    pub fn clear(&self) {
+        self.spans
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clear();
+    }
+}
+
+impl SpanExporter for InMemorySpanExporter {
+    fn export(&self, span: SpanData) {
+        self.spans
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(span);
+    }
+}
+
+/// Extracts the trace context of the current request's span.
+///
+/// Extraction fails with a 500 error if the [`Tracing`] middleware is not in use.
+impl FromRequest for TraceContext {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<TraceContext>()
+                .cloned()
+                .ok_or_else(|| {
+                    log::debug!("TraceContext extractor used without Tracing middleware.");
+                    ErrorInternalServerError("Tracing is not configured")
+                }),
+        )
+    }
+}
+
+/// Middleware for distributed tracing using [W3C Trace Context] propagation.
+///
+/// A server span is created for each request. If the request carries a valid `traceparent` header,
+/// the span joins the caller's trace; otherwise, a new trace is started. The span is named after
+/// the request method and matched resource pattern (see [`HttpRequest::match_pattern()`]) and
+/// records the response status and any error. Finished spans are passed to a [`SpanExporter`] if
+/// sampled.
+///
+/// While the wrapped service is running, the span's context is the
+/// [current](TraceContext::current) trace context, which lets client middleware such as
+/// `awc::middleware::TracePropagation` propagate the trace on outgoing requests. The context is
+/// also available to handlers using the [`TraceContext`] extractor.
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     middleware::{InMemorySpanExporter, Tracing},
+///     web, App, HttpResponse,
+/// };
+///
+/// let exporter = InMemorySpanExporter::new();
+///
+/// let app = App::new()
+///     .wrap(Tracing::new(exporter.clone()))
+///     .route("/users/{id}", web::get().to(HttpResponse::Ok));
+/// ```
+///
+/// [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+#[derive(Clone)]
+pub struct Tracing {
+    exporter: Rc<dyn SpanExporter>,
+}
+
+impl Tracing {
+    /// Constructs tracing middleware that exports spans to `exporter`.
+    pub fn new(exporter: impl SpanExporter + 'static) -> Self {
+        Self {
+            exporter: Rc::new(exporter),
+        }
+    }
+}
+
+impl fmt::Debug for Tracing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracing").finish_non_exhaustive()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Tracing
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = TracingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TracingMiddleware {
+            service,
+            exporter: Rc::clone(&self.exporter),
+        }))
+    }
+}
+
+/// Service created by [`Tracing`] middleware.
+pub struct TracingMiddleware<S> {
+    service: S,
+    exporter: Rc<dyn SpanExporter>,
+}
+
+impl<S, B> Service<ServiceRequest> for TracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = TracingFuture<S>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let parent = TraceContext::from_headers(req.headers());
+
+        let context = match &parent {
+            Some(parent) => parent.child(),
+            None => TraceContext::new_root(),
+        };
+
+        req.extensions_mut().insert(context.clone());
+
+        let route = req.match_pattern();
+        let name = match &route {
+            Some(route) => format!("{} {}", req.method(), route),
+            None => req.method().to_string(),
+        };
+
+        let span = PendingSpan {
+            name,
+            parent_span_id: parent.map(|parent| parent.span_id()),
+            method: req.method().clone(),
+            path: req.path().to_owned(),
+            route,
+            start_time: SystemTime::now(),
+            started: Instant::now(),
+        };
+
+        let fut = context.scope(|| self.service.call(req));
+
+        TracingFuture {
+            fut,
+            context,
+            span: Some(span),
+            exporter: Rc::clone(&self.exporter),
+        }
+    }
+}
+
+/// Request details recorded when a span starts.
+struct PendingSpan {
+    name: String,
+    parent_span_id: Option<SpanId>,
+    method: Method,
+    path: String,
+    route: Option<String>,
+    start_time: SystemTime,
+    started: Instant,
+}
+
+impl PendingSpan {
+    fn finish(self, context: TraceContext, status: StatusCode, error: Option<String>) -> SpanData {
+        SpanData {
+            name: self.name,
+            context,
+            parent_span_id: self.parent_span_id,
+            method: self.method,
+            path: self.path,
+            route: self.route,
+            start_time: self.start_time,
+            duration: self.started.elapsed(),
+            status,
+            error,
+        }
+    }
+}
+
+pin_project! {
+    pub struct TracingFuture<S>
+    where
+        S: Service<ServiceRequest>,
+    {
+        #[pin]
+        fut: S::Future,
+        context: TraceContext,
+        span: Option<PendingSpan>,
+        exporter: Rc<dyn SpanExporter>,
+    }
+}
+
+impl<S, B> Future for TracingFuture<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let fut = this.fut;
+        let res = ready!(this.context.scope(|| fut.poll(cx)));
+
+        let (status, error) = match &res {
+            Ok(res) => (
+                res.status(),
+                res.response().error().map(ToString::to_string),
+            ),
+            Err(err) => (err.as_response_error().status_code(), Some(err.to_string())),
+        };
+
+        if let Some(span) = this.span.take() {
+            if this.context.is_sampled() {
+                this.exporter
+                    .export(span.finish(this.context.clone(), status, error));
+            }
+        }
+
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ErrorBadRequest,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[actix_rt::test]
+    async fn records_spans() {
+        let exporter = InMemorySpanExporter::new();
+
+        let app = test::init_service(
+            App::new()
+                .wrap(Tracing::new(exporter.clone()))
+                .route(
+                    "/users/{id}",
+                    web::get().to(|ctx: TraceContext| async move {
+                        // handler runs within the span's context
+                        assert_eq!(TraceContext::current(), Some(ctx));
+                        "ok"
+                    }),
+                )
+                .route(
+                    "/fail",
+                    web::get().to(|| async { Err::<HttpResponse, _>(ErrorBadRequest("nope")) }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/users/42")
+            .insert_header((
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::get().uri("/fail").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        assert!(TraceContext::current().is_none());
+
+        let spans = exporter.spans();
+        assert_eq!(spans.len(), 2);
+
+        let span = &spans[0];
+        assert_eq!(span.name, "GET /users/{id}");
+        assert_eq!(span.path, "/users/42");
+        assert_eq!(
+            span.context.trace_id().to_string(),
+            "0af7651916cd43dd8448eb211c80319c"
+        );
+        assert_eq!(span.parent_span_id.unwrap().to_string(), "b7ad6b7169203331");
+        assert_eq!(span.status, StatusCode::OK);
+        assert!(span.error.is_none());
+
+        let span = &spans[1];
+        assert_eq!(span.name, "GET /fail");
+        assert!(span.parent_span_id.is_none());
+        assert_eq!(span.status, StatusCode::BAD_REQUEST);
+        assert_eq!(span.error.as_deref(), Some("nope"));
+    }
+
+    #[actix_rt::test]
+    async fn respects_sampling_decision() {
+        let exporter = InMemorySpanExporter::new();
+
+        let app = test::init_service(
+            App::new()
+                .wrap(Tracing::new(exporter.clone()))
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header((
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00",
+            ))
+            .to_request();
+        test::call_service(&app, req).await;
+        assert!(exporter.spans().is_empty());
+
+        test::call_service(&app, TestRequest::default().to_request()).await;
+        let spans = exporter.spans();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].name, "GET");
+        assert_eq!(spans[0].status, StatusCode::NOT_FOUND);
+    }
+}
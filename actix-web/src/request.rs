@@ -18,7 +18,7 @@ use crate::{
     dev::{Extensions, Payload},
     error::UrlGenerationError,
     http::{header::HeaderMap, Method, Uri, Version},
-    info::ConnectionInfo,
+    info::{ConnectionInfo, TrustedProxies},
     rmap::ResourceMap,
     Error, FromRequest, HttpMessage,
 };
@@ -69,6 +69,26 @@ impl HttpRequest {
             }),
         }
     }
+
+    /// Constructs a copy of this request that shares its app data but none of its mutable state.
+    ///
+    /// The copy has empty request extensions, so it can be passed through services while this
+    /// request is still alive.
+    pub(crate) fn detached(&self) -> HttpRequest {
+        let mut head = Message::new();
+        *head = self.head().clone();
+
+        HttpRequest {
+            inner: Rc::new(HttpRequestInner {
+                head,
+                path: self.inner.path.clone(),
+                app_state: Rc::clone(&self.inner.app_state),
+                app_data: self.inner.app_data.clone(),
+                conn_data: self.inner.conn_data.clone(),
+                extensions: Rc::new(RefCell::new(Extensions::new())),
+            }),
+        }
+    }
 }
 
 impl HttpRequest {
@@ -282,7 +302,13 @@ impl HttpRequest {
     #[inline]
     pub fn connection_info(&self) -> Ref<'_, ConnectionInfo> {
         if !self.extensions().contains::<ConnectionInfo>() {
-            let info = ConnectionInfo::new(self.head(), self.app_config());
+            let trusted_proxies = self
+                .inner
+                .app_data
+                .first()
+                .and_then(|data| data.get::<TrustedProxies>());
+
+            let info = ConnectionInfo::new(self.head(), self.app_config(), trusted_proxies);
             self.extensions_mut().insert(info);
         }
 
@@ -0,0 +1,573 @@
+use std::{borrow::Cow, fmt, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use cookie::{time::Duration, Cookie, CookieJar, Key, SameSite};
+use futures_core::future::LocalBoxFuture;
+
+use super::{Session, SessionState, SessionStatus, SessionStore};
+use crate::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderValue},
+    Error, HttpResponse,
+};
+
+/// How the session cookie is protected from tampering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum CookieContentSecurity {
+    /// Cookie content is encrypted and authenticated; clients can neither read nor modify it.
+    #[default]
+    Private,
+
+    /// Cookie content is signed; clients can read but not modify it.
+    Signed,
+}
+
+struct Inner {
+    store: Box<dyn SessionStore>,
+    key: Key,
+    name: Cow<'static, str>,
+    path: Cow<'static, str>,
+    domain: Option<Cow<'static, str>>,
+    secure: bool,
+    http_only: bool,
+    same_site: SameSite,
+    max_age: Option<Duration>,
+    content_security: CookieContentSecurity,
+}
+
+/// Middleware for loading and persisting [`Session`] state.
+///
+/// On each request, the session cookie is verified (or decrypted) and its session key is used to
+/// load state from the [`SessionStore`]. After the wrapped service has responded, modified state
+/// is saved and a new session cookie is set. Requests whose session is left unchanged do not
+/// receive a `Set-Cookie` header.
+///
+/// Session cookies whose signature cannot be verified are ignored, starting a new, empty session.
+///
+/// # Cookie Defaults
+/// - name: `id`
+/// - path: `/`
+/// - `Secure` and `HttpOnly` attributes set
+/// - `SameSite=Lax`
+/// - no `Max-Age`, making it a browser session cookie
+/// - content is encrypted ([`CookieContentSecurity::Private`])
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     cookie::{time::Duration, Key},
+///     session::{CookieContentSecurity, InMemorySessionStore, SessionMiddleware},
+///     web, App, HttpResponse,
+/// };
+///
+/// let store = InMemorySessionStore::new();
+/// let key = Key::generate();
+///
+/// let app = App::new()
+///     .wrap(
+///         SessionMiddleware::new(store.clone(), key.clone())
+///             .cookie_name("sid")
+///             .cookie_max_age(Duration::days(7))
+///             .cookie_content_security(CookieContentSecurity::Signed),
+///     )
+///     .default_service(web::to(HttpResponse::Ok));
+/// ```
+pub struct SessionMiddleware {
+    inner: Rc<Inner>,
+}
+
+impl SessionMiddleware {
+    /// Constructs session middleware storing state in `store`, with cookies protected by `key`.
+    pub fn new(store: impl SessionStore + 'static, key: Key) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                store: Box::new(store),
+                key,
+                name: Cow::Borrowed("id"),
+                path: Cow::Borrowed("/"),
+                domain: None,
+                secure: true,
+                http_only: true,
+                same_site: SameSite::Lax,
+                max_age: None,
+                content_security: CookieContentSecurity::default(),
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner)
+            .expect("SessionMiddleware must be configured before it is cloned")
+    }
+
+    /// Sets the name of the session cookie.
+    pub fn cookie_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().name = name.into();
+        self
+    }
+
+    /// Sets the `Path` attribute of the session cookie.
+    pub fn cookie_path(mut self, path: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().path = path.into();
+        self
+    }
+
+    /// Sets the `Domain` attribute of the session cookie.
+    pub fn cookie_domain(mut self, domain: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().domain = Some(domain.into());
+        self
+    }
+
+    /// Sets whether the session cookie has the `Secure` attribute, restricting it to HTTPS.
+    pub fn cookie_secure(mut self, secure: bool) -> Self {
+        self.inner_mut().secure = secure;
+        self
+    }
+
+    /// Sets whether the session cookie has the `HttpOnly` attribute, hiding it from scripts.
+    pub fn cookie_http_only(mut self, http_only: bool) -> Self {
+        self.inner_mut().http_only = http_only;
+        self
+    }
+
+    /// Sets the `SameSite` attribute of the session cookie.
+    pub fn cookie_same_site(mut self, same_site: SameSite) -> Self {
+        self.inner_mut().same_site = same_site;
+        self
+    }
+
+    /// Sets the `Max-Age` attribute of the session cookie.
+    ///
+    /// Pass `None` to use a browser session cookie, which is removed when the browser is closed.
+    pub fn cookie_max_age(mut self, max_age: impl Into<Option<Duration>>) -> Self {
+        self.inner_mut().max_age = max_age.into();
+        self
+    }
+
+    /// Sets whether the session cookie is encrypted or only signed.
+    pub fn cookie_content_security(mut self, content_security: CookieContentSecurity) -> Self {
+        self.inner_mut().content_security = content_security;
+        self
+    }
+}
+
+impl fmt::Debug for SessionMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionMiddleware")
+            .field("name", &self.inner.name)
+            .field("path", &self.inner.path)
+            .field("domain", &self.inner.domain)
+            .field("secure", &self.inner.secure)
+            .field("http_only", &self.inner.http_only)
+            .field("same_site", &self.inner.same_site)
+            .field("max_age", &self.inner.max_age)
+            .field("content_security", &self.inner.content_security)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    /// Returns session key from a valid session cookie, if present.
+    fn session_key(&self, req: &ServiceRequest) -> Option<String> {
+        let cookie = req.cookie(&self.name)?;
+
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie);
+
+        let verified = match self.content_security {
+            CookieContentSecurity::Private => jar.private(&self.key).get(&self.name),
+            CookieContentSecurity::Signed => jar.signed(&self.key).get(&self.name),
+        };
+
+        if verified.is_none() {
+            log::debug!("Session cookie failed verification; starting a new session");
+        }
+
+        verified.map(|cookie| cookie.value().to_owned())
+    }
+
+    fn cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), value);
+
+        cookie.set_path(self.path.clone());
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+        cookie.set_same_site(self.same_site);
+
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(max_age);
+        }
+
+        cookie
+    }
+
+    fn set_session_cookie<B>(
+        &self,
+        res: &mut HttpResponse<B>,
+        session_key: String,
+    ) -> Result<(), Error> {
+        let mut jar = CookieJar::new();
+
+        match self.content_security {
+            CookieContentSecurity::Private => {
+                jar.private_mut(&self.key).add(self.cookie(session_key))
+            }
+            CookieContentSecurity::Signed => {
+                jar.signed_mut(&self.key).add(self.cookie(session_key))
+            }
+        }
+
+        // signed cookies hold the session state as is, which may contain characters that are not
+        // allowed in cookie values; request cookies are percent-decoded when parsed
+        let cookie = jar.delta().next().expect("jar contains session cookie");
+        let cookie = HeaderValue::from_str(&cookie.encoded().to_string())?;
+        res.headers_mut().append(header::SET_COOKIE, cookie);
+
+        Ok(())
+    }
+
+    fn remove_session_cookie<B>(&self, res: &mut HttpResponse<B>) -> Result<(), Error> {
+        res.add_removal_cookie(&self.cookie(String::new()))?;
+        Ok(())
+    }
+
+    /// Loads state for the request's session, returning the valid session key, if any.
+    async fn load(&self, req: &ServiceRequest) -> Result<(Option<String>, SessionState), Error> {
+        if let Some(session_key) = self.session_key(req) {
+            if let Some(state) = self.store.load(&session_key).await? {
+                return Ok((Some(session_key), state));
+            }
+        }
+
+        Ok((None, SessionState::new()))
+    }
+
+    /// Persists session changes and updates the session cookie accordingly.
+    async fn persist<B>(
+        &self,
+        res: &mut ServiceResponse<B>,
+        session_key: Option<String>,
+    ) -> Result<(), Error> {
+        let (status, state) = Session::get_changes(res);
+
+        match (status, session_key) {
+            (SessionStatus::Changed, Some(session_key)) => {
+                let session_key = self.store.update(session_key, state).await?;
+                self.set_session_cookie(res.response_mut(), session_key)?;
+            }
+
+            (SessionStatus::Changed, None) | (SessionStatus::Renewed, None) => {
+                let session_key = self.store.save(state).await?;
+                self.set_session_cookie(res.response_mut(), session_key)?;
+            }
+
+            (SessionStatus::Renewed, Some(old_session_key)) => {
+                self.store.delete(&old_session_key).await?;
+                let session_key = self.store.save(state).await?;
+                self.set_session_cookie(res.response_mut(), session_key)?;
+            }
+
+            (SessionStatus::Purged, session_key) => {
+                if let Some(session_key) = session_key {
+                    self.store.delete(&session_key).await?;
+                }
+
+                self.remove_session_cookie(res.response_mut())?;
+            }
+
+            (SessionStatus::Unchanged, _) => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SessionMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = SessionMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SessionMiddlewareService {
+            service: Rc::new(service),
+            inner: Rc::clone(&self.inner),
+        }))
+    }
+}
+
+/// Service created by [`SessionMiddleware`].
+pub struct SessionMiddlewareService<S> {
+    service: Rc<S>,
+    inner: Rc<Inner>,
+}
+
+impl<S, B> Service<ServiceRequest> for SessionMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let inner = Rc::clone(&self.inner);
+
+        Box::pin(async move {
+            let (session_key, state) = inner.load(&req).await?;
+            Session::set_session(&mut req, state);
+
+            let mut res = service.call(req).await?;
+            inner.persist(&mut res, session_key).await?;
+
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{header, StatusCode},
+        session::{CookieSessionStore, InMemorySessionStore},
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    async fn count(session: Session) -> Result<HttpResponse, Error> {
+        let count = session.get::<u32>("count")?.unwrap_or(0) + 1;
+        session.insert("count", count)?;
+        Ok(HttpResponse::Ok().body(count.to_string()))
+    }
+
+    async fn set(session: Session, val: String) -> Result<HttpResponse, Error> {
+        session.insert("val", val)?;
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    async fn get(session: Session) -> Result<HttpResponse, Error> {
+        let val = session.get::<String>("val")?.unwrap_or_default();
+        Ok(HttpResponse::Ok().body(val))
+    }
+
+    async fn renew(session: Session) -> HttpResponse {
+        session.renew();
+        HttpResponse::Ok().finish()
+    }
+
+    async fn purge(session: Session) -> HttpResponse {
+        session.purge();
+        HttpResponse::Ok().finish()
+    }
+
+    fn routes(cfg: &mut web::ServiceConfig) {
+        cfg.route("/count", web::get().to(count))
+            .route("/set", web::post().to(set))
+            .route("/get", web::get().to(get))
+            .route("/renew", web::get().to(renew))
+            .route("/purge", web::get().to(purge))
+            .route("/noop", web::get().to(HttpResponse::Ok));
+    }
+
+    fn session_cookie<B>(res: &ServiceResponse<B>) -> Option<Cookie<'static>> {
+        res.response()
+            .cookies()
+            .find(|cookie| cookie.name() == "id")
+            .map(Cookie::into_owned)
+    }
+
+    #[actix_rt::test]
+    async fn cookie_store_round_trip() {
+        for security in [
+            CookieContentSecurity::Private,
+            CookieContentSecurity::Signed,
+        ] {
+            let app = test::init_service(
+                App::new().configure(routes).wrap(
+                    SessionMiddleware::new(CookieSessionStore::default(), Key::generate())
+                        .cookie_content_security(security),
+                ),
+            )
+            .await;
+
+            let res = test::call_service(&app, TestRequest::with_uri("/count").to_request()).await;
+            assert_eq!(test::read_body(res.map_into_boxed_body()).await, "1");
+
+            let res = test::call_service(&app, TestRequest::with_uri("/count").to_request()).await;
+            let cookie = session_cookie(&res).unwrap();
+            assert!(cookie.secure().unwrap());
+            assert!(cookie.http_only().unwrap());
+            assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+
+            let req = TestRequest::with_uri("/count")
+                .cookie(cookie.clone())
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            let cookie = session_cookie(&res).unwrap();
+            assert_eq!(test::read_body(res.map_into_boxed_body()).await, "2");
+
+            if security == CookieContentSecurity::Private {
+                assert!(!cookie.value().contains("count"));
+            }
+
+            // unchanged sessions do not set cookies
+            let req = TestRequest::with_uri("/noop").cookie(cookie).to_request();
+            let res = test::call_service(&app, req).await;
+            assert!(session_cookie(&res).is_none());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn cookie_values_survive_raw_header() {
+        for security in [
+            CookieContentSecurity::Private,
+            CookieContentSecurity::Signed,
+        ] {
+            let app = test::init_service(
+                App::new().configure(routes).wrap(
+                    SessionMiddleware::new(CookieSessionStore::default(), Key::generate())
+                        .cookie_content_security(security),
+                ),
+            )
+            .await;
+
+            let val = r#"100% "ok"; a,b\c héllo"#;
+
+            let req = TestRequest::post()
+                .uri("/set")
+                .set_payload(val)
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            // send the cookie back exactly as a browser would
+            let set_cookie = res.headers().get(header::SET_COOKIE).unwrap();
+            let set_cookie = set_cookie.to_str().unwrap();
+            let pair = set_cookie.split(';').next().unwrap();
+
+            let req = TestRequest::with_uri("/get")
+                .insert_header((header::COOKIE, pair))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(test::read_body(res.map_into_boxed_body()).await, val);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn tampered_cookie_ignored() {
+        let app = test::init_service(
+            App::new().configure(routes).wrap(
+                SessionMiddleware::new(CookieSessionStore::default(), Key::generate())
+                    .cookie_content_security(CookieContentSecurity::Signed),
+            ),
+        )
+        .await;
+
+        let res = test::call_service(&app, TestRequest::with_uri("/count").to_request()).await;
+        let cookie = session_cookie(&res).unwrap();
+
+        let forged = cookie.value().replace(r#""count":"1""#, r#""count":"41""#);
+        let req = TestRequest::with_uri("/count")
+            .cookie(Cookie::new("id", forged))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res.map_into_boxed_body()).await, "1");
+
+        // cookie signed with a different key
+        let other = test::init_service(App::new().configure(routes).wrap(SessionMiddleware::new(
+            CookieSessionStore::default(),
+            Key::generate(),
+        )))
+        .await;
+        let req = TestRequest::with_uri("/count").cookie(cookie).to_request();
+        let res = test::call_service(&other, req).await;
+        assert_eq!(test::read_body(res.map_into_boxed_body()).await, "1");
+    }
+
+    #[actix_rt::test]
+    async fn server_side_renew_and_purge() {
+        let store = InMemorySessionStore::new();
+        let app = test::init_service(
+            App::new()
+                .configure(routes)
+                .wrap(SessionMiddleware::new(store.clone(), Key::generate()).cookie_name("id")),
+        )
+        .await;
+
+        let res = test::call_service(&app, TestRequest::with_uri("/count").to_request()).await;
+        let cookie = session_cookie(&res).unwrap();
+
+        let req = TestRequest::with_uri("/renew")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let renewed = session_cookie(&res).unwrap();
+        assert_ne!(cookie.value(), renewed.value());
+
+        // old session key is no longer valid
+        let req = TestRequest::with_uri("/count").cookie(cookie).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res.map_into_boxed_body()).await, "1");
+
+        // state was carried over to renewed session
+        let req = TestRequest::with_uri("/count")
+            .cookie(renewed.clone())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res.map_into_boxed_body()).await, "2");
+
+        let req = TestRequest::with_uri("/purge")
+            .cookie(renewed.clone())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let set_cookie = res.headers().get(header::SET_COOKIE).unwrap();
+        assert!(set_cookie.to_str().unwrap().contains("Max-Age=0"));
+
+        let req = TestRequest::with_uri("/count").cookie(renewed).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res.map_into_boxed_body()).await, "1");
+    }
+
+    #[actix_rt::test]
+    async fn cookie_attributes() {
+        let app = test::init_service(
+            App::new().configure(routes).wrap(
+                SessionMiddleware::new(CookieSessionStore::default(), Key::generate())
+                    .cookie_path("/app")
+                    .cookie_domain("example.com")
+                    .cookie_secure(false)
+                    .cookie_http_only(false)
+                    .cookie_same_site(SameSite::Strict)
+                    .cookie_max_age(Duration::hours(1)),
+            ),
+        )
+        .await;
+
+        let res = test::call_service(&app, TestRequest::with_uri("/count").to_request()).await;
+        let cookie = session_cookie(&res).unwrap();
+        assert_eq!(cookie.path(), Some("/app"));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert!(!cookie.secure().unwrap_or(false));
+        assert!(!cookie.http_only().unwrap_or(false));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.max_age(), Some(Duration::hours(1)));
+    }
+}
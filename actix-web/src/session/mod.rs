@@ -0,0 +1,51 @@
+//! Cookie-backed sessions.
+//!
+//! Sessions let handlers keep small amounts of per-client state between requests. The state is a
+//! map of string keys to JSON-serialized values, accessed through the [`Session`] extractor and
+//! persisted by [`SessionMiddleware`] at the end of each request.
+//!
+//! Where the state lives is decided by a [`SessionStore`]:
+//! - [`CookieSessionStore`] keeps the whole state in the session cookie itself; and
+//! - [`InMemorySessionStore`] keeps the state server-side, with only a random session key stored in
+//!   the cookie.
+//!
+//! Other stores (e.g., backed by a database) can be added by implementing [`SessionStore`].
+//!
+//! In all cases the session cookie is either signed or encrypted using a [`Key`] so that clients
+//! cannot forge or tamper with it. See [`CookieContentSecurity`].
+//!
+//! # Examples
+//! ```
+//! use actix_web::{
+//!     cookie::Key,
+//!     session::{CookieSessionStore, Session, SessionMiddleware},
+//!     web, App, Error, HttpResponse,
+//! };
+//!
+//! async fn index(session: Session) -> Result<HttpResponse, Error> {
+//!     let visits = session.get::<u32>("visits")?.unwrap_or(0) + 1;
+//!     session.insert("visits", visits)?;
+//!
+//!     Ok(HttpResponse::Ok().body(format!("Visit number {visits}")))
+//! }
+//!
+//! // in practice, load the key from configuration so sessions survive restarts
+//! let key = Key::generate();
+//!
+//! let app = App::new()
+//!     .wrap(SessionMiddleware::new(CookieSessionStore::default(), key))
+//!     .route("/", web::get().to(index));
+//! ```
+//!
+//! [`Key`]: crate::cookie::Key
+
+mod middleware;
+#[allow(clippy::module_inception)]
+mod session;
+mod store;
+
+pub use self::{
+    middleware::{CookieContentSecurity, SessionMiddleware, SessionMiddlewareService},
+    session::{Session, SessionError, SessionStatus},
+    store::{CookieSessionStore, InMemorySessionStore, SessionState, SessionStore},
+};
@@ -0,0 +1,275 @@
+use std::{
+    cell::{Ref, RefCell},
+    mem,
+    rc::Rc,
+};
+
+use actix_utils::future::{ready, Ready};
+use derive_more::derive::{Display, Error};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::error::Error as JsonError;
+
+use super::SessionState;
+use crate::{
+    dev::{Extensions, Payload, ServiceRequest, ServiceResponse},
+    Error, FromRequest, HttpMessage as _, HttpRequest, ResponseError,
+};
+
+/// Status of a [`Session`] after a handler has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum SessionStatus {
+    /// Session state was not modified.
+    #[default]
+    Unchanged,
+
+    /// Session state was modified and must be persisted.
+    Changed,
+
+    /// Session key must be replaced, persisting state under the new key.
+    Renewed,
+
+    /// Session state must be removed from the store and the session cookie deleted.
+    Purged,
+}
+
+/// Errors that can occur when reading or writing session values.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum SessionError {
+    /// Session value could not be serialized.
+    #[display("Failed to serialize session value: {}", _0)]
+    Serialize(JsonError),
+
+    /// Session value could not be deserialized into the requested type.
+    #[display("Failed to deserialize session value: {}", _0)]
+    Deserialize(JsonError),
+}
+
+impl ResponseError for SessionError {}
+
+#[derive(Debug, Default)]
+struct SessionInner {
+    state: SessionState,
+    status: SessionStatus,
+}
+
+/// Session extractor.
+///
+/// Session values are stored as JSON strings and can be read and written as any type implementing
+/// the relevant `serde` traits. Changes are persisted by [`SessionMiddleware`] once the handler
+/// has returned; without the middleware, changes are silently discarded.
+///
+/// # Examples
+/// ```
+/// use actix_web::{session::Session, HttpResponse, Result};
+///
+/// async fn login(session: Session) -> Result<HttpResponse> {
+///     // prevent session fixation by issuing a new session key on privilege change
+///     session.renew();
+///     session.insert("user_id", 42)?;
+///
+///     Ok(HttpResponse::Ok().finish())
+/// }
+///
+/// async fn logout(session: Session) -> HttpResponse {
+///     session.purge();
+///     HttpResponse::Ok().finish()
+/// }
+/// ```
+///
+/// [`SessionMiddleware`]: super::SessionMiddleware
+#[derive(Debug, Clone)]
+pub struct Session(Rc<RefCell<SessionInner>>);
+
+impl Session {
+    /// Returns the deserialized value stored under `key`, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the stored value cannot be deserialized into `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, SessionError> {
+        match self.0.borrow().state.get(key) {
+            Some(val) => serde_json::from_str(val)
+                .map(Some)
+                .map_err(SessionError::Deserialize),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns true if a value is stored under `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.borrow().state.contains_key(key)
+    }
+
+    /// Returns all raw, JSON-serialized entries in the session.
+    pub fn entries(&self) -> Ref<'_, SessionState> {
+        Ref::map(self.0.borrow(), |inner| &inner.state)
+    }
+
+    /// Returns the current status of the session.
+    pub fn status(&self) -> SessionStatus {
+        self.0.borrow().status
+    }
+
+    /// Serializes `value` and stores it under `key`, replacing any previous value.
+    ///
+    /// Has no effect once the session has been [purged](Self::purge).
+    ///
+    /// # Errors
+    /// Returns an error if `value` cannot be serialized to JSON.
+    pub fn insert<T: Serialize>(
+        &self,
+        key: impl Into<String>,
+        value: T,
+    ) -> Result<(), SessionError> {
+        let mut inner = self.0.borrow_mut();
+
+        if inner.status != SessionStatus::Purged {
+            let val = serde_json::to_string(&value).map_err(SessionError::Serialize)?;
+            inner.mark_changed();
+            inner.state.insert(key.into(), val);
+        }
+
+        Ok(())
+    }
+
+    /// Removes the value stored under `key`, returning its raw JSON representation.
+    pub fn remove(&self, key: &str) -> Option<String> {
+        let mut inner = self.0.borrow_mut();
+
+        if inner.status == SessionStatus::Purged {
+            return None;
+        }
+
+        let removed = inner.state.remove(key);
+        if removed.is_some() {
+            inner.mark_changed();
+        }
+
+        removed
+    }
+
+    /// Removes all values from the session, keeping the session key.
+    pub fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+
+        if inner.status != SessionStatus::Purged {
+            inner.mark_changed();
+            inner.state.clear();
+        }
+    }
+
+    /// Removes the session from the store and instructs the client to delete the session cookie.
+    pub fn purge(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.status = SessionStatus::Purged;
+        inner.state.clear();
+    }
+
+    /// Keeps the session state but issues a new session key.
+    ///
+    /// Call this whenever the privilege level of a session changes, such as on login, to prevent
+    /// [session fixation] attacks.
+    ///
+    /// [session fixation]: https://owasp.org/www-community/attacks/Session_fixation
+    pub fn renew(&self) {
+        let mut inner = self.0.borrow_mut();
+
+        if inner.status != SessionStatus::Purged {
+            inner.status = SessionStatus::Renewed;
+        }
+    }
+
+    /// Attaches session state to the request so it can be extracted by handlers.
+    pub(crate) fn set_session(req: &mut ServiceRequest, state: SessionState) {
+        let session = Session::get_session(&mut req.extensions_mut());
+        session.0.borrow_mut().state = state;
+    }
+
+    /// Takes the session state and status out of a response's request.
+    pub(crate) fn get_changes<B>(res: &ServiceResponse<B>) -> (SessionStatus, SessionState) {
+        match res.request().extensions().get::<Session>() {
+            Some(session) => {
+                let mut inner = session.0.borrow_mut();
+                (inner.status, mem::take(&mut inner.state))
+            }
+            None => (SessionStatus::Unchanged, SessionState::new()),
+        }
+    }
+
+    fn get_session(extensions: &mut Extensions) -> Session {
+        if let Some(session) = extensions.get::<Session>() {
+            return session.clone();
+        }
+
+        let session = Session(Rc::default());
+        extensions.insert(session.clone());
+        session
+    }
+}
+
+impl SessionInner {
+    fn mark_changed(&mut self) {
+        // renewal implies state is persisted so there's no need to downgrade it
+        if self.status == SessionStatus::Unchanged {
+            self.status = SessionStatus::Changed;
+        }
+    }
+}
+
+impl FromRequest for Session {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(Session::get_session(&mut req.extensions_mut())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[test]
+    fn typed_values() {
+        let mut req = TestRequest::default().to_srv_request();
+        Session::set_session(
+            &mut req,
+            SessionState::from([("count".to_owned(), "1".to_owned())]),
+        );
+
+        let session = Session::get_session(&mut req.extensions_mut());
+        assert_eq!(session.status(), SessionStatus::Unchanged);
+        assert_eq!(session.get::<u32>("count").unwrap(), Some(1));
+        assert!(session.get::<String>("count").is_err());
+        assert_eq!(session.get::<u32>("missing").unwrap(), None);
+
+        session.insert("count", 2).unwrap();
+        assert_eq!(session.status(), SessionStatus::Changed);
+        assert_eq!(session.entries().get("count").unwrap(), "2");
+
+        assert_eq!(session.remove("count").as_deref(), Some("2"));
+        assert!(!session.contains_key("count"));
+    }
+
+    #[test]
+    fn status_transitions() {
+        let req = TestRequest::default().to_srv_request();
+        let session = Session::get_session(&mut req.extensions_mut());
+
+        session.renew();
+        session.insert("a", "b").unwrap();
+        assert_eq!(session.status(), SessionStatus::Renewed);
+
+        session.purge();
+        assert_eq!(session.status(), SessionStatus::Purged);
+
+        // purged sessions can no longer be modified
+        session.insert("a", "b").unwrap();
+        session.renew();
+        assert_eq!(session.status(), SessionStatus::Purged);
+        assert!(session.entries().is_empty());
+    }
+}
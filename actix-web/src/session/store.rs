@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_utils::future::ready;
+use futures_core::future::LocalBoxFuture;
+use rand::{distributions::Alphanumeric, Rng as _};
+
+use crate::{error, expiry::ExpiringMap, Error};
+
+/// Session state: a map of keys to JSON-serialized values.
+pub type SessionState = HashMap<String, String>;
+
+/// Most browsers refuse to store cookies larger than 4096 bytes, including name and attributes.
+const MAX_COOKIE_STATE_LEN: usize = 4064;
+
+/// Length of randomly generated session keys.
+const SESSION_KEY_LEN: usize = 64;
+
+/// Storage backend for session state.
+///
+/// The session key returned by [`save`](Self::save) and [`update`](Self::update) is placed in the
+/// (signed or encrypted) session cookie and passed back to [`load`](Self::load) on subsequent
+/// requests. Server-side stores should generate unguessable keys; a store may also choose to
+/// encode the entire state into the key, as [`CookieSessionStore`] does.
+pub trait SessionStore {
+    /// Loads the state associated with `session_key`.
+    ///
+    /// Returns `None` if the key is unknown or the session has expired.
+    fn load(
+        &self,
+        session_key: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<SessionState>, Error>>;
+
+    /// Persists state for a new session, returning its session key.
+    fn save(&self, state: SessionState) -> LocalBoxFuture<'static, Result<String, Error>>;
+
+    /// Persists modified state for an existing session, returning its (possibly changed) key.
+    fn update(
+        &self,
+        session_key: String,
+        state: SessionState,
+    ) -> LocalBoxFuture<'static, Result<String, Error>>;
+
+    /// Deletes the state associated with `session_key`.
+    fn delete(&self, session_key: &str) -> LocalBoxFuture<'static, Result<(), Error>>;
+}
+
+/// Session store that keeps the entire session state in the session cookie.
+///
+/// No server-side storage is required, but the state is limited to roughly 4KB once serialized
+/// and sessions cannot be revoked server-side; purging a session only asks the client to delete
+/// its cookie. Use [private cookies](super::CookieContentSecurity::Private) if the state should
+/// not be readable by clients.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct CookieSessionStore;
+
+impl SessionStore for CookieSessionStore {
+    fn load(
+        &self,
+        session_key: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<SessionState>, Error>> {
+        // cookie has already been verified so a parse failure indicates an old state format
+        let state = serde_json::from_str(session_key).ok();
+        Box::pin(ready(Ok(state)))
+    }
+
+    fn save(&self, state: SessionState) -> LocalBoxFuture<'static, Result<String, Error>> {
+        let res = serde_json::to_string(&state)
+            .map_err(error::ErrorInternalServerError)
+            .and_then(|key| {
+                if key.len() > MAX_COOKIE_STATE_LEN {
+                    Err(error::ErrorInternalServerError(
+                        "Session state is too large to be stored in a cookie",
+                    ))
+                } else {
+                    Ok(key)
+                }
+            });
+
+        Box::pin(ready(res))
+    }
+
+    fn update(
+        &self,
+        _session_key: String,
+        state: SessionState,
+    ) -> LocalBoxFuture<'static, Result<String, Error>> {
+        self.save(state)
+    }
+
+    fn delete(&self, _session_key: &str) -> LocalBoxFuture<'static, Result<(), Error>> {
+        Box::pin(ready(Ok(())))
+    }
+}
+
+/// Session store that keeps session state in memory, shared between all server workers.
+///
+/// Only a random session key is stored in the session cookie. Sessions expire a fixed time after
+/// they were last modified (one day by default); requests that only read a session do not extend
+/// it. Sessions are lost when the server restarts.
+///
+/// Cloning this type produces a new handle to the same sessions; see
+/// [sharing state between workers](crate::middleware#sharing-state-between-workers).
+#[derive(Clone)]
+pub struct InMemorySessionStore {
+    sessions: Arc<Mutex<ExpiringMap<SessionState>>>,
+    ttl: Duration,
+}
+
+impl InMemorySessionStore {
+    /// Constructs a new, empty in-memory store.
+    pub fn new() -> Self {
+        Self {
+            sessions: Arc::default(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// Sets how long sessions are kept after they were last modified.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn insert(&self, session_key: String, state: SessionState) {
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap_or_else(|err| err.into_inner());
+
+        sessions.purge(now);
+        sessions.insert(session_key, state, now + self.ttl);
+    }
+}
+
+impl Default for InMemorySessionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for InMemorySessionStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemorySessionStore")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    fn load(
+        &self,
+        session_key: &str,
+    ) -> LocalBoxFuture<'static, Result<Option<SessionState>, Error>> {
+        let state = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(session_key, Instant::now())
+            .cloned();
+
+        Box::pin(ready(Ok(state)))
+    }
+
+    fn save(&self, state: SessionState) -> LocalBoxFuture<'static, Result<String, Error>> {
+        let session_key = generate_session_key();
+        self.insert(session_key.clone(), state);
+        Box::pin(ready(Ok(session_key)))
+    }
+
+    fn update(
+        &self,
+        session_key: String,
+        state: SessionState,
+    ) -> LocalBoxFuture<'static, Result<String, Error>> {
+        self.insert(session_key.clone(), state);
+        Box::pin(ready(Ok(session_key)))
+    }
+
+    fn delete(&self, session_key: &str) -> LocalBoxFuture<'static, Result<(), Error>> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(session_key);
+
+        Box::pin(ready(Ok(())))
+    }
+}
+
+/// Generates a random, URL-safe session key.
+fn generate_session_key() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SESSION_KEY_LEN)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn in_memory_sessions_expire_after_last_modified() {
+        let store = InMemorySessionStore::new().ttl(Duration::from_millis(40));
+
+        let state = SessionState::from([("a".to_owned(), "1".to_owned())]);
+        let key = store.save(state.clone()).await.unwrap();
+
+        // loading a session does not extend it
+        actix_rt::time::sleep(Duration::from_millis(25)).await;
+        assert_eq!(store.load(&key).await.unwrap(), Some(state.clone()));
+        actix_rt::time::sleep(Duration::from_millis(25)).await;
+        assert_eq!(store.load(&key).await.unwrap(), None);
+
+        // updating a session does
+        let key = store.save(state.clone()).await.unwrap();
+        actix_rt::time::sleep(Duration::from_millis(25)).await;
+        let key = store.update(key, state.clone()).await.unwrap();
+        actix_rt::time::sleep(Duration::from_millis(25)).await;
+        assert_eq!(store.load(&key).await.unwrap(), Some(state));
+
+        store.delete(&key).await.unwrap();
+        assert_eq!(store.load(&key).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,390 @@
+//! For authentication extractor documentation, see [`BasicAuth`] and [`BearerAuth`].
+
+use std::{borrow::Cow, fmt};
+
+use actix_utils::future::{ready, Ready};
+
+use crate::{
+    dev::Payload,
+    http::{
+        header::{Authorization, Header as _, WwwAuthenticate},
+        StatusCode,
+    },
+    web, FromRequest, HttpRequest, HttpResponse, ResponseError,
+};
+
+/// Error returned when a request could not be authenticated.
+///
+/// Responds with `401 Unauthorized` (or another status, see [`with_status`](Self::with_status))
+/// and a `WWW-Authenticate` header carrying the challenge.
+///
+/// # Examples
+/// ```
+/// use actix_web::{http::header::WwwAuthenticate, web::AuthenticationError, Error};
+///
+/// let err: Error = AuthenticationError::new(
+///     WwwAuthenticate::bearer().realm("api").error("invalid_token"),
+/// )
+/// .into();
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthenticationError {
+    challenge: WwwAuthenticate,
+    status: StatusCode,
+}
+
+impl AuthenticationError {
+    /// Constructs an authentication error that responds with `401 Unauthorized` and `challenge`.
+    pub fn new(challenge: WwwAuthenticate) -> Self {
+        Self {
+            challenge,
+            status: StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    /// Sets the response status code. Defaults to `401 Unauthorized`.
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Returns the challenge sent in the `WWW-Authenticate` header.
+    pub fn challenge(&self) -> &WwwAuthenticate {
+        &self.challenge
+    }
+}
+
+impl fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.challenge.get_param("error") {
+            Some(error) => write!(f, "Authentication failed: {error}"),
+            None => f.write_str("Authentication required"),
+        }
+    }
+}
+
+impl std::error::Error for AuthenticationError {}
+
+impl ResponseError for AuthenticationError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status)
+            .insert_header(self.challenge.clone())
+            .finish()
+    }
+}
+
+/// Extractor for credentials sent using HTTP Basic authentication ([RFC 7617]).
+///
+/// If the request has no `Authorization` header with valid `Basic` credentials, extraction fails
+/// with an [`AuthenticationError`] carrying a challenge for the realm configured by
+/// [`BasicAuthConfig`].
+///
+/// The credentials are not checked against anything. Use them to authenticate the user in the
+/// handler or with the [`HttpAuthentication`](crate::middleware::HttpAuthentication) middleware.
+///
+/// # Examples
+/// ```
+/// use actix_web::{get, web};
+///
+/// #[get("/")]
+/// async fn index(auth: web::BasicAuth) -> String {
+///     format!("Hello, {}!", auth.user_id())
+/// }
+/// ```
+///
+/// [RFC 7617]: https://www.rfc-editor.org/rfc/rfc7617
+#[derive(Clone)]
+pub struct BasicAuth {
+    user_id: String,
+    password: String,
+}
+
+impl BasicAuth {
+    /// Returns the user ID.
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Returns the password. May be empty.
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("user_id", &self.user_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromRequest for BasicAuth {
+    type Error = AuthenticationError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(match Authorization::parse(req) {
+            Ok(Authorization::Basic { user_id, password }) => Ok(BasicAuth { user_id, password }),
+            _ => Err(BasicAuthConfig::from_req(req).challenge()),
+        })
+    }
+}
+
+/// Configuration for the [`BasicAuth`] extractor.
+///
+/// # Examples
+/// ```
+/// use actix_web::{web, App};
+///
+/// let app = App::new().app_data(web::BasicAuthConfig::default().realm("Admin area"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BasicAuthConfig {
+    realm: Cow<'static, str>,
+}
+
+impl BasicAuthConfig {
+    /// Sets the realm sent in challenges. Defaults to `Restricted`.
+    pub fn realm(mut self, realm: impl Into<Cow<'static, str>>) -> Self {
+        self.realm = realm.into();
+        self
+    }
+
+    /// Returns an error carrying this configuration's challenge.
+    pub fn challenge(&self) -> AuthenticationError {
+        AuthenticationError::new(
+            WwwAuthenticate::basic(self.realm.clone()).param("charset", "UTF-8"),
+        )
+    }
+
+    /// Extracts the config from the request's app data, falling back to the default.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_BASIC_CONFIG)
+    }
+}
+
+const DEFAULT_BASIC_CONFIG: BasicAuthConfig = BasicAuthConfig {
+    realm: Cow::Borrowed("Restricted"),
+};
+
+impl Default for BasicAuthConfig {
+    fn default() -> Self {
+        DEFAULT_BASIC_CONFIG
+    }
+}
+
+/// Extractor for tokens sent using the `Bearer` authentication scheme ([RFC 6750]).
+///
+/// If the request has no `Authorization` header with `Bearer` credentials, extraction fails with
+/// an [`AuthenticationError`] carrying a challenge configured by [`BearerAuthConfig`]. A malformed
+/// `Bearer` header results in a `400 Bad Request` response with an `invalid_request` error code.
+///
+/// The token is not checked against anything. Use it to authenticate the user in the handler or
+/// with the [`HttpAuthentication`](crate::middleware::HttpAuthentication) middleware.
+///
+/// # Examples
+/// ```
+/// use actix_web::{get, web};
+///
+/// #[get("/")]
+/// async fn index(auth: web::BearerAuth) -> String {
+///     format!("Your token is {} bytes long", auth.token().len())
+/// }
+/// ```
+///
+/// [RFC 6750]: https://www.rfc-editor.org/rfc/rfc6750
+#[derive(Clone)]
+pub struct BearerAuth {
+    token: String,
+}
+
+impl BearerAuth {
+    /// Returns the bearer token.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl fmt::Debug for BearerAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BearerAuth").finish_non_exhaustive()
+    }
+}
+
+impl FromRequest for BearerAuth {
+    type Error = AuthenticationError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let config = BearerAuthConfig::from_req(req);
+
+        ready(match Authorization::parse(req) {
+            Ok(Authorization::Bearer(token)) => Ok(BearerAuth { token }),
+
+            // header uses the bearer scheme but is malformed
+            Err(_)
+                if req
+                    .headers()
+                    .get(Authorization::name())
+                    .and_then(|val| val.to_str().ok())
+                    .and_then(|val| val.trim_start().get(..6))
+                    .is_some_and(|scheme| scheme.eq_ignore_ascii_case("bearer")) =>
+            {
+                Err(config
+                    .error("invalid_request")
+                    .with_status(StatusCode::BAD_REQUEST))
+            }
+
+            _ => Err(config.challenge()),
+        })
+    }
+}
+
+/// Configuration for the [`BearerAuth`] extractor.
+///
+/// # Examples
+/// ```
+/// use actix_web::{web, App};
+///
+/// let app = App::new().app_data(
+///     web::BearerAuthConfig::default()
+///         .realm("api")
+///         .scope("read write"),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BearerAuthConfig {
+    realm: Option<Cow<'static, str>>,
+    scope: Option<Cow<'static, str>>,
+}
+
+impl BearerAuthConfig {
+    /// Sets the realm sent in challenges.
+    pub fn realm(mut self, realm: impl Into<Cow<'static, str>>) -> Self {
+        self.realm = Some(realm.into());
+        self
+    }
+
+    /// Sets the space-delimited list of scopes sent in challenges.
+    pub fn scope(mut self, scope: impl Into<Cow<'static, str>>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Returns an error carrying this configuration's challenge, without an error code.
+    ///
+    /// This is the appropriate response to requests that did not attempt to authenticate.
+    pub fn challenge(&self) -> AuthenticationError {
+        let mut challenge = WwwAuthenticate::bearer();
+
+        if let Some(realm) = &self.realm {
+            challenge = challenge.realm(realm.clone());
+        }
+
+        if let Some(scope) = &self.scope {
+            challenge = challenge.param("scope", scope.clone());
+        }
+
+        AuthenticationError::new(challenge)
+    }
+
+    /// Returns an error carrying this configuration's challenge with the given error code, such as
+    /// `invalid_token` or `insufficient_scope`.
+    pub fn error(&self, error: impl Into<String>) -> AuthenticationError {
+        let mut err = self.challenge();
+        err.challenge = err.challenge.error(error);
+        err
+    }
+
+    /// Extracts the config from the request's app data, falling back to the default.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_BEARER_CONFIG)
+    }
+}
+
+const DEFAULT_BEARER_CONFIG: BearerAuthConfig = BearerAuthConfig {
+    realm: None,
+    scope: None,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::header::{self, HeaderValue},
+        test::TestRequest,
+    };
+
+    #[actix_rt::test]
+    async fn basic_auth() {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header(Authorization::basic("user", "pass"))
+            .to_http_parts();
+        let auth = BasicAuth::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(auth.user_id(), "user");
+        assert_eq!(auth.password(), "pass");
+
+        let (req, mut pl) = TestRequest::default()
+            .insert_header(Authorization::bearer("token"))
+            .app_data(BasicAuthConfig::default().realm("Admin \"area\""))
+            .to_http_parts();
+        let err = BasicAuth::from_request(&req, &mut pl).await.unwrap_err();
+        let res = err.error_response();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Basic realm=\"Admin \\\"area\\\"\", charset=\"UTF-8\""
+        );
+    }
+
+    #[actix_rt::test]
+    async fn bearer_auth() {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header(Authorization::bearer("token"))
+            .to_http_parts();
+        let auth = BearerAuth::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(auth.token(), "token");
+
+        let (req, mut pl) = TestRequest::default()
+            .app_data(web::Data::new(
+                BearerAuthConfig::default().realm("api").scope("read"),
+            ))
+            .to_http_parts();
+        let res = BearerAuth::from_request(&req, &mut pl)
+            .await
+            .unwrap_err()
+            .error_response();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer realm=\"api\", scope=\"read\""
+        );
+
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((
+                header::AUTHORIZATION,
+                HeaderValue::from_static("bearer a b"),
+            ))
+            .to_http_parts();
+        let res = BearerAuth::from_request(&req, &mut pl)
+            .await
+            .unwrap_err()
+            .error_response();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer error=\"invalid_request\""
+        );
+    }
+}
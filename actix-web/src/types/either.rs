@@ -14,6 +14,7 @@ use pin_project_lite::pin_project;
 use crate::{
     body::EitherBody,
     dev,
+    helpers::payload_from_bytes,
     web::{Form, Json},
     Error, FromRequest, HttpRequest, HttpResponse, Responder,
 };
@@ -276,12 +277,6 @@ where
     }
 }
 
-fn payload_from_bytes(bytes: Bytes) -> dev::Payload {
-    let (_, mut h1_payload) = actix_http::h1::Payload::create(true);
-    h1_payload.unread_data(bytes);
-    dev::Payload::from(h1_payload)
-}
-
 #[cfg(test)]
 mod tests {
     use serde::{Deserialize, Serialize};
@@ -17,12 +17,12 @@ use futures_core::{future::LocalBoxFuture, ready};
 use futures_util::{FutureExt as _, StreamExt as _};
 use serde::{de::DeserializeOwned, Serialize};
 
-#[cfg(feature = "__compress")]
-use crate::dev::Decompress;
 use crate::{
     body::EitherBody, error::UrlencodedError, extract::FromRequest, http::header::CONTENT_LENGTH,
     web, Error, HttpMessage, HttpRequest, HttpResponse, Responder,
 };
+#[cfg(feature = "__compress")]
+use crate::{dev::Decompress, types::payload::resolve_decompress_config, web::DecompressConfig};
 
 /// URL encoded payload extractor and responder.
 ///
@@ -130,10 +130,17 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        let FormConfig { limit, err_handler } = FormConfig::from_req(req).clone();
+        let config = FormConfig::from_req(req);
+        let limit = config.limit;
+        let err_handler = config.err_handler.clone();
+
+        let fut = UrlEncoded::new(req, payload).limit(limit);
+
+        #[cfg(feature = "__compress")]
+        let fut = fut.decompress_config(resolve_decompress_config(req, config.decompress, limit));
 
         FormExtractFut {
-            fut: UrlEncoded::new(req, payload).limit(limit),
+            fut,
             req: req.clone(),
             err_handler,
         }
@@ -224,6 +231,8 @@ impl<T: Serialize> Responder for Form<T> {
 pub struct FormConfig {
     limit: usize,
     err_handler: FormErrHandler,
+    #[cfg(feature = "__compress")]
+    decompress: Option<DecompressConfig>,
 }
 
 impl FormConfig {
@@ -242,6 +251,16 @@ impl FormConfig {
         self
     }
 
+    /// Set limits for decompressing compressed payloads.
+    ///
+    /// Overrides any [`DecompressConfig`] set as app data. By default, only the payload size limit
+    /// is applied, which is checked against the decompressed payload.
+    #[cfg(feature = "__compress")]
+    pub fn decompress_config(mut self, config: DecompressConfig) -> Self {
+        self.decompress = Some(config);
+        self
+    }
+
     /// Extract payload config from app data.
     ///
     /// Checks both `T` and `Data<T>`, in that order, and falls back to the default payload config.
@@ -256,6 +275,8 @@ impl FormConfig {
 const DEFAULT_CONFIG: FormConfig = FormConfig {
     limit: 16_384, // 2^14 bytes (~16kB)
     err_handler: None,
+    #[cfg(feature = "__compress")]
+    decompress: None,
 };
 
 impl Default for FormConfig {
@@ -346,6 +367,16 @@ impl<T> UrlEncoded<T> {
         self.limit = limit;
         self
     }
+
+    /// Set limits for decompressing the payload, if it is compressed.
+    ///
+    /// By default, no limits are applied during decompression, although the overall size limit
+    /// still applies to the decompressed payload.
+    #[cfg(feature = "__compress")]
+    pub fn decompress_config(mut self, config: DecompressConfig) -> Self {
+        self.stream = self.stream.map(|stream| stream.with_config(config));
+        self
+    }
 }
 
 impl<T> Future for UrlEncoded<T>
@@ -15,8 +15,6 @@ use bytes::BytesMut;
 use futures_core::{ready, Stream as _};
 use serde::{de::DeserializeOwned, Serialize};
 
-#[cfg(feature = "__compress")]
-use crate::dev::Decompress;
 use crate::{
     body::EitherBody,
     error::{Error, JsonPayloadError},
@@ -25,6 +23,8 @@ use crate::{
     request::HttpRequest,
     web, HttpMessage, HttpResponse, Responder,
 };
+#[cfg(feature = "__compress")]
+use crate::{dev::Decompress, types::payload::resolve_decompress_config, web::DecompressConfig};
 
 /// JSON extractor and responder.
 ///
@@ -149,9 +149,14 @@ impl<T: DeserializeOwned> FromRequest for Json<T> {
         let ctype_fn = config.content_type.as_deref();
         let err_handler = config.err_handler.clone();
 
+        let fut = JsonBody::new(req, payload, ctype_fn, ctype_required).limit(limit);
+
+        #[cfg(feature = "__compress")]
+        let fut = fut.decompress_config(resolve_decompress_config(req, config.decompress, limit));
+
         JsonExtractFut {
             req: Some(req.clone()),
-            fut: JsonBody::new(req, payload, ctype_fn, ctype_required).limit(limit),
+            fut,
             err_handler,
         }
     }
@@ -234,6 +239,8 @@ pub struct JsonConfig {
     err_handler: JsonErrorHandler,
     content_type: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
     content_type_required: bool,
+    #[cfg(feature = "__compress")]
+    decompress: Option<DecompressConfig>,
 }
 
 impl JsonConfig {
@@ -267,6 +274,16 @@ impl JsonConfig {
         self
     }
 
+    /// Set limits for decompressing compressed payloads.
+    ///
+    /// Overrides any [`DecompressConfig`] set as app data. By default, only the payload size limit
+    /// is applied, which is checked against the decompressed payload.
+    #[cfg(feature = "__compress")]
+    pub fn decompress_config(mut self, config: DecompressConfig) -> Self {
+        self.decompress = Some(config);
+        self
+    }
+
     /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
     /// back to the default payload config.
     fn from_req(req: &HttpRequest) -> &Self {
@@ -284,6 +301,8 @@ const DEFAULT_CONFIG: JsonConfig = JsonConfig {
     err_handler: None,
     content_type: None,
     content_type_required: true,
+    #[cfg(feature = "__compress")]
+    decompress: None,
 };
 
 impl Default for JsonConfig {
@@ -401,6 +420,30 @@ impl<T: DeserializeOwned> JsonBody<T> {
             JsonBody::Error(err) => JsonBody::Error(err),
         }
     }
+
+    /// Set limits for decompressing the payload, if it is compressed.
+    ///
+    /// By default, no limits are applied during decompression, although the overall size limit
+    /// still applies to the decompressed payload.
+    #[cfg(feature = "__compress")]
+    pub fn decompress_config(self, config: DecompressConfig) -> Self {
+        match self {
+            JsonBody::Body {
+                limit,
+                length,
+                payload,
+                buf,
+                ..
+            } => JsonBody::Body {
+                limit,
+                length,
+                payload: payload.with_config(config),
+                buf,
+                _res: PhantomData,
+            },
+            JsonBody::Error(err) => JsonBody::Error(err),
+        }
+    }
 }
 
 impl<T: DeserializeOwned> Future for JsonBody<T> {
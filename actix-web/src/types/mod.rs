@@ -1,5 +1,6 @@
 //! Common extractors and responders.
 
+mod auth;
 mod either;
 mod form;
 mod header;
@@ -9,8 +10,10 @@ mod path;
 mod payload;
 mod query;
 mod readlines;
+mod request_id;
 
 pub use self::{
+    auth::{AuthenticationError, BasicAuth, BasicAuthConfig, BearerAuth, BearerAuthConfig},
     either::Either,
     form::{Form, FormConfig, UrlEncoded},
     header::Header,
@@ -20,4 +23,7 @@ pub use self::{
     payload::{Payload, PayloadConfig},
     query::{Query, QueryConfig},
     readlines::Readlines,
+    request_id::RequestId,
 };
+#[cfg(feature = "__compress")]
+pub use self::payload::DecompressConfig;
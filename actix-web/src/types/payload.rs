@@ -8,6 +8,8 @@ use std::{
     task::{Context, Poll},
 };
 
+#[cfg(feature = "__compress")]
+pub use actix_http::encoding::DecoderConfig as DecompressConfig;
 use actix_http::error::PayloadError;
 use actix_utils::future::{ready, Either, Ready};
 use bytes::{Bytes, BytesMut};
@@ -168,9 +170,12 @@ impl FromRequest for Bytes {
             return Either::right(ready(Err(err)));
         }
 
-        Either::left(BytesExtractFut {
-            body_fut: HttpMessageBody::new(req, payload).limit(cfg.limit),
-        })
+        let body_fut = HttpMessageBody::new(req, payload).limit(cfg.limit);
+
+        #[cfg(feature = "__compress")]
+        let body_fut = body_fut.decompress_config(cfg.resolve_decompress_config(req));
+
+        Either::left(BytesExtractFut { body_fut })
     }
 }
 
@@ -223,6 +228,9 @@ impl FromRequest for String {
         let limit = cfg.limit;
         let body_fut = HttpMessageBody::new(req, payload).limit(limit);
 
+        #[cfg(feature = "__compress")]
+        let body_fut = body_fut.decompress_config(cfg.resolve_decompress_config(req));
+
         Either::left(StringExtractFut { body_fut, encoding })
     }
 }
@@ -268,12 +276,18 @@ fn bytes_to_string(body: Bytes, encoding: &'static Encoding) -> Result<String, E
 ///
 /// By default, the payload size limit is 256kB and there is no mime type condition.
 ///
+/// The size limit applies to the decompressed payload. Decompression stops as soon as the limit is
+/// exceeded, so compressed payloads can not expand past it; see
+/// [`decompress_config`](Self::decompress_config) for further limits.
+///
 /// To use this, add an instance of it to your [`app`](crate::App), [`scope`](crate::Scope)
 /// or [`resource`](crate::Resource) through the associated `.app_data()` method.
 #[derive(Clone)]
 pub struct PayloadConfig {
     limit: usize,
     mimetype: Option<Mime>,
+    #[cfg(feature = "__compress")]
+    decompress: Option<DecompressConfig>,
 }
 
 impl PayloadConfig {
@@ -297,6 +311,21 @@ impl PayloadConfig {
         self
     }
 
+    /// Set limits for decompressing compressed payloads.
+    ///
+    /// Overrides any [`DecompressConfig`] set as app data. By default, only the payload size limit
+    /// is applied.
+    #[cfg(feature = "__compress")]
+    pub fn decompress_config(mut self, config: DecompressConfig) -> Self {
+        self.decompress = Some(config);
+        self
+    }
+
+    #[cfg(feature = "__compress")]
+    fn resolve_decompress_config(&self, req: &HttpRequest) -> DecompressConfig {
+        resolve_decompress_config(req, self.decompress, self.limit)
+    }
+
     fn check_mimetype(&self, req: &HttpRequest) -> Result<(), Error> {
         // check content-type
         if let Some(ref mt) = self.mimetype {
@@ -333,8 +362,31 @@ const DEFAULT_CONFIG_LIMIT: usize = 262_144; // 2^18 bytes (~256kB)
 const DEFAULT_CONFIG: PayloadConfig = PayloadConfig {
     limit: DEFAULT_CONFIG_LIMIT,
     mimetype: None,
+    #[cfg(feature = "__compress")]
+    decompress: None,
 };
 
+/// Resolves decompression limits for a body extractor.
+///
+/// Limits set on the extractor's config take precedence over a [`DecompressConfig`] set as app
+/// data. Decompressed output is always capped at the extractor's size `limit`, since larger
+/// payloads would be rejected anyway.
+#[cfg(feature = "__compress")]
+pub(crate) fn resolve_decompress_config(
+    req: &HttpRequest,
+    config: Option<DecompressConfig>,
+    limit: usize,
+) -> DecompressConfig {
+    let config = config
+        .or_else(|| req.app_data::<DecompressConfig>().copied())
+        .unwrap_or_default();
+
+    let max_size = config
+        .get_max_size()
+        .map_or(limit, |max_size| max_size.min(limit));
+    config.max_size(max_size)
+}
+
 impl Default for PayloadConfig {
     fn default() -> Self {
         DEFAULT_CONFIG
@@ -409,6 +461,16 @@ impl HttpMessageBody {
         self.limit = limit;
         self
     }
+
+    /// Set limits for decompressing the payload, if it is compressed.
+    ///
+    /// By default, no limits are applied during decompression, although the overall size limit
+    /// still applies to the decompressed payload.
+    #[cfg(feature = "__compress")]
+    pub fn decompress_config(mut self, config: DecompressConfig) -> Self {
+        self.stream = self.stream.with_config(config);
+        self
+    }
 }
 
 impl Future for HttpMessageBody {
@@ -607,6 +669,69 @@ mod tests {
         assert_eq!(s, "hello=world");
     }
 
+    #[cfg(feature = "compress-gzip")]
+    #[actix_rt::test]
+    async fn test_decompression_limits() {
+        use std::io::Write as _;
+
+        use crate::{test::TestRequest, web, App};
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&[b'a'; 1024 * 1024]).unwrap();
+        let bomb = Bytes::from(encoder.finish().unwrap());
+
+        async fn len(body: Bytes) -> String {
+            body.len().to_string()
+        }
+
+        let srv = init_service(
+            App::new()
+                .app_data(PayloadConfig::new(4 * 1024 * 1024))
+                .route("/", web::post().to(len))
+                .service(
+                    web::resource("/ratio")
+                        .app_data(DecompressConfig::new().max_ratio(100))
+                        .route(web::post().to(len)),
+                )
+                .service(
+                    web::resource("/override")
+                        .app_data(DecompressConfig::new().max_ratio(100))
+                        .app_data(
+                            PayloadConfig::new(4 * 1024 * 1024)
+                                .decompress_config(DecompressConfig::new()),
+                        )
+                        .route(web::post().to(len)),
+                )
+                .service(
+                    web::resource("/small")
+                        .app_data(PayloadConfig::new(1024))
+                        .route(web::post().to(len)),
+                ),
+        )
+        .await;
+
+        let req = |path| {
+            TestRequest::post()
+                .uri(path)
+                .insert_header((header::CONTENT_ENCODING, "gzip"))
+                .set_payload(bomb.clone())
+                .to_request()
+        };
+
+        let res = call_service(&srv, req("/")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "1048576");
+
+        let res = call_service(&srv, req("/ratio")).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let res = call_service(&srv, req("/override")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = call_service(&srv, req("/small")).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
     #[actix_rt::test]
     async fn test_message_body() {
         let (req, mut pl) = TestRequest::default()
@@ -0,0 +1,61 @@
+//! For request ID extractor documentation, see [`RequestId`].
+
+use std::{fmt, rc::Rc};
+
+use actix_utils::future::{ready, Ready};
+
+use crate::{
+    dev::Payload, error::ErrorInternalServerError, Error, FromRequest, HttpMessage as _,
+    HttpRequest,
+};
+
+/// Request ID extractor.
+///
+/// Holds the ID assigned to the current request by the [`RequestId`] middleware, either taken from
+/// the incoming request or newly generated.
+///
+/// Extraction fails with a 500 error if the middleware is not in use.
+///
+/// # Examples
+/// ```
+/// use actix_web::{get, web};
+///
+/// #[get("/")]
+/// async fn index(req_id: web::RequestId) -> String {
+///     format!("Your request ID is {req_id}")
+/// }
+/// ```
+///
+/// [`RequestId`]: crate::middleware::RequestId
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(Rc<str>);
+
+impl RequestId {
+    pub(crate) fn new(id: impl Into<Rc<str>>) -> Self {
+        Self(id.into())
+    }
+
+    /// Returns the request ID as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<RequestId>().cloned().ok_or_else(|| {
+            log::debug!("RequestId extractor used without RequestId middleware.");
+            ErrorInternalServerError("Request ID is not configured")
+        }))
+    }
+}
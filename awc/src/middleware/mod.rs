@@ -1,10 +1,11 @@
 mod redirect;
+mod trace;
 
 use std::marker::PhantomData;
 
 use actix_service::Service;
 
-pub use self::redirect::Redirect;
+pub use self::{redirect::Redirect, trace::TracePropagation};
 
 /// Trait for transform a type to another one.
 /// Both the input and output type should impl [actix_service::Service] trait.
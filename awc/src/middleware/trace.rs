@@ -0,0 +1,134 @@
+use actix_http::{header::HeaderMap, trace::TraceContext, RequestHeadType};
+use actix_service::Service;
+
+use super::Transform;
+use crate::connect::ConnectRequest;
+
+/// Middleware that propagates the current trace on outgoing requests.
+///
+/// If a [current trace context](TraceContext::current) is set when a request is sent, its
+/// `traceparent` and `tracestate` headers are added to the request. When requests are sent from
+/// within handlers wrapped by Actix Web's `Tracing` middleware, this links the downstream service's
+/// spans to the handler's span.
+///
+/// Requests that already have a `traceparent` header are left unchanged.
+///
+/// # Examples
+/// ```
+/// use awc::{middleware::TracePropagation, ClientBuilder};
+///
+/// let client = ClientBuilder::new().wrap(TracePropagation).finish();
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracePropagation;
+
+impl<S> Transform<S, ConnectRequest> for TracePropagation
+where
+    S: Service<ConnectRequest>,
+{
+    type Transform = TracePropagationService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        TracePropagationService { connector: service }
+    }
+}
+
+pub struct TracePropagationService<S> {
+    connector: S,
+}
+
+impl<S> Service<ConnectRequest> for TracePropagationService<S>
+where
+    S: Service<ConnectRequest>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    actix_service::forward_ready!(connector);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        let req = match TraceContext::current() {
+            Some(ctx) => inject(&ctx, req),
+            None => req,
+        };
+
+        self.connector.call(req)
+    }
+}
+
+fn inject(ctx: &TraceContext, req: ConnectRequest) -> ConnectRequest {
+    fn inject_into(ctx: &TraceContext, headers: &mut HeaderMap) {
+        if !headers.contains_key(actix_http::trace::TRACEPARENT) {
+            ctx.inject(headers);
+        }
+    }
+
+    match req {
+        ConnectRequest::Client(RequestHeadType::Owned(mut head), body, addr) => {
+            inject_into(ctx, head.headers_mut());
+            ConnectRequest::Client(RequestHeadType::Owned(head), body, addr)
+        }
+
+        ConnectRequest::Client(RequestHeadType::Rc(head, extra_headers), body, addr) => {
+            if head.headers().contains_key(actix_http::trace::TRACEPARENT) {
+                return ConnectRequest::Client(
+                    RequestHeadType::Rc(head, extra_headers),
+                    body,
+                    addr,
+                );
+            }
+
+            let mut extra_headers = extra_headers.unwrap_or_default();
+            inject_into(ctx, &mut extra_headers);
+
+            ConnectRequest::Client(RequestHeadType::Rc(head, Some(extra_headers)), body, addr)
+        }
+
+        ConnectRequest::Tunnel(mut head, addr) => {
+            inject_into(ctx, head.headers_mut());
+            ConnectRequest::Tunnel(head, addr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{web, App, HttpRequest};
+
+    use super::*;
+    use crate::ClientBuilder;
+
+    #[actix_rt::test]
+    async fn propagates_current_context() {
+        let client = ClientBuilder::new().wrap(TracePropagation).finish();
+
+        let srv = actix_test::start(|| {
+            App::new().default_service(web::to(|req: HttpRequest| async move {
+                req.headers()
+                    .get("traceparent")
+                    .map(|val| val.to_str().unwrap().to_owned())
+                    .unwrap_or_default()
+            }))
+        });
+
+        let mut res = client.get(srv.url("/")).send().await.unwrap();
+        assert!(res.body().await.unwrap().is_empty());
+
+        let ctx = TraceContext::new_root();
+        let send = ctx.scope(|| client.get(srv.url("/")).send());
+        let mut res = send.await.unwrap();
+        assert_eq!(res.body().await.unwrap(), ctx.to_string());
+
+        // explicitly set headers are not overridden
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let send = ctx.scope(|| {
+            client
+                .get(srv.url("/"))
+                .insert_header(("traceparent", traceparent))
+                .send()
+        });
+        let mut res = send.await.unwrap();
+        assert_eq!(res.body().await.unwrap(), traceparent);
+    }
+}